@@ -0,0 +1,58 @@
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use libmactime2::{BodyfileDecoder, Consumer, Joinable, Provider, RunOptions};
+use std::sync::mpsc;
+
+const LINES: usize = 100_000;
+
+/// generates bodyfile lines which look like the output of `fls`
+fn generate_bodyfile(lines: usize) -> Vec<String> {
+    (0..lines)
+        .map(|i| {
+            format!(
+                "0|/Windows/System32/DriverStore/FileRepository/driver_{}/file_{}.sys|{}-128-1|r/rrwxrwxrwx|0|0|{}|{}|{}|{}|{}",
+                i % 1000,
+                i,
+                i,
+                i * 17 % 100_000,
+                1_600_000_000 + i,
+                1_600_000_100 + i,
+                1_600_000_200 + i,
+                1_600_000_300 + i
+            )
+        })
+        .collect()
+}
+
+fn decode(lines: &[String], threads: usize) {
+    let (tx, rx) = mpsc::channel();
+    let mut decoder = BodyfileDecoder::with_receiver(
+        rx,
+        RunOptions {
+            threads,
+            ..Default::default()
+        },
+    );
+    let receiver = decoder.get_receiver();
+    for line in lines {
+        tx.send(line.clone()).unwrap();
+    }
+    drop(tx);
+    assert_eq!(receiver.iter().count(), lines.len());
+    decoder.join().unwrap().unwrap();
+}
+
+fn bench_decoder(c: &mut Criterion) {
+    let lines = generate_bodyfile(LINES);
+    let mut group = c.benchmark_group("bodyfile_decoder");
+    group.throughput(Throughput::Elements(LINES as u64));
+    group.sample_size(10);
+    for threads in [1, 2, 4, 8] {
+        group.bench_with_input(BenchmarkId::from_parameter(threads), &threads, |b, threads| {
+            b.iter(|| decode(&lines, *threads))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_decoder);
+criterion_main!(benches);
@@ -1,49 +1,59 @@
-use crate::{Filter, Joinable, RunOptions, Provider, Consumer};
-use std::sync::mpsc::{self, Sender, Receiver};
+use crate::{Filter, Joinable, MactimeError, RunOptions, Provider, Consumer, CHANNEL_CAPACITY};
+use std::sync::mpsc::{self, Receiver, SyncSender};
 use std::thread::{JoinHandle};
-use bodyfile::Bodyfile3Line;
+use crate::{dst_policy, AmbiguousPolicy, BodyfileLine, GapPolicy, MACBFlags};
+use chrono::{LocalResult, TimeZone};
+use chrono_tz::Tz;
 use std::convert::TryFrom;
 
+/// parses bodyfile lines. Invalid lines are skipped with a warning, but in
+/// strict mode, the decoder stops at the first invalid line and returns an
+/// error from [`Joinable::join`]. It also marks [`RunOptions::report`] as
+/// aborted, so that the outputs do not write the lines which have been
+/// decoded so far as if they were a complete timeline.
 pub struct BodyfileDecoder {
-    worker: Option<JoinHandle<()>>,
-    rx: Option<Receiver<Bodyfile3Line>>,
+    worker: Option<JoinHandle<Result<(), MactimeError>>>,
+    rx: Option<Receiver<BodyfileLine>>,
 }
 
-impl Filter<String, Bodyfile3Line, ()> for BodyfileDecoder {
-    fn worker(reader: Receiver<String>, tx: Sender<Bodyfile3Line>, options: RunOptions) {
+/// number of lines which are parsed together by one worker thread
+const CHUNK_SIZE: usize = 1024;
+
+/// number of chunks which may be queued for every worker thread
+const CHUNKS_PER_WORKER: usize = 4;
+
+/// a chunk of lines, together with the line number of its first line
+type Chunk = (usize, Vec<String>);
+
+impl Filter<String, BodyfileLine, Result<(), MactimeError>> for BodyfileDecoder {
+    fn worker(reader: Receiver<String>, tx: SyncSender<BodyfileLine>, options: RunOptions) -> Result<(), MactimeError> {
+        if options.threads > 1 {
+            return Self::parallel_worker(reader, tx, options);
+        }
+
+        let mut line_number = 0;
         loop {
-            let mut line = match reader.recv() {
+            let line = match reader.recv() {
                 Err(_) => {break;}
                 Ok(l) => l
             };
+            line_number += 1;
 
-            if line.starts_with('#') { continue; }
-            Self::trim_newline(&mut line);
-
-            let bf_line = match Bodyfile3Line::try_from(line.as_ref()) {
-                Err(e) => {
-                    if options.strict_mode {
-                        log::warn!("bodyfile parser error: {}", e);
-                        panic!("failed while parsing: {:?}", line);
-                    } else {
-                        log::warn!("bodyfile parser error: {}", e);
-                        #[cfg(debug_assertions)]
-                        log::warn!("failed line was: {:?}", line);
-                    }
-                    continue;
-                }
-                Ok(l) => l
+            let bf_line = match Self::parse_line(line, line_number, &options)? {
+                None => continue,
+                Some(l) => l
             };
 
             if tx.send(bf_line).is_err() {
                 break;
             }
         }
+        Ok(())
     }
 }
 
-impl Provider<Bodyfile3Line, ()> for BodyfileDecoder {
-    fn get_receiver(&mut self) -> Receiver<Bodyfile3Line> {
+impl Provider<BodyfileLine, Result<(), MactimeError>> for BodyfileDecoder {
+    fn get_receiver(&mut self) -> Receiver<BodyfileLine> {
         self.rx.take().unwrap()
     }
 
@@ -51,7 +61,7 @@ impl Provider<Bodyfile3Line, ()> for BodyfileDecoder {
 
 impl Consumer<String> for BodyfileDecoder {
     fn with_receiver(reader: Receiver<String>, options: RunOptions) -> Self {
-        let (tx, rx): (Sender<Bodyfile3Line>, Receiver<Bodyfile3Line>) = mpsc::channel();
+        let (tx, rx): (SyncSender<BodyfileLine>, Receiver<BodyfileLine>) = mpsc::sync_channel(CHANNEL_CAPACITY);
         Self {
             worker: Some(std::thread::spawn(move || {
                 Self::worker(reader, tx, options)
@@ -62,6 +72,167 @@ impl Consumer<String> for BodyfileDecoder {
 }
 
 impl BodyfileDecoder {
+    /// parses a single line, where `line_number` is the number of this line
+    /// in the input, and corrects its timestamps by `options.skew`. Comments
+    /// and invalid lines result in `None`, but in strict mode, invalid lines
+    /// result in an error. Invalid lines are recorded in `options.report` in
+    /// either case, and errors mark `options.report` as aborted.
+    pub(crate) fn parse_line(line: String, line_number: usize, options: &RunOptions) -> Result<Option<BodyfileLine>, MactimeError> {
+        let result = Self::decode_line(line, line_number, options);
+        if result.is_err() {
+            options.report.abort();
+        }
+        result
+    }
+
+    fn decode_line(mut line: String, line_number: usize, options: &RunOptions) -> Result<Option<BodyfileLine>, MactimeError> {
+        options.report.count_line();
+        if line.starts_with('#') { return Ok(None); }
+        Self::trim_newline(&mut line);
+
+        match BodyfileLine::try_from(line.as_ref()) {
+            Err(e) => {
+                options.report.reject(line_number, &line, &e)?;
+                if options.strict_mode {
+                    return Err(MactimeError::ParseError { line: line_number, message: e.to_string() });
+                }
+                log::warn!("bodyfile parser error in line {}: {}", line_number, e);
+                #[cfg(debug_assertions)]
+                log::warn!("failed line was: {:?}", line);
+                Ok(None)
+            }
+            Ok(l) => {
+                let l = match options.skew {
+                    Some(skew) => l.with_skew(skew),
+                    None => l,
+                };
+                if let Err(why) = Self::check_timestamps(&l, line_number, options) {
+                    options.report.reject(line_number, &line, &why)?;
+                    return Err(why);
+                }
+                options.report.count_parsed(&l);
+                Ok(Some(l))
+            }
+        }
+    }
+
+    /// counts all timestamps of a line which are affected by a DST transition
+    /// in the source timezone, and makes sure that they can be converted
+    /// into UTC according to `options.dst_policy`. In strict mode, all
+    /// timestamps must exist in the source timezone, unless another gap
+    /// policy has been chosen.
+    fn check_timestamps(line: &BodyfileLine, line_number: usize, options: &RunOptions) -> Result<(), MactimeError> {
+        let src_zone = options.src_zone;
+        if src_zone == Tz::UTC {
+            return Ok(());
+        }
+        for (timestamp, _) in MACBFlags::of_line(line) {
+            let local = match dst_policy::local_datetime(&timestamp) {
+                Some(local) => src_zone.from_local_datetime(&local),
+                None => LocalResult::None,
+            };
+            let timestamp = timestamp.secs();
+            match local {
+                LocalResult::Single(_) => (),
+                LocalResult::Ambiguous(_, _) => {
+                    options.report.count_ambiguous();
+                    if options.dst_policy.ambiguous == AmbiguousPolicy::Error {
+                        return Err(MactimeError::AmbiguousTimestamp { line: line_number, timestamp, zone: src_zone });
+                    }
+                }
+                LocalResult::None => {
+                    options.report.count_nonexistent();
+                    if options.dst_policy.gap_policy(options.strict_mode) == GapPolicy::Error {
+                        return Err(MactimeError::TimezoneError { line: line_number, timestamp, zone: src_zone });
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// parses chunks of lines using `options.threads` worker threads. The
+    /// chunks are distributed to the workers in turn, and their results are
+    /// collected in the same order, so that the order of lines is kept.
+    ///
+    /// If a worker fails, it passes the error to the collector instead of a
+    /// result. So the collector reports the error of the first invalid line,
+    /// just like in single threaded mode.
+    fn parallel_worker(reader: Receiver<String>, tx: SyncSender<BodyfileLine>, options: RunOptions) -> Result<(), MactimeError> {
+        let mut chunk_senders = Vec::with_capacity(options.threads);
+        let mut result_receivers = Vec::with_capacity(options.threads);
+        let mut workers = Vec::with_capacity(options.threads);
+
+        for _ in 0..options.threads {
+            let (chunk_tx, chunk_rx): (SyncSender<Chunk>, Receiver<Chunk>) =
+                mpsc::sync_channel(CHUNKS_PER_WORKER);
+            let (result_tx, result_rx) = mpsc::sync_channel(CHUNKS_PER_WORKER);
+            let options = options.clone();
+            workers.push(std::thread::spawn(move || {
+                for (first_line, chunk) in chunk_rx {
+                    let lines: Result<Vec<BodyfileLine>, MactimeError> = chunk
+                        .into_iter()
+                        .enumerate()
+                        .filter_map(|(idx, line)| Self::parse_line(line, first_line + idx, &options).transpose())
+                        .collect();
+                    let failed = lines.is_err();
+                    if result_tx.send(lines).is_err() || failed {
+                        break;
+                    }
+                }
+            }));
+            chunk_senders.push(chunk_tx);
+            result_receivers.push(result_rx);
+        }
+
+        let collector = std::thread::spawn(move || {
+            for result_rx in result_receivers.iter().cycle() {
+                let lines = match result_rx.recv() {
+                    Err(_) => break,
+                    Ok(lines) => lines?,
+                };
+                for line in lines {
+                    if tx.send(line).is_err() {
+                        return Ok(());
+                    }
+                }
+            }
+            Ok(())
+        });
+
+        let mut chunk = Vec::with_capacity(CHUNK_SIZE);
+        let mut first_line = 1;
+        let mut next_worker = 0;
+        for line in reader {
+            chunk.push(line);
+            if chunk.len() == CHUNK_SIZE {
+                let full_chunk = std::mem::replace(&mut chunk, Vec::with_capacity(CHUNK_SIZE));
+                if chunk_senders[next_worker].send((first_line, full_chunk)).is_err() {
+                    break;
+                }
+                first_line += CHUNK_SIZE;
+                next_worker = (next_worker + 1) % chunk_senders.len();
+            }
+        }
+        if !chunk.is_empty() {
+            let _ = chunk_senders[next_worker].send((first_line, chunk));
+        }
+        drop(chunk_senders);
+
+        let mut panic = None;
+        for worker in workers {
+            if let Err(why) = worker.join() {
+                panic.get_or_insert(why);
+            }
+        }
+        let result = collector.join();
+
+        if let Some(why) = panic {
+            std::panic::resume_unwind(why);
+        }
+        result.unwrap_or_else(|why| std::panic::resume_unwind(why))
+    }
+
     fn trim_newline(s: &mut String) {
         if s.ends_with('\n') {
             s.pop();
@@ -72,8 +243,8 @@ impl BodyfileDecoder {
     }
 }
 
-impl Joinable<()> for BodyfileDecoder {
-    fn join(&mut self) -> std::thread::Result<()> {
+impl Joinable<Result<(), MactimeError>> for BodyfileDecoder {
+    fn join(&mut self) -> std::thread::Result<Result<(), MactimeError>> {
         self.worker.take().unwrap().join()
     }
 }
\ No newline at end of file
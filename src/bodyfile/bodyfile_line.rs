@@ -0,0 +1,339 @@
+use std::convert::TryFrom;
+use std::fmt;
+use std::ops::Deref;
+
+use bodyfile::{Bodyfile3Line, Bodyfile3ParserError};
+
+use crate::{ClockSkew, Timestamp};
+
+/// a bodyfile line whose timestamps may have a fraction of a second, like
+/// `1661772116.123456789`, or may be before 1970.
+///
+/// The line dereferences to the [`Bodyfile3Line`] which contains the whole
+/// seconds of all timestamps, so all fields can be read as usual. The exact
+/// timestamps are returned by [`BodyfileLine::mtime`] and its siblings, which
+/// return `None` if a timestamp is not set. In the bodyfile format, such
+/// timestamps are written as `-1`, so `1969-12-31T23:59:59+00:00` cannot be
+/// distinguished from a missing timestamp.
+#[derive(Debug)]
+pub struct BodyfileLine {
+    line: Bodyfile3Line,
+
+    /// atime, mtime, ctime and crtime (in this order)
+    timestamps: [Option<Timestamp>; 4],
+
+    /// the correction which has been applied to the timestamps, if any
+    skew: Option<ClockSkew>,
+
+    /// the MD5 hash of the file is contained in a set of known-bad hashes
+    known_bad: bool,
+}
+
+const ATIME: usize = 0;
+const MTIME: usize = 1;
+const CTIME: usize = 2;
+const CRTIME: usize = 3;
+
+/// the value of a timestamp which is not set in a bodyfile
+const MISSING: i64 = -1;
+
+impl BodyfileLine {
+    pub fn atime(&self) -> Option<Timestamp> {
+        self.timestamps[ATIME]
+    }
+
+    pub fn mtime(&self) -> Option<Timestamp> {
+        self.timestamps[MTIME]
+    }
+
+    pub fn ctime(&self) -> Option<Timestamp> {
+        self.timestamps[CTIME]
+    }
+
+    pub fn crtime(&self) -> Option<Timestamp> {
+        self.timestamps[CRTIME]
+    }
+
+    pub fn with_atime(self, ts: Option<Timestamp>) -> Self {
+        self.with_timestamp(ATIME, ts)
+    }
+
+    pub fn with_mtime(self, ts: Option<Timestamp>) -> Self {
+        self.with_timestamp(MTIME, ts)
+    }
+
+    pub fn with_ctime(self, ts: Option<Timestamp>) -> Self {
+        self.with_timestamp(CTIME, ts)
+    }
+
+    pub fn with_crtime(self, ts: Option<Timestamp>) -> Self {
+        self.with_timestamp(CRTIME, ts)
+    }
+
+    /// returns mtime, atime, ctime and crtime (in this order)
+    pub fn timestamps(&self) -> [Option<Timestamp>; 4] {
+        [self.mtime(), self.atime(), self.ctime(), self.crtime()]
+    }
+
+    /// returns the correction which has been added to all timestamps of
+    /// this line using [`BodyfileLine::with_skew`]
+    pub fn skew(&self) -> Option<ClockSkew> {
+        self.skew
+    }
+
+    /// adds `skew` to all timestamps, and records it, so that the correction
+    /// can be displayed together with the line
+    pub fn with_skew(self, skew: ClockSkew) -> Self {
+        let [mtime, atime, ctime, crtime] = self.timestamps().map(|ts| ts.map(|ts| skew.apply(ts)));
+        let recorded = self.skew.map_or(skew, |s| ClockSkew::from_nanos(s.as_nanos() + skew.as_nanos()));
+        self.with_mtime(mtime)
+            .with_atime(atime)
+            .with_ctime(ctime)
+            .with_crtime(crtime)
+            .with_recorded_skew(Some(recorded))
+    }
+
+    /// records that the timestamps already have been corrected by `skew`
+    pub(crate) fn with_recorded_skew(mut self, skew: Option<ClockSkew>) -> Self {
+        self.skew = skew;
+        self
+    }
+
+    /// returns `true` if the MD5 hash of this line has been found in a set
+    /// of known-bad hashes (see [`HashFilter`](crate::HashFilter))
+    pub fn is_known_bad(&self) -> bool {
+        self.known_bad
+    }
+
+    pub fn with_known_bad(mut self, known_bad: bool) -> Self {
+        self.known_bad = known_bad;
+        self
+    }
+
+    pub fn into_inner(self) -> Bodyfile3Line {
+        self.line
+    }
+
+    fn illegal_timestamp(index: usize) -> Bodyfile3ParserError {
+        match index {
+            ATIME => Bodyfile3ParserError::IllegalATime,
+            MTIME => Bodyfile3ParserError::IllegalMTime,
+            CTIME => Bodyfile3ParserError::IllegalCTime,
+            _ => Bodyfile3ParserError::IllegalCRTime,
+        }
+    }
+
+    fn with_timestamp(mut self, index: usize, ts: Option<Timestamp>) -> Self {
+        let secs = ts.map_or(MISSING, |ts| ts.secs());
+        self.line = match index {
+            ATIME => self.line.with_atime(secs),
+            MTIME => self.line.with_mtime(secs),
+            CTIME => self.line.with_ctime(secs),
+            _ => self.line.with_crtime(secs),
+        };
+        self.timestamps[index] = ts;
+        self
+    }
+}
+
+impl Clone for BodyfileLine {
+    fn clone(&self) -> Self {
+        let line = Bodyfile3Line::from_values(
+            self.get_md5().to_owned(),
+            self.get_name().to_owned(),
+            self.get_inode().to_owned(),
+            self.get_mode().to_owned(),
+            self.get_uid(),
+            self.get_gid(),
+            self.get_size(),
+            self.get_atime(),
+            self.get_mtime(),
+            self.get_ctime(),
+            self.get_crtime(),
+        );
+        Self {
+            line,
+            timestamps: self.timestamps,
+            skew: self.skew,
+            known_bad: self.known_bad,
+        }
+    }
+}
+
+impl Deref for BodyfileLine {
+    type Target = Bodyfile3Line;
+
+    fn deref(&self) -> &Self::Target {
+        &self.line
+    }
+}
+
+/// converts a line with whole seconds, where `-1` means that a timestamp is
+/// not set
+impl From<Bodyfile3Line> for BodyfileLine {
+    fn from(line: Bodyfile3Line) -> Self {
+        let timestamp = |secs| Some(secs).filter(|secs| *secs != MISSING).map(Timestamp::from);
+        let timestamps = [
+            timestamp(line.get_atime()),
+            timestamp(line.get_mtime()),
+            timestamp(line.get_ctime()),
+            timestamp(line.get_crtime()),
+        ];
+        Self {
+            line,
+            timestamps,
+            skew: None,
+            known_bad: false,
+        }
+    }
+}
+
+impl TryFrom<&str> for BodyfileLine {
+    type Error = Bodyfile3ParserError;
+
+    /// parses a bodyfile line. The timestamps are parsed here, and the
+    /// remaining columns are parsed by [`Bodyfile3Line`], which only
+    /// supports whole seconds after 1970.
+    fn try_from(line: &str) -> Result<Self, Self::Error> {
+        // lines which are too short are reported like by Bodyfile3Line,
+        // instead of complaining about the column which should contain a
+        // timestamp
+        if line.split('|').count() < 11 {
+            return Err(Bodyfile3ParserError::WrongNumberOfColumns);
+        }
+
+        // the name may contain '|', so the timestamps are the last columns
+        let mut columns = line.rsplitn(5, '|');
+        let mut timestamps = [None; 4];
+        for index in [CRTIME, CTIME, MTIME, ATIME].iter().copied() {
+            let column = columns.next().ok_or(Bodyfile3ParserError::WrongNumberOfColumns)?;
+            timestamps[index] = match column.parse::<Timestamp>() {
+                Ok(ts) if ts == Timestamp::from(MISSING) => None,
+                Ok(ts) => Some(ts),
+                Err(_) => return Err(Self::illegal_timestamp(index)),
+            };
+        }
+        let remainder = columns.next().ok_or(Bodyfile3ParserError::WrongNumberOfColumns)?;
+
+        let line = Bodyfile3Line::try_from(format!("{}|-1|-1|-1|-1", remainder).as_str())?;
+        Ok(Self::from(line)
+            .with_atime(timestamps[ATIME])
+            .with_mtime(timestamps[MTIME])
+            .with_ctime(timestamps[CTIME])
+            .with_crtime(timestamps[CRTIME]))
+    }
+}
+
+/// displays the line in bodyfile format. Timestamps without a fraction are
+/// displayed exactly like [`Bodyfile3Line`] does. If the line has a skew,
+/// the corrected timestamps are displayed.
+impl fmt::Display for BodyfileLine {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let [mtime, atime, ctime, crtime] = self.timestamps().map(|ts| match ts {
+            Some(ts) => ts.to_string(),
+            None => MISSING.to_string(),
+        });
+        write!(
+            f,
+            "{}|{}|{}|{}|{}|{}|{}|{}|{}|{}|{}",
+            self.get_md5(),
+            self.get_name(),
+            self.get_inode(),
+            self.get_mode(),
+            self.get_uid(),
+            self.get_gid(),
+            self.get_size(),
+            atime,
+            mtime,
+            ctime,
+            crtime
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BodyfileLine;
+    use crate::{ClockSkew, Timestamp};
+    use bodyfile::{Bodyfile3Line, Bodyfile3ParserError};
+    use std::convert::TryFrom;
+
+    #[test]
+    fn test_fractions() {
+        let line = BodyfileLine::try_from("0|/tmp/a|b|1|r|0|0|10|1661772116.5|1661772116|-1|1661772115.999999999").unwrap();
+        assert_eq!(line.get_name(), "/tmp/a|b");
+        assert_eq!(line.atime(), Some(Timestamp::new(1661772116, 500_000_000)));
+        assert_eq!(line.mtime(), Some(Timestamp::new(1661772116, 0)));
+        assert_eq!(line.ctime(), None);
+        assert_eq!(line.crtime(), Some(Timestamp::new(1661772115, 999_999_999)));
+        assert_eq!(line.get_atime(), 1661772116);
+        assert_eq!(
+            line.to_string(),
+            "0|/tmp/a|b|1|r|0|0|10|1661772116.5|1661772116|-1|1661772115.999999999"
+        );
+    }
+
+    #[test]
+    fn test_before_1970() {
+        let line = BodyfileLine::try_from("0|/tmp/a|1|r|0|0|10|-86400|-1.5|-1|0").unwrap();
+        assert_eq!(line.atime(), Some(Timestamp::new(-86400, 0)));
+        assert_eq!(line.mtime(), Some(Timestamp::new(-2, 500_000_000)));
+        assert_eq!(line.ctime(), None);
+        assert_eq!(line.crtime(), Some(Timestamp::new(0, 0)));
+        assert_eq!(line.get_ctime(), -1);
+        assert_eq!(line.to_string(), "0|/tmp/a|1|r|0|0|10|-86400|-1.5|-1|0");
+    }
+
+    #[test]
+    fn test_same_as_bodyfile3line() {
+        let lines = [
+            "0|/etc/passwd|1|r|0|0|10|1661774614|1661772116|1661772116|-1",
+            "d41d8cd98f00b204e9800998ecf8427e|ls -l |wc|1|2|3|4|5|6|7|8|9",
+        ];
+        for line in lines.iter() {
+            let expected = Bodyfile3Line::try_from(*line).unwrap().to_string();
+            assert_eq!(BodyfileLine::try_from(*line).unwrap().to_string(), expected);
+        }
+
+        assert!(matches!(
+            BodyfileLine::try_from("0|/tmp/a|1|r|0|0|10|1|2|3"),
+            Err(Bodyfile3ParserError::WrongNumberOfColumns)
+        ));
+        assert!(matches!(
+            BodyfileLine::try_from("0|/tmp/a|1|r|0|0|10|1|2.x|3|4"),
+            Err(Bodyfile3ParserError::IllegalMTime)
+        ));
+        assert!(matches!(
+            BodyfileLine::try_from("0|/tmp/a|1|r|x|0|10|1|2|3|4"),
+            Err(Bodyfile3ParserError::IllegalUid)
+        ));
+    }
+
+    #[test]
+    fn test_short_lines() {
+        for line in ["bad line", "", "0|/tmp/a|1|r|0|0|10|1"].iter() {
+            assert!(
+                matches!(BodyfileLine::try_from(*line), Err(Bodyfile3ParserError::WrongNumberOfColumns)),
+                "unexpected result for '{}'",
+                line
+            );
+            assert!(matches!(
+                Bodyfile3Line::try_from(*line),
+                Err(Bodyfile3ParserError::WrongNumberOfColumns)
+            ));
+        }
+    }
+
+    #[test]
+    fn test_skew() {
+        let skew: ClockSkew = "-00:00:01.5".parse().unwrap();
+        let line = BodyfileLine::try_from("0|/tmp/a|1|r|0|0|10|10|20.5|-1|0").unwrap().with_skew(skew);
+        assert_eq!(line.skew(), Some(skew));
+        assert_eq!(line.to_string(), "0|/tmp/a|1|r|0|0|10|8.5|19|-1|-1.5");
+        assert_eq!(line.clone().skew(), Some(skew));
+
+        let line = line.with_skew("+00:00:01.5".parse().unwrap());
+        assert_eq!(line.skew().map(|s| s.as_nanos()), Some(0));
+        assert_eq!(line.to_string(), "0|/tmp/a|1|r|0|0|10|10|20.5|-1|0");
+    }
+}
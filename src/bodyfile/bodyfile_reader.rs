@@ -1,24 +1,24 @@
-use crate::Provider;
+use crate::{MactimeError, Provider};
 use crate::stream::*;
 use crate::Joinable;
 use encoding_rs_io::DecodeReaderBytesBuilder;
 use std::io::{BufRead, BufReader, Read};
-use std::sync::mpsc::{Receiver, Sender};
+use std::sync::mpsc::{Receiver, SyncSender};
 use std::thread::{JoinHandle};
 
 pub struct BodyfileReader {
-    worker: Option<JoinHandle<()>>,
+    worker: Option<JoinHandle<Result<(), MactimeError>>>,
     rx: Option<Receiver<String>>,
 }
 
-impl Provider<String, ()> for BodyfileReader {
+impl Provider<String, Result<(), MactimeError>> for BodyfileReader {
     fn get_receiver(&mut self) -> Receiver<String> {
         self.rx.take().unwrap()
     }
 }
 
 impl StreamWorker<String> for BodyfileReader {
-    fn worker<R: Read + Send>(input: R, tx: Sender<String>) {
+    fn worker<R: Read + Send>(input: R, tx: SyncSender<String>) -> Result<(), MactimeError> {
         let mut line_ctr = 1;
 
         let drb = DecodeReaderBytesBuilder::new()
@@ -33,8 +33,7 @@ impl StreamWorker<String> for BodyfileReader {
 
             match size {
                 Err(why) => {
-                    eprintln!("IO Error in line {}: {:?}", line_ctr, why);
-                    break;
+                    return Err(MactimeError::ReadError { line: line_ctr, source: why });
                 }
                 Ok(s) => {
                     if s == 0 {
@@ -48,11 +47,12 @@ impl StreamWorker<String> for BodyfileReader {
             }
             line_ctr += 1;
         }
+        Ok(())
     }
 }
 
-impl StreamReader<String, ()> for BodyfileReader {
-    fn new (worker: JoinHandle<()>, rx: Receiver<String>) -> Self {
+impl StreamReader<String, Result<(), MactimeError>> for BodyfileReader {
+    fn new (worker: JoinHandle<Result<(), MactimeError>>, rx: Receiver<String>) -> Self {
         Self {
             worker: Some(worker),
             rx: Some(rx)
@@ -60,8 +60,8 @@ impl StreamReader<String, ()> for BodyfileReader {
     }
 }
 
-impl Joinable<()> for BodyfileReader {
-    fn join(&mut self) -> std::thread::Result<()> {
+impl Joinable<Result<(), MactimeError>> for BodyfileReader {
+    fn join(&mut self) -> std::thread::Result<Result<(), MactimeError>> {
         self.worker.take().unwrap().join()
     }
 }
@@ -1,27 +1,45 @@
-use crate::{MactimeError, Runnable, Sorter};
+use crate::{DstMark, MactimeError, OutputFile, Runnable, Sorter, Timeline, Timestamp};
 use crate::{Joinable, RunOptions};
 use bitflags::bitflags;
-use bodyfile::Bodyfile3Line;
-use std::borrow::Borrow;
+use crate::BodyfileLine;
 use std::cmp::Ordering;
-use std::collections::{BTreeMap, HashSet};
 use std::fmt;
+use std::io::{self, Write};
 use std::sync::Arc;
 use std::sync::mpsc::Receiver;
 use std::thread::JoinHandle;
 
+/// formats the entries of a timeline. `timestamp` is `None` for entries of
+/// lines which do not have any timestamp.
 pub trait Mactime2Writer: Send {
-    fn write(&self, timestamp: &i64, entry: &ListEntry) {
-        println!("{}", self.fmt(timestamp, entry));
+    fn write(&self, sink: &mut dyn Write, timestamp: Option<&Timestamp>, entry: &ListEntry) -> io::Result<()> {
+        writeln!(sink, "{}", self.fmt(timestamp, entry))
+    }
+    fn fmt(&self, timestamp: Option<&Timestamp>, entry: &ListEntry) -> String;
+
+    /// writes everything which precedes the entries, such as a header line
+    fn write_header(&self, _sink: &mut dyn Write) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// writes everything which follows the entries, such as entries which
+    /// have been held back by [`Mactime2Writer::write`]
+    fn write_footer(&self, _sink: &mut dyn Write) -> io::Result<()> {
+        Ok(())
     }
-    fn fmt(&self, timestamp: &i64, entry: &ListEntry) -> String;
 }
 
+/// a receiver of bodyfile lines, together with the label of the input
+/// which they have been read from
+pub type LabeledReceiver = (Receiver<BodyfileLine>, Option<Arc<str>>);
+
 #[derive(Default)]
 pub struct BodyfileSorter {
     worker: Option<JoinHandle<Result<(),MactimeError>>>,
-    receiver: Option<Receiver<Bodyfile3Line>>,
-    output: Option<Box<dyn Mactime2Writer>>
+    receivers: Vec<LabeledReceiver>,
+    output: Option<Box<dyn Mactime2Writer>>,
+    sink: Option<OutputFile>,
+    options: RunOptions,
 }
 
 bitflags! {
@@ -35,6 +53,57 @@ bitflags! {
     }
 }
 
+impl MACBFlags {
+    /// groups the timestamps of a bodyfile line. Every distinct timestamp is
+    /// returned together with the flags of all times which share this
+    /// timestamp. Missing timestamps are omitted. Timestamps are only
+    /// grouped if their fractions of a second are equal as well.
+    pub fn of_line(line: &BodyfileLine) -> Vec<(Timestamp, MACBFlags)> {
+        let mut flags: [MACBFlags; 4] = [MACBFlags::NONE; 4];
+        let timestamps = line.timestamps();
+        let [mtime, atime, ctime, crtime] = timestamps;
+
+        if mtime.is_some() {
+            flags[0] |= MACBFlags::M;
+        }
+        if atime.is_some() {
+            if mtime == atime {
+                flags[0] |= MACBFlags::A;
+            } else {
+                flags[1] |= MACBFlags::A;
+            }
+        }
+        if ctime.is_some() {
+            if mtime == ctime {
+                flags[0] |= MACBFlags::C;
+            } else if atime == ctime {
+                flags[1] |= MACBFlags::C;
+            } else {
+                flags[2] |= MACBFlags::C;
+            }
+        }
+        if crtime.is_some() {
+            if mtime == crtime {
+                flags[0] |= MACBFlags::B;
+            } else if atime == crtime {
+                flags[1] |= MACBFlags::B;
+            } else if ctime == crtime {
+                flags[2] |= MACBFlags::B;
+            } else {
+                flags[3] |= MACBFlags::B;
+            }
+        }
+
+        timestamps
+            .iter()
+            .copied()
+            .zip(flags)
+            .filter(|(_, flag)| *flag != MACBFlags::NONE)
+            .filter_map(|(ts, flag)| ts.map(|ts| (ts, flag)))
+            .collect()
+    }
+}
+
 impl fmt::Display for MACBFlags {
     fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let m = if *self & Self::M == Self::M { 'm' } else { '.' };
@@ -48,14 +117,24 @@ impl fmt::Display for MACBFlags {
 #[derive(Debug)]
 pub struct ListEntry {
     pub flags: MACBFlags,
-    pub line: Arc<Bodyfile3Line>,
+    pub line: Arc<BodyfileLine>,
+
+    /// label of the input from which this entry has been read. This is only
+    /// set if the source of an entry is relevant, e.g. if multiple inputs
+    /// are merged into one timeline
+    pub source: Option<Arc<str>>,
+
+    /// set if the timestamp of this entry is affected by a DST transition
+    /// in the source timezone
+    pub dst: Option<DstMark>,
 }
 
 impl Eq for ListEntry {}
 impl PartialEq for ListEntry {
     fn eq(&self, other: &Self) -> bool {
         self.line.get_inode().eq(other.line.get_inode()) &&
-        self.line.get_name().eq(other.line.get_name())
+        self.line.get_name().eq(other.line.get_name()) &&
+        self.source.eq(&other.source)
     }
 }
 impl PartialOrd for ListEntry {
@@ -67,62 +146,44 @@ impl PartialOrd for ListEntry {
 impl Ord for ListEntry {
     fn cmp(&self, other: &Self) -> Ordering {
         match self.line.get_name().cmp(other.line.get_name()) {
-            Ordering::Equal => self.line.get_inode().cmp(other.line.get_inode()),
+            Ordering::Equal => match self.line.get_inode().cmp(other.line.get_inode()) {
+                Ordering::Equal => self.source.cmp(&other.source),
+                other => other
+            },
             other => other
         }
     }
 }
 
-fn insert_timestamp(
-    entries: &mut BTreeMap<i64, Vec<ListEntry>>,
-    flag: MACBFlags,
-    line: Arc<Bodyfile3Line>,
-) {
-    let timestamp = if flag.contains(MACBFlags::M) {
-        line.get_mtime()
-    } else if flag.contains(MACBFlags::A) {
-        line.get_atime()
-    } else if flag.contains(MACBFlags::C) {
-        line.get_ctime()
-    } else if flag.contains(MACBFlags::B) {
-        line.get_crtime()
-    } else {
-        -1
-    };
-
-    match entries.get_mut(&timestamp) {
-        None => {
-            let mut entries_at_ts = Vec::new();
-            let entry = ListEntry {
-                flags: flag,
-                line,
-            };
-            entries_at_ts.push(entry);
-            entries.insert(timestamp, entries_at_ts);
-        }
-
-        Some(entries_at_ts) => {
-            let entry = ListEntry {
-                flags: flag,
-                line,
-            };
-            entries_at_ts.push(entry);
-        }
-    }
-}
-
 impl Runnable for BodyfileSorter {
     fn run(&mut self) {
-        let receiver = self.receiver.take().expect("no receiver provided; please call with_receiver()");
+        assert!(!self.receivers.is_empty(), "no receiver provided; please call with_receiver()");
+        let receivers = std::mem::take(&mut self.receivers);
         let output = self.output.take().expect("no output provided; please call with_output()");
+        let sink = self.sink.take().unwrap_or_else(OutputFile::stdout);
+        let options = self.options.clone();
         self.worker = Some(
-            std::thread::spawn(move || Self::worker(receiver, output)));
+            std::thread::spawn(move || Self::worker(receivers, output, sink, options)));
     }
 }
 
 impl BodyfileSorter {
-    pub fn with_receiver(mut self, decoder: Receiver<Bodyfile3Line>, _: RunOptions) -> Self {
-        self.receiver = Some(decoder);
+    pub fn new(options: RunOptions) -> Self {
+        Self {
+            options,
+            ..Default::default()
+        }
+    }
+
+    pub fn with_receiver(mut self, decoder: Receiver<BodyfileLine>, options: RunOptions) -> Self {
+        self.options = options;
+        self.with_input(decoder, None)
+    }
+
+    /// adds another input. All inputs are merged into the same timeline,
+    /// and entries of inputs with a label carry this label as their source.
+    pub fn with_input(mut self, decoder: Receiver<BodyfileLine>, label: Option<&str>) -> Self {
+        self.receivers.push((decoder, label.map(Arc::from)));
         self
     }
 
@@ -131,82 +192,21 @@ impl BodyfileSorter {
         self
     }
 
-    fn worker(decoder: Receiver<Bodyfile3Line>, output: Box<dyn Mactime2Writer>) -> Result<(), MactimeError> {
-        let mut entries: BTreeMap<i64, Vec<ListEntry>> = BTreeMap::new();
-        let mut names: HashSet<(String,String)> = HashSet::new();
+    /// sets the destination of the timeline. If no output file has been
+    /// specified, the timeline is written to stdout.
+    pub fn with_output_file(mut self, sink: OutputFile) -> Self {
+        self.sink = Some(sink);
+        self
+    }
 
-        loop {
-            let line = Arc::new(match decoder.recv() {
-                Err(_) => {
-                    break;
-                }
-                Ok(l) => l,
+    fn worker(receivers: Vec<LabeledReceiver>, output: Box<dyn Mactime2Writer>, mut sink: OutputFile, options: RunOptions) -> Result<(), MactimeError> {
+        let timeline = receivers
+            .into_iter()
+            .fold(Timeline::new(options), |timeline, (decoder, source)| {
+                timeline.with_lines(decoder, source.as_deref())
             });
-
-            // each name && inode SHOULD occur only once
-            {
-                let bf: &Bodyfile3Line = line.borrow();
-                if names.contains(&(bf.get_inode().to_owned(), bf.get_name().to_owned())) {
-                    log::warn!("ambigious file name: '{}' and inode '{}'", bf.get_name(), bf.get_inode());
-                    //return Err(MactimeError::AmbiguousFilename(bf.get_name().to_owned()))
-                }
-                names.insert((bf.get_inode().to_owned(), bf.get_name().to_owned()));
-            } // delete the borrow to line
-
-            // we need *some* value in mactimes!
-            if line.get_mtime() == -1
-                && line.get_atime() == -1
-                && line.get_ctime() == -1
-                && line.get_crtime() == -1
-            {
-                insert_timestamp(&mut entries, MACBFlags::NONE, Arc::clone(&line));
-                continue;
-            }
-
-            let mut flags: [MACBFlags; 4] = [MACBFlags::NONE; 4];
-
-            if line.get_mtime() != -1 {
-                flags[0] |= MACBFlags::M;
-            }
-            if line.get_atime() != -1 {
-                if line.get_mtime() == line.get_atime() {
-                    flags[0] |= MACBFlags::A;
-                } else {
-                    flags[1] |= MACBFlags::A;
-                }
-            }
-            if line.get_ctime() != -1 {
-                if line.get_mtime() == line.get_ctime() {
-                    flags[0] |= MACBFlags::C;
-                } else if line.get_atime() == line.get_ctime() {
-                    flags[1] |= MACBFlags::C;
-                } else {
-                    flags[2] |= MACBFlags::C;
-                }
-            }
-            if line.get_crtime() != -1 {
-                if line.get_mtime() == line.get_crtime() {
-                    flags[0] |= MACBFlags::B;
-                } else if line.get_atime() == line.get_crtime() {
-                    flags[1] |= MACBFlags::B;
-                } else if line.get_ctime() == line.get_crtime() {
-                    flags[2] |= MACBFlags::B;
-                } else {
-                    flags[3] |= MACBFlags::B;
-                }
-            }
-            for flag in flags.iter() {
-                if flag != &MACBFlags::NONE {
-                    insert_timestamp(&mut entries, *flag, Arc::clone(&line));
-                }
-            }
-        }
-
-        for (ts, entries_at_ts) in entries.iter() {
-            for line in entries_at_ts {
-                output.write(ts, line);
-            }
-        }
+        timeline.write(output.as_ref(), &mut sink)?;
+        sink.finish()?;
         Ok(())
     }
 }
@@ -0,0 +1,264 @@
+use crate::{Consumer, Filter, Joinable, MactimeError, Provider, RunOptions, CHANNEL_CAPACITY};
+use crate::BodyfileLine;
+use std::fs::File;
+use std::io::{BufRead, BufReader};
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+
+/// a set of MD5 hashes, like a list of known files. The hashes are kept as
+/// a sorted list of numbers, so that large hash sets like the NSRL need as
+/// little memory as possible.
+#[derive(Clone, Debug, Default)]
+pub struct Md5Set {
+    hashes: Vec<u128>,
+}
+
+impl Md5Set {
+    /// reads a hash set, which is either a NSRL RDS file (comma separated
+    /// values with a header line which contains a column `"MD5"`) or a file
+    /// with one hash per line. In the latter case, everything after the hash
+    /// is ignored (like the names which are written by `md5sum`), as well as
+    /// empty lines and lines starting with `#`.
+    pub fn from_reader<R: BufRead>(mut reader: R) -> Result<Self, String> {
+        let is_rds = reader
+            .fill_buf()
+            .map_err(|e| e.to_string())?
+            .starts_with(b"\"");
+        let mut hashes = if is_rds {
+            Self::read_rds(reader)?
+        } else {
+            Self::read_list(reader)?
+        };
+        hashes.sort_unstable();
+        hashes.dedup();
+        Ok(Self { hashes })
+    }
+
+    fn read_rds<R: BufRead>(reader: R) -> Result<Vec<u128>, String> {
+        let mut reader = csv::Reader::from_reader(reader);
+        let column = reader
+            .byte_headers()
+            .map_err(|e| e.to_string())?
+            .iter()
+            .position(|header| header == b"MD5")
+            .ok_or_else(|| "the hash set has no column 'MD5'".to_owned())?;
+
+        let mut hashes = Vec::new();
+        for record in reader.byte_records() {
+            let record = record.map_err(|e| e.to_string())?;
+            let hash = record.get(column).unwrap_or_default();
+            match parse_md5(hash) {
+                Some(hash) => hashes.push(hash),
+                None => return Err(invalid_hash(hash, record.position().map_or(0, |p| p.line()))),
+            }
+        }
+        Ok(hashes)
+    }
+
+    fn read_list<R: BufRead>(reader: R) -> Result<Vec<u128>, String> {
+        let mut hashes = Vec::new();
+
+        // names which follow the hash may use any encoding
+        for (index, line) in reader.split(b'\n').enumerate() {
+            let line = line.map_err(|e| e.to_string())?;
+            let hash = line.split(|b| b.is_ascii_whitespace()).find(|s| !s.is_empty());
+            match hash {
+                None => continue,
+                Some(hash) if hash.starts_with(b"#") => continue,
+                Some(hash) => match parse_md5(hash) {
+                    Some(hash) => hashes.push(hash),
+                    None => return Err(invalid_hash(hash, index as u64 + 1)),
+                },
+            }
+        }
+        Ok(hashes)
+    }
+
+    /// merges two hash sets
+    pub fn merge(mut self, other: Md5Set) -> Self {
+        self.hashes.extend(other.hashes);
+        self.hashes.sort_unstable();
+        self.hashes.dedup();
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.hashes.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.hashes.is_empty()
+    }
+
+    /// returns `true` if the set contains `md5`, which is not case sensitive.
+    /// Values which are no MD5 hashes (like `0` for files which have not
+    /// been hashed) are never contained.
+    pub fn contains(&self, md5: &str) -> bool {
+        parse_md5(md5.as_bytes()).is_some_and(|hash| self.hashes.binary_search(&hash).is_ok())
+    }
+}
+
+/// reads a hash set from the file at `path` (see [`Md5Set::from_reader`])
+impl FromStr for Md5Set {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let file = File::open(path).map_err(|e| format!("unable to read '{}': {}", path, e))?;
+        Self::from_reader(BufReader::new(file)).map_err(|e| format!("unable to read '{}': {}", path, e))
+    }
+}
+
+fn parse_md5(hash: &[u8]) -> Option<u128> {
+    if hash.len() != 32 {
+        return None;
+    }
+    hash.iter()
+        .try_fold(0u128, |value, b| char::from(*b).to_digit(16).map(|digit| value << 4 | u128::from(digit)))
+}
+
+fn invalid_hash(hash: &[u8], line: u64) -> String {
+    format!("invalid MD5 hash '{}' in line {}", String::from_utf8_lossy(hash), line)
+}
+
+/// decides which entries are dropped or marked by [`HashFilter`]. Entries
+/// whose hashes are known-bad are always kept, even if they are known-good
+/// as well.
+#[derive(Clone, Debug, Default)]
+pub struct HashFilterRules {
+    known_good: Arc<Md5Set>,
+    known_bad: Arc<Md5Set>,
+}
+
+impl HashFilterRules {
+    pub fn new(known_good: Md5Set, known_bad: Md5Set) -> Self {
+        Self {
+            known_good: Arc::new(known_good),
+            known_bad: Arc::new(known_bad),
+        }
+    }
+
+    /// returns `true` if there are no hashes at all, which means that all
+    /// entries will be passed unchanged
+    pub fn is_empty(&self) -> bool {
+        self.known_good.is_empty() && self.known_bad.is_empty()
+    }
+
+    /// returns `true` if entries may be marked as known-bad
+    pub fn has_known_bad(&self) -> bool {
+        !self.known_bad.is_empty()
+    }
+
+    pub fn is_known_good(&self, md5: &str) -> bool {
+        !self.is_known_bad(md5) && self.known_good.contains(md5)
+    }
+
+    pub fn is_known_bad(&self, md5: &str) -> bool {
+        self.known_bad.contains(md5)
+    }
+
+    /// returns `None` if `line` is known-good, or the line itself, which is
+    /// marked if it is known-bad
+    pub fn apply(&self, line: BodyfileLine) -> Option<BodyfileLine> {
+        if self.is_known_bad(line.get_md5()) {
+            Some(line.with_known_bad(true))
+        } else if self.known_good.contains(line.get_md5()) {
+            None
+        } else {
+            Some(line)
+        }
+    }
+}
+
+/// drops all bodyfile lines whose hashes are known-good, and marks all lines
+/// whose hashes are known-bad, according to the [`HashFilterRules`] in
+/// [`RunOptions::hash_filter`]
+pub struct HashFilter {
+    worker: Option<JoinHandle<Result<(), MactimeError>>>,
+    rx: Option<Receiver<BodyfileLine>>,
+}
+
+impl Filter<BodyfileLine, BodyfileLine, Result<(), MactimeError>> for HashFilter {
+    fn worker(reader: Receiver<BodyfileLine>, tx: SyncSender<BodyfileLine>, options: RunOptions) -> Result<(), MactimeError> {
+        while let Ok(line) = reader.recv() {
+            let line = match options.hash_filter.apply(line) {
+                Some(line) => line,
+                None => continue,
+            };
+
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Provider<BodyfileLine, Result<(), MactimeError>> for HashFilter {
+    fn get_receiver(&mut self) -> Receiver<BodyfileLine> {
+        self.rx.take().unwrap()
+    }
+}
+
+impl Consumer<BodyfileLine> for HashFilter {
+    fn with_receiver(reader: Receiver<BodyfileLine>, options: RunOptions) -> Self {
+        let (tx, rx): (SyncSender<BodyfileLine>, Receiver<BodyfileLine>) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        Self {
+            worker: Some(std::thread::spawn(move || Self::worker(reader, tx, options))),
+            rx: Some(rx),
+        }
+    }
+}
+
+impl Joinable<Result<(), MactimeError>> for HashFilter {
+    fn join(&mut self) -> std::thread::Result<Result<(), MactimeError>> {
+        self.worker.take().unwrap().join()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HashFilterRules, Md5Set};
+
+    const EMPTY: &str = "d41d8cd98f00b204e9800998ecf8427e";
+
+    fn md5_set(content: &str) -> Md5Set {
+        Md5Set::from_reader(content.as_bytes()).unwrap()
+    }
+
+    #[test]
+    fn test_list() {
+        let set = md5_set("# known files\n\nD41D8CD98F00B204E9800998ECF8427E  empty.txt\n0cc175b9c0f1b6a831c399e269772661\n");
+        assert_eq!(set.len(), 2);
+        assert!(set.contains(EMPTY));
+        assert!(set.contains("0CC175B9C0F1B6A831C399E269772661"));
+        assert!(!set.contains("0"));
+        assert!(!set.contains("92eb5ffee6ae2fec3ad71c777531578f"));
+
+        assert!(Md5Set::from_reader("d41d8cd98f00b204e9800998ecf8427e\nda39a3ee\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_rds() {
+        let set = md5_set(concat!(
+            "\"SHA-1\",\"MD5\",\"CRC32\",\"FileName\",\"FileSize\",\"ProductCode\",\"OpSystemCode\",\"SpecialCode\"\n",
+            "\"DA39A3EE5E6B4B0D3255BFEF95601890AFD80709\",\"D41D8CD98F00B204E9800998ECF8427E\",\"00000000\",\"a, b.txt\",0,1,\"362\",\"\"\n",
+        ));
+        assert_eq!(set.len(), 1);
+        assert!(set.contains(EMPTY));
+
+        assert!(Md5Set::from_reader("\"SHA-1\",\"CRC32\"\n\"DA39A3EE\",\"00000000\"\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = HashFilterRules::default();
+        assert!(rules.is_empty());
+
+        let rules = HashFilterRules::new(md5_set(EMPTY), md5_set(EMPTY).merge(md5_set("0cc175b9c0f1b6a831c399e269772661")));
+        assert!(rules.has_known_bad());
+        assert!(rules.is_known_bad(EMPTY));
+        assert!(!rules.is_known_good(EMPTY));
+    }
+}
@@ -0,0 +1,350 @@
+use crate::{BodyfileLine, Consumer, Filter, Joinable, MactimeError, Provider, RunOptions, Timestamp, CHANNEL_CAPACITY};
+use bodyfile::Bodyfile3Line;
+use serde_json::Value;
+use std::collections::BTreeSet;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::JoinHandle;
+
+/// parses the JSON lines generated by [`JsonSorter`](crate::JsonSorter) back
+/// into bodyfile lines.
+///
+/// Every bodyfile line yields one JSON document per distinct timestamp, but
+/// all of these documents contain the complete set of timestamps. So
+/// the decoder reconstructs the original bodyfile line from the first
+/// document, and skips all further documents of the same file.
+///
+/// The documents of a file are either next to each other (if the timeline
+/// has not been sorted), or they are sorted by time. So a file only needs to
+/// be remembered until the documents have passed its latest timestamp.
+///
+/// Keep in mind that the timestamps in the JSON documents have already
+/// been converted to UTC, and that the conversion loses data: the documents
+/// contain neither the MD5 hash nor the mode of a file, so the decoded lines
+/// have an MD5 hash of `0` and no mode, and hash sets cannot be applied to
+/// them. Documents of different inputs (`labels.source`) are never merged,
+/// but the decoded lines do not carry their source.
+pub struct JsonDecoder {
+    worker: Option<JoinHandle<Result<(), MactimeError>>>,
+    rx: Option<Receiver<BodyfileLine>>,
+}
+
+#[derive(Clone, PartialEq, Eq, PartialOrd, Ord)]
+struct DocumentKey {
+    name: String,
+    inode: String,
+    timestamps: [Option<Timestamp>; 4],
+    source: Option<String>,
+}
+
+impl DocumentKey {
+    fn of(document: &Document) -> Self {
+        Self {
+            name: document.line.get_name().to_owned(),
+            inode: document.line.get_inode().to_owned(),
+            timestamps: document.line.timestamps(),
+            source: document.source.clone(),
+        }
+    }
+
+    /// the timestamp of the last document of this file
+    fn latest(&self) -> Option<Timestamp> {
+        self.timestamps.iter().flatten().max().copied()
+    }
+}
+
+/// a JSON document, converted back into a bodyfile line
+struct Document {
+    line: BodyfileLine,
+
+    /// the timestamp which the document has been created for
+    timestamp: Option<Timestamp>,
+
+    /// the label of the input which the document has been created from
+    source: Option<String>,
+}
+
+/// the files whose documents have already been decoded
+#[derive(Default)]
+struct KnownDocuments {
+    previous: Option<DocumentKey>,
+
+    /// the files which might have further documents in a sorted timeline,
+    /// ordered by their latest timestamp
+    open: BTreeSet<(Timestamp, DocumentKey)>,
+
+    /// the latest timestamp of all documents so far
+    now: Option<Timestamp>,
+}
+
+impl KnownDocuments {
+    /// returns `false` if another document of the same file has already
+    /// been decoded. `timestamp` is the timestamp of the current document.
+    fn insert(&mut self, key: DocumentKey, timestamp: Option<Timestamp>) -> bool {
+        if self.previous.as_ref() == Some(&key) {
+            return false;
+        }
+
+        // files whose last document lies behind us will not occur again
+        self.now = self.now.max(timestamp);
+        while self.open.first().is_some_and(|(latest, _)| Some(*latest) < self.now) {
+            self.open.pop_first();
+        }
+
+        let is_new = match key.latest() {
+            Some(latest) => self.open.insert((latest, key.clone())),
+            None => true,
+        };
+        self.previous = Some(key);
+        is_new
+    }
+}
+
+impl Filter<String, BodyfileLine, Result<(), MactimeError>> for JsonDecoder {
+    fn worker(reader: Receiver<String>, tx: SyncSender<BodyfileLine>, options: RunOptions) -> Result<(), MactimeError> {
+        // the report must be marked before `tx` is dropped
+        let result = Self::decode_documents(reader, &tx, &options);
+        if result.is_err() {
+            options.report.abort();
+        }
+        result
+    }
+}
+
+impl JsonDecoder {
+    fn decode_documents(reader: Receiver<String>, tx: &SyncSender<BodyfileLine>, options: &RunOptions) -> Result<(), MactimeError> {
+        let mut known_documents = KnownDocuments::default();
+        let mut line_number = 0;
+        loop {
+            let line = match reader.recv() {
+                Err(_) => break,
+                Ok(l) => l,
+            };
+            line_number += 1;
+            options.report.count_line();
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let document = match Self::parse_document(&line) {
+                Err(e) => {
+                    options.report.reject(line_number, line.trim_end(), &e)?;
+                    if options.strict_mode {
+                        return Err(MactimeError::ParseError { line: line_number, message: e });
+                    }
+                    log::warn!("json parser error in line {}: {}", line_number, e);
+                    #[cfg(debug_assertions)]
+                    log::warn!("failed line was: {:?}", line);
+                    continue;
+                }
+                Ok(document) => document,
+            };
+
+            // the timestamps of the key must be comparable to the timestamp
+            // of the document, so they are not corrected
+            let key = DocumentKey::of(&document);
+            let bf_line = match options.skew {
+                Some(skew) => document.line.with_skew(skew),
+                None => document.line,
+            };
+            options.report.count_parsed(&bf_line);
+
+            if !known_documents.insert(key, document.timestamp) {
+                continue;
+            }
+
+            if tx.send(bf_line).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+
+    fn parse_document(line: &str) -> Result<Document, String> {
+        let document: Value = serde_json::from_str(line).map_err(|e| e.to_string())?;
+        let file = document
+            .get("file")
+            .ok_or_else(|| "document has no 'file' attribute".to_owned())?;
+
+        let name = match file["path"].as_str() {
+            Some(path) => path,
+            None => document["message"]
+                .as_str()
+                .ok_or_else(|| "unable to determine the file name".to_owned())?,
+        };
+
+        let line = Bodyfile3Line::new()
+            .with_name(name)
+            .with_inode(file["inode"].as_str().unwrap_or("0"))
+            .with_mode(file["mode"].as_str().unwrap_or(""))
+            .with_uid(file["uid"].as_u64().unwrap_or(0))
+            .with_gid(file["gid"].as_u64().unwrap_or(0))
+            .with_size(file["size"].as_u64().unwrap_or(0));
+        let line = BodyfileLine::from(line)
+            .with_mtime(Self::parse_timestamp(&file["mtime"])?)
+            .with_atime(Self::parse_timestamp(&file["accessed"])?)
+            .with_ctime(Self::parse_timestamp(&file["ctime"])?)
+            .with_crtime(Self::parse_timestamp(&file["created"])?);
+        Ok(Document {
+            line,
+            timestamp: Self::parse_timestamp(&document["@timestamp"])?,
+            source: document["labels"]["source"].as_str().map(str::to_owned),
+        })
+    }
+
+    /// converts a timestamp in milliseconds into a [`Timestamp`], or `None`
+    /// if the timestamp is not set. Timestamps with a fraction of a
+    /// millisecond are stored as strings, like `"1661772116123.456789"`.
+    fn parse_timestamp(value: &Value) -> Result<Option<Timestamp>, String> {
+        match value {
+            Value::Null => Ok(None),
+            Value::Number(n) => n
+                .as_i64()
+                .map(|millis| Some(Timestamp::from_millis(millis)))
+                .ok_or_else(|| format!("invalid timestamp: {}", n)),
+            Value::String(s) => Self::parse_millis(s)
+                .map(Some)
+                .ok_or_else(|| format!("invalid timestamp: {}", s)),
+            v => Err(format!("invalid timestamp: {}", v)),
+        }
+    }
+
+    fn parse_millis(s: &str) -> Option<Timestamp> {
+        // a timestamp in milliseconds is a timestamp in seconds which has
+        // been shifted by three digits
+        let (millis, fraction) = s.split_once('.').unwrap_or((s, ""));
+        let negative = millis.starts_with('-');
+        let millis = millis.trim_start_matches('-');
+        let secs = match millis.len() {
+            0 => return None,
+            1..=3 => "0",
+            len => &millis[..len - 3],
+        };
+        let sign = if negative { "-" } else { "" };
+        let ms = format!("{:0>3}", &millis[millis.len().saturating_sub(3)..]);
+        format!("{}{}.{}{}", sign, secs, ms, fraction).parse().ok()
+    }
+}
+
+impl Provider<BodyfileLine, Result<(), MactimeError>> for JsonDecoder {
+    fn get_receiver(&mut self) -> Receiver<BodyfileLine> {
+        self.rx.take().unwrap()
+    }
+}
+
+impl Consumer<String> for JsonDecoder {
+    fn with_receiver(reader: Receiver<String>, options: RunOptions) -> Self {
+        let (tx, rx): (SyncSender<BodyfileLine>, Receiver<BodyfileLine>) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        Self {
+            worker: Some(std::thread::spawn(move || Self::worker(reader, tx, options))),
+            rx: Some(rx),
+        }
+    }
+}
+
+impl Joinable<Result<(), MactimeError>> for JsonDecoder {
+    fn join(&mut self) -> std::thread::Result<Result<(), MactimeError>> {
+        self.worker.take().unwrap().join()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DocumentKey, JsonDecoder, KnownDocuments};
+    use crate::{BodyfileLine, Consumer, Joinable, Provider, RunOptions};
+    use bodyfile::Bodyfile3Line;
+    use chrono_tz::Tz;
+    use es4forensics::{objects::PosixFile, TimelineObject};
+    use std::convert::TryFrom;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_roundtrip() {
+        let options = RunOptions::default();
+        let (tx, rx) = mpsc::channel();
+        let mut decoder = JsonDecoder::with_receiver(rx, options);
+        let receiver = decoder.get_receiver();
+
+        let lines = [
+            Bodyfile3Line::new()
+                .with_name("/home")
+                .with_inode("1703937")
+                .with_uid(1000)
+                .with_gid(100)
+                .with_size(4096)
+                .with_atime(1666017593)
+                .with_mtime(1661772116)
+                .with_ctime(1661772116)
+                .with_crtime(1661774614),
+            Bodyfile3Line::new()
+                .with_name("/tmp/a|b")
+                .with_inode("12")
+                .with_mtime(1661772116),
+        ];
+
+        for line in lines.iter() {
+            for value in PosixFile::try_from((line, &Tz::UTC)).unwrap().into_values() {
+                tx.send(serde_json::to_string(&value).unwrap()).unwrap();
+            }
+        }
+        drop(tx);
+
+        let decoded: Vec<BodyfileLine> = receiver.iter().collect();
+        decoder.join().unwrap().unwrap();
+
+        assert_eq!(decoded.len(), lines.len());
+        for (expected, actual) in lines.iter().zip(decoded.iter()) {
+            assert_eq!(expected.to_string(), actual.to_string());
+        }
+    }
+
+    #[test]
+    fn test_sources() {
+        // identical files of different inputs are not merged
+        let (tx, rx) = mpsc::channel();
+        let mut decoder = JsonDecoder::with_receiver(rx, RunOptions::default());
+        let receiver = decoder.get_receiver();
+
+        let line = Bodyfile3Line::new().with_name("/etc/passwd").with_mtime(10).with_atime(20);
+        for source in [None, Some("host1"), Some("host1"), Some("host2")].iter() {
+            for mut value in PosixFile::try_from((&line, &Tz::UTC)).unwrap().into_values() {
+                if let Some(source) = source {
+                    value["labels"]["source"] = serde_json::json!(source);
+                }
+                tx.send(serde_json::to_string(&value).unwrap()).unwrap();
+            }
+        }
+        drop(tx);
+
+        assert_eq!(receiver.iter().count(), 3);
+        decoder.join().unwrap().unwrap();
+    }
+
+    #[test]
+    fn test_sorted_documents() {
+        let lines = [
+            Bodyfile3Line::new().with_name("/a").with_mtime(10).with_atime(40),
+            Bodyfile3Line::new().with_name("/b").with_mtime(20).with_atime(30),
+            Bodyfile3Line::new().with_name("/c").with_mtime(50),
+        ];
+        let mut documents: Vec<(i64, String)> = lines
+            .iter()
+            .flat_map(|line| PosixFile::try_from((line, &Tz::UTC)).unwrap().into_values())
+            .map(|value| (value["@timestamp"].as_i64().unwrap(), serde_json::to_string(&value).unwrap()))
+            .collect();
+        documents.sort();
+
+        let mut known_documents = KnownDocuments::default();
+        let mut decoded = Vec::new();
+        for (_, document) in documents {
+            let document = JsonDecoder::parse_document(&document).unwrap();
+            if known_documents.insert(DocumentKey::of(&document), document.timestamp) {
+                decoded.push(document.line.get_name().to_owned());
+            }
+        }
+        assert_eq!(decoded, vec!["/a", "/b", "/c"]);
+
+        // only the file whose last document has not been passed yet is kept
+        assert_eq!(known_documents.open.len(), 1);
+    }
+}
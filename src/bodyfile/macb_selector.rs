@@ -0,0 +1,157 @@
+use crate::MACBFlags;
+use std::str::FromStr;
+
+/// selects timeline entries by their [`MACBFlags`].
+///
+/// A selector can be given in two forms:
+///
+///  - as positional pattern like `m.c.`, which is compared with the flags
+///    as they are displayed. Every position may contain its letter (the flag
+///    must be set), `.` (the flag must not be set) or `?` (the flag is
+///    ignored). A trailing `*` ignores all remaining positions, so that
+///    `mac*` selects all entries with `m`, `a` and `c` set.
+///  - as set of letters like `b` or `mb`, which selects all entries where
+///    all of these flags are set.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct MacbSelector {
+    required: MACBFlags,
+    forbidden: MACBFlags,
+}
+
+const POSITIONS: [(char, MACBFlags); 4] = [
+    ('m', MACBFlags::M),
+    ('a', MACBFlags::A),
+    ('c', MACBFlags::C),
+    ('b', MACBFlags::B),
+];
+
+impl FromStr for MacbSelector {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let s = s.to_lowercase();
+        Self::parse_positional(&s)
+            .or_else(|| Self::parse_letters(&s))
+            .ok_or_else(|| {
+                format!(
+                    "invalid MACB selector '{}', expected something like 'm.c.', 'mac*' or 'b'",
+                    s
+                )
+            })
+    }
+}
+
+impl MacbSelector {
+    pub fn matches(&self, flags: MACBFlags) -> bool {
+        flags.contains(self.required) && !flags.intersects(self.forbidden)
+    }
+
+    fn parse_positional(s: &str) -> Option<Self> {
+        let (pattern, wildcard) = match s.strip_suffix('*') {
+            Some(pattern) => (pattern, true),
+            None => (s, false),
+        };
+        let chars: Vec<char> = pattern.chars().collect();
+        if chars.len() > POSITIONS.len() || (!wildcard && chars.len() != POSITIONS.len()) {
+            return None;
+        }
+
+        let mut selector = Self {
+            required: MACBFlags::NONE,
+            forbidden: MACBFlags::NONE,
+        };
+        for (c, (letter, flag)) in chars.into_iter().zip(POSITIONS.iter()) {
+            match c {
+                '.' => selector.forbidden |= *flag,
+                '?' => (),
+                c if c == *letter => selector.required |= *flag,
+                _ => return None,
+            }
+        }
+        Some(selector)
+    }
+
+    fn parse_letters(s: &str) -> Option<Self> {
+        if s.is_empty() {
+            return None;
+        }
+        let mut required = MACBFlags::NONE;
+        for c in s.chars() {
+            let (_, flag) = POSITIONS.iter().find(|(letter, _)| *letter == c)?;
+            required |= *flag;
+        }
+        Some(Self {
+            required,
+            forbidden: MACBFlags::NONE,
+        })
+    }
+}
+
+/// a list of [`MacbSelector`]s. An entry is accepted if it matches any of
+/// the selectors, or if there are no selectors at all.
+#[derive(Clone, Debug, Default)]
+pub struct MacbFilter(Vec<MacbSelector>);
+
+impl MacbFilter {
+    pub fn new(selectors: Vec<MacbSelector>) -> Self {
+        Self(selectors)
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.0.is_empty()
+    }
+
+    pub fn accepts(&self, flags: MACBFlags) -> bool {
+        self.is_empty() || self.0.iter().any(|s| s.matches(flags))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{MacbFilter, MacbSelector};
+    use crate::MACBFlags;
+
+    fn selector(s: &str) -> MacbSelector {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_letters() {
+        let birth = selector("b");
+        assert!(birth.matches(MACBFlags::B));
+        assert!(birth.matches(MACBFlags::M | MACBFlags::B));
+        assert!(!birth.matches(MACBFlags::M));
+        assert_eq!(selector("B"), birth);
+        assert_eq!(selector("bm"), selector("m??b"));
+    }
+
+    #[test]
+    fn test_positional() {
+        let mc = selector("m.c.");
+        assert!(mc.matches(MACBFlags::M | MACBFlags::C));
+        assert!(!mc.matches(MACBFlags::M | MACBFlags::C | MACBFlags::B));
+        assert!(!mc.matches(MACBFlags::M));
+
+        let mac = selector("mac*");
+        assert!(mac.matches(MACBFlags::M | MACBFlags::A | MACBFlags::C));
+        assert!(mac.matches(MACBFlags::all()));
+        assert!(!mac.matches(MACBFlags::M | MACBFlags::A));
+
+        assert!(selector("....").matches(MACBFlags::NONE));
+        assert!(selector("*").matches(MACBFlags::NONE));
+
+        for invalid in ["", "x", "am..", "m.c", "m.c..", "m*c"] {
+            assert!(invalid.parse::<MacbSelector>().is_err(), "{}", invalid);
+        }
+    }
+
+    #[test]
+    fn test_filter() {
+        assert!(MacbFilter::default().accepts(MACBFlags::M));
+
+        let filter = MacbFilter::new(vec![selector("b"), selector("macb")]);
+        assert!(filter.accepts(MACBFlags::B));
+        assert!(filter.accepts(MACBFlags::all()));
+        assert!(!filter.accepts(MACBFlags::M | MACBFlags::A));
+    }
+}
@@ -1,7 +1,21 @@
 mod bodyfile_decoder;
+mod bodyfile_line;
 mod bodyfile_reader;
 mod bodyfile_sorter;
+mod hash_filter;
+mod macb_selector;
+mod parse_report;
+mod path_filter;
+#[cfg(feature = "elastic")]
+mod json_decoder;
 
 pub use bodyfile_decoder::*;
+pub use bodyfile_line::*;
 pub use bodyfile_reader::*;
-pub use bodyfile_sorter::*;
\ No newline at end of file
+pub use bodyfile_sorter::*;
+pub use hash_filter::*;
+pub use macb_selector::*;
+pub use parse_report::*;
+pub use path_filter::*;
+#[cfg(feature = "elastic")]
+pub use json_decoder::*;
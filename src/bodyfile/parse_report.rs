@@ -0,0 +1,166 @@
+use std::fmt::{self, Display};
+use std::io::{self, Write};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+use crate::BodyfileLine;
+
+use crate::{MactimeError, OutputFile};
+
+/// counts the lines which have been parsed by the decoders, and writes all
+/// rejected lines into a sidecar file (if one has been specified).
+///
+/// A report can be cloned and shared between all decoder threads. Use
+/// [`ParseReport::for_input`] to create a report for a single input file, so
+/// that rejected lines can be associated with the file they have been read
+/// from. The rejects file contains one tab separated line per rejected line,
+/// with the input file, the line number, the parser error and the raw line.
+/// If lines are parsed by multiple threads, the rejected lines may not be
+/// ordered by their line number.
+#[derive(Clone, Default)]
+pub struct ParseReport {
+    shared: Arc<SharedReport>,
+    input: Option<Arc<str>>,
+}
+
+#[derive(Default)]
+struct SharedReport {
+    total_lines: AtomicUsize,
+    parsed_lines: AtomicUsize,
+    rejected_lines: AtomicUsize,
+    lines_without_timestamps: AtomicUsize,
+    ambiguous_timestamps: AtomicUsize,
+    nonexistent_timestamps: AtomicUsize,
+    rejects: Mutex<Option<OutputFile>>,
+    aborted: AtomicBool,
+}
+
+/// the number of lines which have been read by all decoders
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct ParseStatistics {
+    /// all lines, including comments
+    pub total_lines: usize,
+    pub parsed_lines: usize,
+    pub rejected_lines: usize,
+
+    /// lines which have been parsed, but do not contain any timestamp
+    pub lines_without_timestamps: usize,
+
+    /// distinct timestamps of all lines which occur twice in the source
+    /// timezone, because of a DST transition
+    pub ambiguous_timestamps: usize,
+
+    /// distinct timestamps of all lines which do not exist in the source
+    /// timezone, because of a DST transition
+    pub nonexistent_timestamps: usize,
+}
+
+impl Display for ParseStatistics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "read {} lines: {} parsed, {} rejected, {} without timestamps",
+            self.total_lines, self.parsed_lines, self.rejected_lines, self.lines_without_timestamps
+        )?;
+        if self.ambiguous_timestamps > 0 || self.nonexistent_timestamps > 0 {
+            write!(
+                f,
+                "; found {} ambiguous and {} nonexistent timestamps",
+                self.ambiguous_timestamps, self.nonexistent_timestamps
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl ParseReport {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// creates a report which writes all rejected lines into `rejects`
+    pub fn with_rejects(mut rejects: OutputFile) -> io::Result<Self> {
+        writeln!(rejects, "input\tline\terror\tcontent")?;
+        let report = Self::default();
+        *report.shared.rejects.lock().unwrap() = Some(rejects);
+        Ok(report)
+    }
+
+    /// returns a report which shares its counters and its rejects file with
+    /// this report, but labels rejected lines with `input`
+    pub fn for_input(&self, input: &str) -> Self {
+        Self {
+            shared: Arc::clone(&self.shared),
+            input: Some(input.into()),
+        }
+    }
+
+    pub(crate) fn count_line(&self) {
+        self.shared.total_lines.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_parsed(&self, line: &BodyfileLine) {
+        self.shared.parsed_lines.fetch_add(1, Ordering::Relaxed);
+        if line.timestamps().iter().all(Option::is_none) {
+            self.shared.lines_without_timestamps.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    pub(crate) fn count_ambiguous(&self) {
+        self.shared.ambiguous_timestamps.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn count_nonexistent(&self) {
+        self.shared.nonexistent_timestamps.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// counts a line which could not be parsed, and writes it into the
+    /// rejects file
+    pub(crate) fn reject(&self, line_number: usize, line: &str, error: &dyn Display) -> Result<(), MactimeError> {
+        self.shared.rejected_lines.fetch_add(1, Ordering::Relaxed);
+        if let Some(rejects) = self.shared.rejects.lock().unwrap().as_mut() {
+            writeln!(
+                rejects,
+                "{}\t{}\t{}\t{}",
+                self.input.as_deref().unwrap_or("-"),
+                line_number,
+                error,
+                line
+            )?;
+        }
+        Ok(())
+    }
+
+    /// marks that a decoder has stopped because of an error. This must be
+    /// called before the decoder closes its channel, so that the stages
+    /// which read from it do not take the truncated input for a complete one.
+    pub(crate) fn abort(&self) {
+        self.shared.aborted.store(true, Ordering::SeqCst);
+    }
+
+    /// returns `true` if any decoder has stopped because of an error. The
+    /// error itself is returned by the decoder.
+    pub fn is_aborted(&self) -> bool {
+        self.shared.aborted.load(Ordering::SeqCst)
+    }
+
+    pub fn statistics(&self) -> ParseStatistics {
+        ParseStatistics {
+            total_lines: self.shared.total_lines.load(Ordering::Relaxed),
+            parsed_lines: self.shared.parsed_lines.load(Ordering::Relaxed),
+            rejected_lines: self.shared.rejected_lines.load(Ordering::Relaxed),
+            lines_without_timestamps: self.shared.lines_without_timestamps.load(Ordering::Relaxed),
+            ambiguous_timestamps: self.shared.ambiguous_timestamps.load(Ordering::Relaxed),
+            nonexistent_timestamps: self.shared.nonexistent_timestamps.load(Ordering::Relaxed),
+        }
+    }
+
+    /// finishes the rejects file. This must be called after all decoders
+    /// have been joined.
+    pub fn finish(&self) -> io::Result<()> {
+        match self.shared.rejects.lock().unwrap().take() {
+            Some(rejects) => rejects.finish(),
+            None => Ok(()),
+        }
+    }
+}
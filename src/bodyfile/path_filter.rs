@@ -0,0 +1,226 @@
+use crate::{Consumer, Filter, Joinable, MactimeError, Provider, RunOptions, CHANNEL_CAPACITY};
+use crate::BodyfileLine;
+use glob::MatchOptions;
+use regex::Regex;
+use std::str::FromStr;
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::thread::JoinHandle;
+
+/// a pattern which is matched against the name of a bodyfile entry.
+///
+/// Patterns prefixed with `re:` are regular expressions, which match if they
+/// match any part of the name. All other patterns (optionally prefixed with
+/// `glob:`) are glob patterns, where `*` does not match `/`. A glob pattern
+/// matches a name if it matches the complete name or one of its parent
+/// directories, so that `/proc` matches `/proc/1/maps` as well. If a glob
+/// pattern does not start with `/`, it may match at any depth.
+#[derive(Clone, Debug)]
+pub enum PathPattern {
+    Regex(Regex),
+    Glob(glob::Pattern),
+}
+
+const GLOB_OPTIONS: MatchOptions = MatchOptions {
+    case_sensitive: true,
+    require_literal_separator: true,
+    require_literal_leading_dot: false,
+};
+
+impl FromStr for PathPattern {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Some(regex) = s.strip_prefix("re:") {
+            return Regex::new(regex)
+                .map(Self::Regex)
+                .map_err(|e| format!("invalid regular expression '{}': {}", regex, e));
+        }
+
+        let glob = s.strip_prefix("glob:").unwrap_or(s);
+        let glob = if glob.starts_with('/') || glob.starts_with("**") {
+            glob.to_owned()
+        } else {
+            format!("**/{}", glob)
+        };
+        glob::Pattern::new(&glob)
+            .map(Self::Glob)
+            .map_err(|e| format!("invalid glob pattern '{}': {}", s, e))
+    }
+}
+
+impl PathPattern {
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            Self::Regex(regex) => regex.is_match(name),
+            Self::Glob(pattern) => {
+                pattern.matches_with(name, GLOB_OPTIONS)
+                    || name
+                        .match_indices('/')
+                        .filter(|(idx, _)| *idx > 0)
+                        .any(|(idx, _)| pattern.matches_with(&name[..idx], GLOB_OPTIONS))
+            }
+        }
+    }
+}
+
+/// a file which contains one [`PathPattern`] per line. Empty lines and lines
+/// starting with `#` are ignored.
+#[derive(Clone, Debug)]
+pub struct PatternFile(Vec<PathPattern>);
+
+impl FromStr for PatternFile {
+    type Err = String;
+
+    fn from_str(path: &str) -> Result<Self, Self::Err> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("unable to read '{}': {}", path, e))?;
+
+        content
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'))
+            .map(PathPattern::from_str)
+            .collect::<Result<Vec<_>, _>>()
+            .map(Self)
+    }
+}
+
+impl From<PatternFile> for Vec<PathPattern> {
+    fn from(file: PatternFile) -> Self {
+        file.0
+    }
+}
+
+/// decides which entries are passed by [`PathFilter`]. If there are any
+/// include patterns, the name of an entry must match at least one of them.
+/// Entries whose names match any exclude pattern are always dropped.
+#[derive(Clone, Debug, Default)]
+pub struct PathFilterRules {
+    include: Vec<PathPattern>,
+    exclude: Vec<PathPattern>,
+}
+
+impl PathFilterRules {
+    pub fn new(include: Vec<PathPattern>, exclude: Vec<PathPattern>) -> Self {
+        Self { include, exclude }
+    }
+
+    pub fn with_include(mut self, pattern: PathPattern) -> Self {
+        self.include.push(pattern);
+        self
+    }
+
+    pub fn with_exclude(mut self, pattern: PathPattern) -> Self {
+        self.exclude.push(pattern);
+        self
+    }
+
+    /// returns `true` if there are no patterns at all, which means that all
+    /// entries will be passed
+    pub fn is_empty(&self) -> bool {
+        self.include.is_empty() && self.exclude.is_empty()
+    }
+
+    pub fn accepts(&self, name: &str) -> bool {
+        (self.include.is_empty() || self.include.iter().any(|p| p.matches(name)))
+            && !self.exclude.iter().any(|p| p.matches(name))
+    }
+}
+
+/// drops all bodyfile lines whose names are not accepted by the
+/// [`PathFilterRules`] in [`RunOptions::path_filter`]
+pub struct PathFilter {
+    worker: Option<JoinHandle<Result<(), MactimeError>>>,
+    rx: Option<Receiver<BodyfileLine>>,
+}
+
+impl Filter<BodyfileLine, BodyfileLine, Result<(), MactimeError>> for PathFilter {
+    fn worker(reader: Receiver<BodyfileLine>, tx: SyncSender<BodyfileLine>, options: RunOptions) -> Result<(), MactimeError> {
+        while let Ok(line) = reader.recv() {
+            if !options.path_filter.accepts(line.get_name()) {
+                continue;
+            }
+
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Provider<BodyfileLine, Result<(), MactimeError>> for PathFilter {
+    fn get_receiver(&mut self) -> Receiver<BodyfileLine> {
+        self.rx.take().unwrap()
+    }
+}
+
+impl Consumer<BodyfileLine> for PathFilter {
+    fn with_receiver(reader: Receiver<BodyfileLine>, options: RunOptions) -> Self {
+        let (tx, rx): (SyncSender<BodyfileLine>, Receiver<BodyfileLine>) = mpsc::sync_channel(CHANNEL_CAPACITY);
+        Self {
+            worker: Some(std::thread::spawn(move || Self::worker(reader, tx, options))),
+            rx: Some(rx),
+        }
+    }
+}
+
+impl Joinable<Result<(), MactimeError>> for PathFilter {
+    fn join(&mut self) -> std::thread::Result<Result<(), MactimeError>> {
+        self.worker.take().unwrap().join()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{PathFilterRules, PathPattern};
+
+    fn pattern(s: &str) -> PathPattern {
+        s.parse().unwrap()
+    }
+
+    #[test]
+    fn test_glob() {
+        assert!(pattern("/proc").matches("/proc"));
+        assert!(pattern("/proc").matches("/proc/1/maps"));
+        assert!(!pattern("/proc").matches("/procfs"));
+        assert!(!pattern("/proc").matches("/home/proc"));
+
+        let appdata = pattern("Users/*/AppData");
+        assert!(appdata.matches("C:/Users/alice/AppData/Local/Temp/x.tmp"));
+        assert!(appdata.matches("/Users/alice/AppData"));
+        assert!(!appdata.matches("/Users/alice/Documents/AppData"));
+
+        assert!(pattern("glob:*.tmp").matches("/tmp/a/b.tmp"));
+        assert!("[".parse::<PathPattern>().is_err());
+    }
+
+    #[test]
+    fn test_regex() {
+        let cache = pattern(r"re:/Cache(_Data)?/");
+        assert!(cache.matches("/home/alice/.cache/chromium/Default/Cache/Cache_Data/f_000001"));
+        assert!(!cache.matches("/home/alice/.cache"));
+        assert!("re:(".parse::<PathPattern>().is_err());
+    }
+
+    #[test]
+    fn test_rules() {
+        let rules = PathFilterRules::default();
+        assert!(rules.is_empty());
+        assert!(rules.accepts("/proc/1/maps"));
+
+        let rules = rules
+            .with_exclude(pattern("/proc"))
+            .with_exclude(pattern("/sys"));
+        assert!(!rules.accepts("/proc/1/maps"));
+        assert!(!rules.accepts("/sys"));
+        assert!(rules.accepts("/etc/passwd"));
+
+        let rules = rules
+            .with_include(pattern("/home"))
+            .with_exclude(pattern("re:\\.cache"));
+        assert!(rules.accepts("/home/alice/.bashrc"));
+        assert!(!rules.accepts("/home/alice/.cache/x"));
+        assert!(!rules.accepts("/etc/passwd"));
+    }
+}
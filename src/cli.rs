@@ -1,25 +1,36 @@
 
 use clap::{Parser};
-use crate::{OutputFormat};
+use std::path::PathBuf;
+use crate::external_sort::parse_size;
+use crate::output::parse_delimiter;
+use crate::{AmbiguousPolicy, CsvColumn, DateBound, DateFormat, GapPolicy, InputFormat, MacbSelector, Md5Set, OutputFormat, PathPattern, PatternFile, SkewSpec, TimestampPrecision};
 
 
 #[cfg(feature = "gzip")]
-const BODYFILE_HELP: &str = "path to input file or '-' for stdin (files ending with .gz will be treated as being gzipped)";
+const BODYFILE_HELP: &str = "path to input file or '-' for stdin (files ending with .gz will be treated as being gzipped). \
+This option can be specified multiple times, and glob patterns are expanded. Use `name=path` to label the entries read from path with name";
 #[cfg(not(feature = "gzip"))]
-const BODYFILE_HELP: &str = "path to input file or '-' for stdin";
+const BODYFILE_HELP: &str = "path to input file or '-' for stdin. \
+This option can be specified multiple times, and glob patterns are expanded. Use `name=path` to label the entries read from path with name";
 
 #[derive(Parser)]
 #[clap(author, version, about, long_about = None)]
 pub struct Cli {
-    #[clap(short('b'), default_value="-", help=BODYFILE_HELP, display_order(100))]
-    pub(crate) input_file: String,
+    #[clap(short('b'), value_name("INPUT_FILE"), default_value="-", help=BODYFILE_HELP, display_order(100))]
+    pub(crate) input_files: Vec<String>,
+
+    /// format of the input file. Use 'json' to read files which have been created using `--format=json`. Such files
+    /// contain neither MD5 hashes nor modes, so they are missing in the output
+    #[clap(short('I'), long("input-format"), value_enum, default_value="bodyfile", display_order(200))]
+    pub(crate) input_format: InputFormat,
 
     /// output format, if not specified, default value is 'txt'
     #[clap(short('F'), long("format"), value_enum, display_order(600))]
     pub(crate) output_format: Option<OutputFormat>,
 
     /// output as CSV instead of TXT. This is a conveniance option, which is identical to `--format=csv`
-    /// and will be removed in a future release. If you specified `--format` and `-d`, the latter will be ignored.
+    /// and will be removed in a future release. If you specified `--format` and `-d`, the latter will be ignored,
+    /// unless the format is 'mactime-compat', where `-d` selects comma delimited output like in `mactime`.
     #[clap(short('d'), display_order(610))]
     pub(crate) csv_format: bool,
 
@@ -28,22 +39,200 @@ pub struct Cli {
     #[clap(short('j'), display_order(620))]
     pub(crate) json_format: bool,
 
+    /// character which separates the fields of CSV output, like ';' for Excel in some locales, or 'tab'
+    #[clap(long("csv-delimiter"), value_name("CHAR"), value_parser=parse_delimiter, default_value=",", display_order(611))]
+    pub(crate) csv_delimiter: u8,
+
+    /// do not write a header line with the names of the columns into CSV output
+    #[clap(long("no-header"), display_order(612))]
+    pub(crate) no_header: bool,
+
+    /// write a UTF-8 byte order mark at the beginning of CSV output, so that Excel detects the encoding
+    #[clap(long("csv-bom"), display_order(613))]
+    pub(crate) csv_bom: bool,
+
+    /// comma separated list of the columns of CSV output, in the order in which they are written.
+    /// Other columns (like the source, the skew or the MD5 hash) are not added automatically if this option is specified
+    #[clap(long("csv-columns"), value_name("COLUMNS"), value_enum, value_delimiter(','), display_order(614))]
+    pub(crate) csv_columns: Option<Vec<CsvColumn>>,
+
+    /// with `--format=mactime-compat`, display dates in ISO 8601 format in UTC, like `mactime -y`
+    #[clap(short('y'), conflicts_with("mactime_month_number"), display_order(640))]
+    pub(crate) mactime_iso8601: bool,
+
+    /// with `--format=mactime-compat`, display the month as number instead of its name, like `mactime -m`
+    #[clap(short('m'), display_order(650))]
+    pub(crate) mactime_month_number: bool,
+
+    /// write the output into this file instead of stdout. The file is written atomically, so it will not
+    /// exist if mactime2 fails. If the name ends with '.gz', '.zst' or '.xz', the output is compressed
+    /// (if mactime2 has been built with the according feature). With `--format=elastic`, the `_bulk` request body
+    /// is written into this file. `--format=sqlite` requires this option, and writes an uncompressed database
+    #[clap(short('o'), long("output"), alias("bulk-file"), value_name("OUTPUT_FILE"), display_order(630))]
+    pub(crate) output: Option<PathBuf>,
+
     /// name of offset of source timezone (or 'list' to display all possible values
     #[clap(short('f'), long("from-timezone"), display_order(300))]
     pub(crate) src_zone: Option<String>,
 
+    /// how timestamps are handled which occur twice in the source timezone, because the clock has been turned back
+    /// at the end of DST. All affected entries are marked in the output
+    #[clap(long("dst-ambiguous"), value_name("POLICY"), value_enum, default_value="earliest", display_order(310))]
+    pub(crate) dst_ambiguous: AmbiguousPolicy,
+
+    /// how timestamps are handled which do not exist in the source timezone, because the clock has been turned
+    /// forward at the beginning of DST. All affected entries are marked in the output [default: mark, or error with
+    /// --strict]
+    #[clap(long("dst-gap"), value_name("POLICY"), value_enum, display_order(320))]
+    pub(crate) dst_gap: Option<GapPolicy>,
+
+    /// correct the clock of the input files by SKEW, like '+00:07:32' if the clock was 7 minutes and 32 seconds behind.
+    /// Use 'LABEL=SKEW' to correct only the input files with this label (or file name). The correction is applied
+    /// before the timezone conversion, and is displayed in an additional column. This option can be specified
+    /// multiple times
+    #[clap(long("skew"), value_name("[LABEL=]SKEW"), display_order(330))]
+    pub(crate) skew: Vec<SkewSpec>,
+
     /// name of offset of destination timezone (or 'list' to display all possible values
     #[clap(short('t'), long("to-timezone"), display_order(400))]
     pub(crate) dst_zone: Option<String>,
 
-    // /// convert only, but do not sort
-    // #[clap(short('c'), long("convert-only"), display_order(450))]
-    // pub(crate) dont_sort: bool,
+    /// only display entries at or after this date. The date must be given as 'yyyy-mm-dd',
+    /// 'yyyy-mm-ddThh:mm:ss' or in RFC3339 format. If it has no explicit offset, it is interpreted
+    /// in the destination timezone
+    #[clap(long("from"), value_name("DATE"), display_order(410))]
+    pub(crate) from: Option<DateBound>,
+
+    /// only display entries at or before this date. A date without time includes the whole day
+    #[clap(long("to"), value_name("DATE"), display_order(420))]
+    pub(crate) to: Option<DateBound>,
+
+    /// only display entries whose name matches PATTERN. Patterns prefixed with 're:' are regular expressions,
+    /// all other patterns are glob patterns. A glob pattern also matches all entries below a matching directory,
+    /// and matches at any depth if it does not start with '/'. This option can be specified multiple times
+    #[clap(long("include"), value_name("PATTERN"), display_order(430))]
+    pub(crate) include: Vec<PathPattern>,
+
+    /// read include patterns from FILE, which contains one pattern per line
+    #[clap(long("include-from"), value_name("FILE"), display_order(431))]
+    pub(crate) include_from: Vec<PatternFile>,
+
+    /// do not display entries whose name matches PATTERN. This option can be specified multiple times,
+    /// and takes precedence over `--include`
+    #[clap(long("exclude"), value_name("PATTERN"), display_order(440))]
+    pub(crate) exclude: Vec<PathPattern>,
+
+    /// read exclude patterns from FILE, which contains one pattern per line
+    #[clap(long("exclude-from"), value_name("FILE"), display_order(441))]
+    pub(crate) exclude_from: Vec<PatternFile>,
+
+    /// only display entries whose MACB flags match SELECTOR. SELECTOR is either a positional pattern like 'm.c.'
+    /// ('.' means not set, '?' means any value, and a trailing '*' ignores the remaining flags, like in 'mac*'), or a set
+    /// of flags which must be set, like 'b'. This option can be specified multiple times
+    #[clap(long("macb"), value_name("SELECTOR"), display_order(445))]
+    pub(crate) macb: Vec<MacbSelector>,
+
+    /// do not display entries whose MD5 hash is contained in FILE, which is either a NSRL RDS file ('NSRLFile.txt')
+    /// or contains one hash per line. This option can be specified multiple times
+    #[clap(long("known-good"), value_name("FILE"), display_order(446))]
+    pub(crate) known_good: Vec<Md5Set>,
+
+    /// mark entries whose MD5 hash is contained in FILE, which has the same format as with `--known-good`. Marked
+    /// entries are displayed with 'known-bad' in an additional column of TXT and CSV output, and with the tag
+    /// 'known-bad' in JSON output. This option can be specified multiple times
+    #[clap(long("known-bad"), value_name("FILE"), display_order(447))]
+    pub(crate) known_bad: Vec<Md5Set>,
+
+    /// convert only, but do not sort. Every entry is written as soon as it has been read, which is useful
+    /// if the input is already sorted, or if the timeline will be sorted by some other tool anyway
+    #[clap(short('c'), long("convert-only"), conflicts_with("memory_limit"), display_order(450))]
+    pub(crate) convert_only: bool,
+
+    /// write all lines which could not be parsed into FILE, together with their line number and the parser error
+    #[clap(long("rejects"), value_name("FILE"), display_order(490))]
+    pub(crate) rejects: Option<PathBuf>,
+
+    /// number of digits of the fraction of a second which are displayed. Bodyfiles may contain timestamps like
+    /// '1661772116.123456789', which are always sorted with full precision
+    #[clap(long("precision"), value_enum, default_value="s", display_order(495))]
+    pub(crate) precision: TimestampPrecision,
+
+    /// how timestamps are displayed in TXT and CSV output. FORMAT is either one of the presets 'rfc3339' (the
+    /// default), 'iso8601' (which uses 'Z' for UTC), 'excel' ('yyyy-mm-dd hh:mm:ss' without offset), 'epoch'
+    /// (seconds since 1970) and 'mactime' (like 'Thu Sep 01 2022 12:34:56'), or a strftime format string like
+    /// '%d.%m.%Y %H:%M:%S'
+    #[clap(long("date-format"), value_name("FORMAT"), display_order(494))]
+    pub(crate) date_format: Option<DateFormat>,
+
+    /// display the MD5 hash of every entry in an additional column of TXT and CSV output
+    #[clap(long("md5"), display_order(497))]
+    pub(crate) md5: bool,
+
+    /// display the mtime, atime, ctime and crtime of every entry in additional columns of TXT and CSV output.
+    /// They are displayed like in the bodyfile, as seconds since 1970 (after the skew has been applied), and '-1'
+    /// means that a timestamp is not set
+    #[clap(long("macb-times"), display_order(498))]
+    pub(crate) macb_times: bool,
+
+    /// text which is displayed instead of a missing timestamp in TXT and CSV output
+    /// [default: 0000-00-00T00:00:00+00:00]
+    #[clap(long("missing-timestamp"), value_name("TEXT"), display_order(496))]
+    pub(crate) missing_timestamp: Option<String>,
 
     /// strict mode: do not only warn, but abort if an error occurs
     #[clap(long("strict"), display_order(500))]
     pub(crate) strict_mode: bool,
 
+    /// approximate amount of memory (like '512M' or '2G') which may be used to sort the timeline. If the timeline
+    /// is larger, sorted parts of it are written into temporary files, which are merged afterwards
+    #[clap(long("memory-limit"), value_name("SIZE"), value_parser=parse_size, display_order(510))]
+    pub(crate) memory_limit: Option<usize>,
+
+    /// directory where temporary files are created if `--memory-limit` is exceeded
+    #[clap(long("temp-dir"), value_name("DIR"), requires("memory_limit"), display_order(520))]
+    pub(crate) temp_dir: Option<PathBuf>,
+
+    /// number of threads which are used to parse bodyfile lines. The order of the lines is kept, so the output
+    /// does not depend on the number of threads
+    #[clap(long("threads"), value_name("N"), default_value_t=1, value_parser=clap::value_parser!(u16).range(1..), display_order(530))]
+    pub(crate) threads: u16,
+
+    /// name of the index into which the documents are written (only used with `--format=elastic`)
+    #[cfg(feature = "elastic")]
+    #[clap(long("index"), default_value="mactime2", display_order(700))]
+    pub(crate) index_name: String,
+
+    /// URL of an elasticsearch or opensearch instance, to which the documents are sent using the `_bulk` API.
+    /// If this is specified, the request body is only written if `--output` is specified as well
+    #[cfg(feature = "elastic")]
+    #[clap(long("elastic-url"), display_order(720))]
+    pub(crate) elastic_url: Option<String>,
+
+    /// username used to authenticate at the elasticsearch instance
+    #[cfg(feature = "elastic")]
+    #[clap(long("elastic-user"), requires("elastic_url"), display_order(730))]
+    pub(crate) elastic_user: Option<String>,
+
+    /// password used to authenticate at the elasticsearch instance
+    #[cfg(feature = "elastic")]
+    #[clap(long("elastic-password"), requires("elastic_user"), display_order(740))]
+    pub(crate) elastic_password: Option<String>,
+
+    /// number of documents which are sent in one bulk request
+    #[cfg(feature = "elastic")]
+    #[clap(long("batch-size"), default_value_t=1000, display_order(750))]
+    pub(crate) batch_size: usize,
+
+    /// number of retries if a bulk request fails
+    #[cfg(feature = "elastic")]
+    #[clap(long("retries"), default_value_t=3, display_order(760))]
+    pub(crate) retries: usize,
+
+    /// delay (in milliseconds) before the first retry, which is increased with every further retry
+    #[cfg(feature = "elastic")]
+    #[clap(long("retry-delay"), default_value_t=1000, display_order(770))]
+    pub(crate) retry_delay: u64,
+
     #[clap(flatten)]
     pub(crate) verbose: clap_verbosity_flag::Verbosity,
 }
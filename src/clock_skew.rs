@@ -0,0 +1,153 @@
+use std::fmt;
+use std::str::FromStr;
+
+use crate::Timestamp;
+
+const NANOS_PER_SEC: i64 = 1_000_000_000;
+
+/// a correction which is added to all timestamps of a source, because its
+/// clock was wrong. If the clock was seven minutes behind, the skew is
+/// `+00:07:00`.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub struct ClockSkew {
+    nanos: i64,
+}
+
+impl ClockSkew {
+    pub fn from_nanos(nanos: i64) -> Self {
+        Self { nanos }
+    }
+
+    pub fn as_nanos(&self) -> i64 {
+        self.nanos
+    }
+
+    /// returns the corrected timestamp
+    pub fn apply(&self, timestamp: Timestamp) -> Timestamp {
+        let nanos = timestamp.secs() as i128 * NANOS_PER_SEC as i128 + timestamp.nanos() as i128 + self.nanos as i128;
+        let secs = nanos.div_euclid(NANOS_PER_SEC as i128) as i64;
+        Timestamp::new(secs, nanos.rem_euclid(NANOS_PER_SEC as i128) as u32)
+    }
+}
+
+/// parses a skew like `+00:07:32`, `-01:00:00` or `+00:00:01.5`. The sign
+/// may be omitted for positive values, and the hours may exceed 23.
+impl FromStr for ClockSkew {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid skew '{}', expected '+hh:mm:ss' or '-hh:mm:ss'", s);
+        let (sign, value) = match s.as_bytes().first() {
+            Some(b'-') => (-1, &s[1..]),
+            Some(b'+') => (1, &s[1..]),
+            _ => (1, s),
+        };
+        let parts: Vec<&str> = value.split(':').collect();
+        let (hours, minutes, seconds) = match parts[..] {
+            [hours, minutes, seconds] => (hours, minutes, seconds),
+            _ => return Err(invalid()),
+        };
+        let is_number = |s: &str| !s.is_empty() && s.bytes().all(|b| b.is_ascii_digit());
+        if !is_number(hours) || minutes.len() != 2 || !is_number(minutes) || !seconds.starts_with(|c: char| c.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let hours = hours.parse::<i64>().map_err(|_| invalid())?;
+        let minutes = minutes.parse::<i64>().map_err(|_| invalid())?;
+        let seconds = seconds.parse::<Timestamp>().map_err(|_| invalid())?;
+        if minutes >= 60 || seconds.secs() >= 60 {
+            return Err(invalid());
+        }
+
+        let nanos = (hours * 3600 + minutes * 60 + seconds.secs())
+            .checked_mul(NANOS_PER_SEC)
+            .and_then(|nanos| nanos.checked_add(seconds.nanos().into()))
+            .ok_or_else(invalid)?;
+        Ok(Self::from_nanos(sign * nanos))
+    }
+}
+
+/// displays the skew like `+00:07:32`, in the format which can be parsed
+/// again
+impl fmt::Display for ClockSkew {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let sign = if self.nanos < 0 { '-' } else { '+' };
+        let nanos = self.nanos.unsigned_abs();
+        let secs = nanos / NANOS_PER_SEC as u64;
+        write!(f, "{}{:02}:{:02}:{:02}", sign, secs / 3600, secs / 60 % 60, secs % 60)?;
+
+        let fraction = nanos % NANOS_PER_SEC as u64;
+        if fraction > 0 {
+            write!(f, ".{}", format!("{:09}", fraction).trim_end_matches('0'))?;
+        }
+        Ok(())
+    }
+}
+
+/// a skew as it is specified on the command line, which applies either to
+/// all inputs, or to the inputs with a specific label, like in
+/// `host1=+00:07:32`
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct SkewSpec {
+    pub label: Option<String>,
+    pub skew: ClockSkew,
+}
+
+impl FromStr for SkewSpec {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.rsplit_once('=') {
+            Some((label, skew)) if !label.is_empty() => Ok(Self {
+                label: Some(label.to_owned()),
+                skew: skew.parse()?,
+            }),
+            _ => Ok(Self {
+                label: None,
+                skew: s.parse()?,
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ClockSkew, SkewSpec};
+    use crate::Timestamp;
+
+    #[test]
+    fn test_parse() {
+        let skew: ClockSkew = "+00:07:32".parse().unwrap();
+        assert_eq!(skew.as_nanos(), 452_000_000_000);
+        assert_eq!(skew.to_string(), "+00:07:32");
+
+        let skew: ClockSkew = "-26:00:00.25".parse().unwrap();
+        assert_eq!(skew.as_nanos(), -93_600_250_000_000);
+        assert_eq!(skew.to_string(), "-26:00:00.25");
+
+        assert_eq!("00:00:01".parse::<ClockSkew>().unwrap().as_nanos(), 1_000_000_000);
+        for invalid in ["", "+7:32", "+00:7:32", "+00:60:00", "+00:00:60", "+00:00:-1", "+a:00:00", "1h"].iter() {
+            assert!(invalid.parse::<ClockSkew>().is_err(), "'{}' should be invalid", invalid);
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let skew: ClockSkew = "-00:00:00.5".parse().unwrap();
+        assert_eq!(skew.apply(Timestamp::new(10, 250_000_000)), Timestamp::new(9, 750_000_000));
+        assert_eq!(skew.apply(Timestamp::new(0, 0)), Timestamp::new(-1, 500_000_000));
+
+        let skew: ClockSkew = "+01:00:00".parse().unwrap();
+        assert_eq!(skew.apply(Timestamp::from(-3600)), Timestamp::from(0));
+    }
+
+    #[test]
+    fn test_spec() {
+        let spec: SkewSpec = "host1=-00:05:00".parse().unwrap();
+        assert_eq!(spec.label.as_deref(), Some("host1"));
+        assert_eq!(spec.skew.to_string(), "-00:05:00");
+
+        let spec: SkewSpec = "+00:05:00".parse().unwrap();
+        assert_eq!(spec.label, None);
+        assert!("host1=".parse::<SkewSpec>().is_err());
+    }
+}
@@ -0,0 +1,154 @@
+use std::str::FromStr;
+
+use chrono::format::{Item, StrftimeItems};
+use chrono::{DateTime, NaiveDateTime};
+use chrono_tz::Tz;
+
+use crate::{Timestamp, TimestampPrecision};
+
+/// how timestamps are displayed in TXT and CSV output
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum DateFormat {
+    /// RFC3339 with an explicit offset, like `2022-09-01T12:34:56+00:00`
+    #[default]
+    Rfc3339,
+
+    /// like [`DateFormat::Rfc3339`], but UTC timestamps use the suffix `Z`
+    /// instead of `+00:00`, like `2022-09-01T12:34:56Z`
+    Iso8601,
+
+    /// `2022-09-01 12:34:56`, without any offset, which can be read by Excel
+    Excel,
+
+    /// seconds since the epoch, like `1662035696`
+    Epoch,
+
+    /// the format of the original `mactime`, like `Thu Sep 01 2022 12:34:56`
+    Mactime,
+
+    /// a format string as it is understood by [`chrono::format::strftime`]
+    Custom(String),
+}
+
+impl DateFormat {
+    /// returns the names of all presets, which can be used instead of a
+    /// format string
+    pub fn presets() -> &'static [&'static str] {
+        &["rfc3339", "iso8601", "excel", "epoch", "mactime"]
+    }
+
+    /// displays `datetime` in this format. All presets append as many digits
+    /// of the fraction of a second as `precision` requires; custom formats
+    /// need to specify the fraction themselves (e.g. using `%.3f`)
+    pub fn format(&self, datetime: &DateTime<Tz>, precision: TimestampPrecision) -> String {
+        match self {
+            Self::Rfc3339 => datetime.to_rfc3339_opts(precision.seconds_format(), false),
+            Self::Iso8601 => datetime.to_rfc3339_opts(precision.seconds_format(), true),
+            Self::Excel | Self::Mactime => self.format_local(&datetime.naive_local(), precision),
+            Self::Epoch => {
+                let timestamp = Timestamp::new(datetime.timestamp(), datetime.timestamp_subsec_nanos());
+                format_epoch(timestamp, precision)
+            }
+            Self::Custom(format) => datetime.format(format).to_string(),
+        }
+    }
+
+    /// displays a local time which has no offset, because it does not exist
+    /// in its timezone. Formats which would need an offset (or the point in
+    /// time) display it like RFC3339 without the offset instead.
+    pub fn format_local(&self, datetime: &NaiveDateTime, precision: TimestampPrecision) -> String {
+        let format = match self {
+            Self::Excel => "%Y-%m-%d %H:%M:%S",
+            Self::Mactime => "%a %b %d %Y %H:%M:%S",
+            _ => "%Y-%m-%dT%H:%M:%S",
+        };
+        format!("{}{}", datetime.format(format), fraction(datetime.and_utc().timestamp_subsec_nanos(), precision))
+    }
+}
+
+/// parses either the name of a preset, or a format string which contains at
+/// least one specifier like `%Y`
+impl FromStr for DateFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "rfc3339" => Ok(Self::Rfc3339),
+            "iso8601" => Ok(Self::Iso8601),
+            "excel" => Ok(Self::Excel),
+            "epoch" => Ok(Self::Epoch),
+            "mactime" => Ok(Self::Mactime),
+            _ if !s.contains('%') => Err(format!(
+                "invalid date format '{}', expected a format string or one of {}",
+                s,
+                Self::presets().join(", ")
+            )),
+
+            // formatting would panic later if the format string is invalid
+            _ if StrftimeItems::new(s).any(|item| matches!(item, Item::Error)) => {
+                Err(format!("invalid format string: '{}'", s))
+            }
+            _ => Ok(Self::Custom(s.to_owned())),
+        }
+    }
+}
+
+fn fraction(nanos: u32, precision: TimestampPrecision) -> String {
+    match precision.digits() {
+        0 => String::new(),
+        digits => format!(".{:09}", nanos)[..=digits].to_owned(),
+    }
+}
+
+fn format_epoch(timestamp: Timestamp, precision: TimestampPrecision) -> String {
+    let timestamp = timestamp.truncate(precision);
+    if precision.digits() == 0 || timestamp.secs() >= 0 || timestamp.nanos() == 0 {
+        return format!("{}{}", timestamp.secs(), fraction(timestamp.nanos(), precision));
+    }
+
+    // the fraction of a negative timestamp moves it further into the past
+    let secs = timestamp.secs() + 1;
+    let sign = if secs == 0 { "-" } else { "" };
+    format!("{}{}{}", sign, secs, fraction(1_000_000_000 - timestamp.nanos(), precision))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::DateFormat;
+    use crate::{Timestamp, TimestampPrecision};
+    use chrono::TimeZone;
+    use chrono_tz::Tz;
+
+    fn format(format: &str, timestamp: Timestamp, precision: TimestampPrecision) -> String {
+        let datetime = Tz::UTC.timestamp_opt(timestamp.secs(), timestamp.nanos()).unwrap();
+        format.parse::<DateFormat>().unwrap().format(&datetime, precision)
+    }
+
+    #[test]
+    fn test_presets() {
+        let ts = Timestamp::new(1662035696, 123_456_789);
+        assert_eq!(format("rfc3339", ts, TimestampPrecision::Seconds), "2022-09-01T12:34:56+00:00");
+        assert_eq!(format("iso8601", ts, TimestampPrecision::Millis), "2022-09-01T12:34:56.123Z");
+        assert_eq!(format("excel", ts, TimestampPrecision::Seconds), "2022-09-01 12:34:56");
+        assert_eq!(format("mactime", ts, TimestampPrecision::Seconds), "Thu Sep 01 2022 12:34:56");
+        assert_eq!(format("epoch", ts, TimestampPrecision::Seconds), "1662035696");
+        assert_eq!(format("epoch", ts, TimestampPrecision::Micros), "1662035696.123456");
+        assert_eq!(format("%d.%m.%Y %H:%M", ts, TimestampPrecision::Nanos), "01.09.2022 12:34");
+    }
+
+    #[test]
+    fn test_negative_epoch() {
+        let ts: Timestamp = "-86400.25".parse().unwrap();
+        assert_eq!(format("epoch", ts, TimestampPrecision::Seconds), "-86401");
+        assert_eq!(format("epoch", ts, TimestampPrecision::Millis), "-86400.250");
+        let ts: Timestamp = "-0.5".parse().unwrap();
+        assert_eq!(format("epoch", ts, TimestampPrecision::Millis), "-0.500");
+    }
+
+    #[test]
+    fn test_invalid() {
+        assert!("rfc".parse::<DateFormat>().is_err());
+        assert!("%Y-%".parse::<DateFormat>().is_err());
+        assert!("%Q".parse::<DateFormat>().is_err());
+    }
+}
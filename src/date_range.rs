@@ -0,0 +1,156 @@
+use std::str::FromStr;
+
+use chrono::{DateTime, Duration, FixedOffset, LocalResult, NaiveDate, NaiveDateTime, NaiveTime, TimeZone, Utc};
+use chrono_tz::Tz;
+
+/// a date, as specified by the user for `--from` or `--to`. Dates without an
+/// explicit offset are interpreted in the destination timezone, because this
+/// is the timezone the user sees in the output.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DateBound {
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+    Absolute(DateTime<FixedOffset>),
+}
+
+impl FromStr for DateBound {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        if let Ok(d) = NaiveDate::parse_from_str(s, "%Y-%m-%d") {
+            return Ok(Self::Date(d));
+        }
+        for format in ["%Y-%m-%dT%H:%M:%S", "%Y-%m-%d %H:%M:%S"] {
+            if let Ok(d) = NaiveDateTime::parse_from_str(s, format) {
+                return Ok(Self::DateTime(d));
+            }
+        }
+        if let Ok(d) = DateTime::parse_from_rfc3339(s) {
+            return Ok(Self::Absolute(d));
+        }
+        Err(format!(
+            "invalid date '{}', expected 'yyyy-mm-dd', 'yyyy-mm-ddThh:mm:ss' or RFC3339",
+            s
+        ))
+    }
+}
+
+impl DateBound {
+    /// returns the first second which is covered by this date
+    pub fn start(&self, zone: &Tz) -> DateTime<Utc> {
+        match self {
+            Self::Date(d) => Self::from_local(&d.and_time(NaiveTime::MIN), zone),
+            Self::DateTime(d) => Self::from_local(d, zone),
+            Self::Absolute(d) => d.with_timezone(&Utc),
+        }
+    }
+
+    /// returns the first second which is not covered by this date anymore.
+    /// A date without time covers the whole day.
+    pub fn end(&self, zone: &Tz) -> DateTime<Utc> {
+        match self {
+            Self::Date(d) => match d.succ_opt() {
+                Some(next) => Self::from_local(&next.and_time(NaiveTime::MIN), zone),
+                None => DateTime::<Utc>::MAX_UTC,
+            },
+            _ => self.start(zone) + Duration::seconds(1),
+        }
+    }
+
+    fn from_local(d: &NaiveDateTime, zone: &Tz) -> DateTime<Utc> {
+        match zone.from_local_datetime(d) {
+            LocalResult::Single(t) | LocalResult::Ambiguous(t, _) => t.with_timezone(&Utc),
+
+            // the date is in a gap caused by a DST transition, so we use the
+            // first point in time after the gap
+            LocalResult::None => Self::from_local(&(*d + Duration::hours(1)), zone),
+        }
+    }
+}
+
+/// a range of time, which is used to filter timeline entries. The lower
+/// bound is inclusive, while the upper bound is exclusive.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DateRange {
+    from: Option<i64>,
+    to: Option<i64>,
+}
+
+impl DateRange {
+    pub fn new(from: Option<DateTime<Utc>>, to: Option<DateTime<Utc>>) -> Self {
+        Self {
+            from: from.map(|d| d.timestamp()),
+            to: to.map(|d| d.timestamp()),
+        }
+    }
+
+    /// creates a range which includes both `from` and `to`
+    pub fn with_bounds(from: Option<&DateBound>, to: Option<&DateBound>, zone: &Tz) -> Self {
+        Self::new(from.map(|d| d.start(zone)), to.map(|d| d.end(zone)))
+    }
+
+    pub fn is_unbounded(&self) -> bool {
+        self.from.is_none() && self.to.is_none()
+    }
+
+    /// returns `true` if no timestamp can be inside this range
+    pub fn is_empty(&self) -> bool {
+        matches!((self.from, self.to), (Some(from), Some(to)) if from >= to)
+    }
+
+    /// checks if a unix timestamp (in UTC) is inside this range
+    pub fn contains(&self, unix_ts: i64) -> bool {
+        self.from.map_or(true, |from| unix_ts >= from) && self.to.map_or(true, |to| unix_ts < to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DateBound, DateRange};
+    use chrono::{NaiveDate, TimeZone, Utc};
+    use chrono_tz::Tz;
+
+    #[test]
+    fn test_parse() {
+        assert_eq!(
+            "2022-08-29".parse::<DateBound>().unwrap(),
+            DateBound::Date(NaiveDate::from_ymd_opt(2022, 8, 29).unwrap())
+        );
+        assert_eq!(
+            "2022-08-29T12:03:34".parse::<DateBound>().unwrap(),
+            DateBound::DateTime(NaiveDate::from_ymd_opt(2022, 8, 29).unwrap().and_hms_opt(12, 3, 34).unwrap())
+        );
+        assert_eq!(
+            "2022-08-29 12:03:34".parse::<DateBound>().unwrap(),
+            DateBound::DateTime(NaiveDate::from_ymd_opt(2022, 8, 29).unwrap().and_hms_opt(12, 3, 34).unwrap())
+        );
+        assert!(matches!(
+            "2022-08-29T12:03:34+02:00".parse::<DateBound>().unwrap(),
+            DateBound::Absolute(_)
+        ));
+        assert!("29.08.2022".parse::<DateBound>().is_err());
+    }
+
+    #[test]
+    fn test_whole_days() {
+        let from: DateBound = "2022-08-29".parse().unwrap();
+        let to: DateBound = "2022-08-30".parse().unwrap();
+        let range = DateRange::with_bounds(Some(&from), Some(&to), &Tz::UTC);
+
+        let start = Utc.with_ymd_and_hms(2022, 8, 29, 0, 0, 0).unwrap().timestamp();
+        assert!(!range.contains(start - 1));
+        assert!(range.contains(start));
+        assert!(range.contains(start + 2 * 86400 - 1));
+        assert!(!range.contains(start + 2 * 86400));
+    }
+
+    #[test]
+    fn test_timezones() {
+        // the range is specified in Europe/Berlin, which is UTC+2 in August
+        let from: DateBound = "2022-08-29T12:00:00".parse().unwrap();
+        let range = DateRange::with_bounds(Some(&from), None, &Tz::Europe__Berlin);
+        let utc_start = Utc.with_ymd_and_hms(2022, 8, 29, 10, 0, 0).unwrap().timestamp();
+        assert!(range.contains(utc_start));
+        assert!(!range.contains(utc_start - 1));
+    }
+}
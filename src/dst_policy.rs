@@ -0,0 +1,246 @@
+use std::fmt;
+
+use chrono::{DateTime, Duration, LocalResult, NaiveDateTime, Offset, TimeZone};
+use chrono_tz::Tz;
+use clap::clap_derive::ValueEnum;
+
+use crate::Timestamp;
+
+/// how local timestamps are handled which occur twice, because the clock
+/// has been turned back at the end of daylight saving time
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum AmbiguousPolicy {
+    /// use the first occurrence, which is still in daylight saving time
+    #[default]
+    Earliest,
+
+    /// use the second occurrence, which is already in standard time
+    Latest,
+
+    /// display an entry for both occurrences
+    Both,
+
+    /// abort if an ambiguous timestamp is found
+    Error,
+}
+
+/// how local timestamps are handled which do not exist, because the clock
+/// has been turned forward at the beginning of daylight saving time
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum GapPolicy {
+    /// use the offset before the gap, which moves the timestamp forward by
+    /// the length of the gap
+    Shift,
+
+    /// abort if a nonexistent timestamp is found
+    Error,
+
+    /// display the local time as it is, without converting it
+    #[default]
+    Mark,
+}
+
+/// decides how timestamps are converted from the source timezone if they
+/// are affected by a DST transition
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct DstPolicy {
+    pub ambiguous: AmbiguousPolicy,
+
+    /// if no policy has been chosen, nonexistent timestamps are an error in
+    /// strict mode, and are marked otherwise (see [`DstPolicy::gap_policy`])
+    pub gap: Option<GapPolicy>,
+}
+
+/// marks an entry whose timestamp has been affected by a DST transition.
+/// Such entries are flagged in the output.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash)]
+pub enum DstMark {
+    /// the first occurrence of an ambiguous timestamp
+    Earliest,
+
+    /// the second occurrence of an ambiguous timestamp
+    Latest,
+
+    /// a nonexistent timestamp, which has been moved behind the gap
+    Shifted,
+
+    /// a nonexistent timestamp, which has not been converted
+    Nonexistent,
+}
+
+impl DstMark {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Earliest => "dst:earliest",
+            Self::Latest => "dst:latest",
+            Self::Shifted => "dst:shifted",
+            Self::Nonexistent => "dst:nonexistent",
+        }
+    }
+}
+
+impl fmt::Display for DstMark {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}]", self.as_str())
+    }
+}
+
+/// a timestamp of a bodyfile, together with the point in time it refers to
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ResolvedTimestamp {
+    /// the timestamp as it has been read, in the source timezone
+    pub local: Timestamp,
+
+    /// the timestamp in UTC, which is used to sort the timeline
+    pub instant: Timestamp,
+
+    pub mark: Option<DstMark>,
+}
+
+impl DstPolicy {
+    /// returns how nonexistent timestamps are handled. An explicitly chosen
+    /// policy always wins over strict mode.
+    pub fn gap_policy(&self, strict_mode: bool) -> GapPolicy {
+        match self.gap {
+            Some(gap) => gap,
+            None if strict_mode => GapPolicy::Error,
+            None => GapPolicy::default(),
+        }
+    }
+
+    /// converts a timestamp, which has been recorded in `zone`, into UTC.
+    /// This returns two timestamps if the timestamp is ambiguous and both
+    /// occurrences should be displayed.
+    ///
+    /// Timestamps which would cause an error are rejected by the decoders,
+    /// so they are resolved like with the default policies here.
+    pub fn resolve(&self, local: Timestamp, zone: &Tz) -> impl Iterator<Item = ResolvedTimestamp> {
+        let resolved = |instant: Timestamp, mark| ResolvedTimestamp { local, instant, mark };
+        let unchanged = (resolved(local, None), None);
+        let (first, second) = match local_datetime(&local) {
+            None => unchanged,
+            Some(_) if *zone == Tz::UTC => unchanged,
+            Some(datetime) => match zone.from_local_datetime(&datetime) {
+                LocalResult::Single(t) => (resolved(instant(&t, &local), None), None),
+                LocalResult::Ambiguous(t1, t2) => {
+                    let earliest = resolved(instant(&t1, &local), Some(DstMark::Earliest));
+                    let latest = resolved(instant(&t2, &local), Some(DstMark::Latest));
+                    match self.ambiguous {
+                        AmbiguousPolicy::Latest => (latest, None),
+                        AmbiguousPolicy::Both => (earliest, Some(latest)),
+                        AmbiguousPolicy::Earliest | AmbiguousPolicy::Error => (earliest, None),
+                    }
+                }
+                LocalResult::None => {
+                    let mark = match self.gap.unwrap_or_default() {
+                        GapPolicy::Shift => DstMark::Shifted,
+                        GapPolicy::Mark | GapPolicy::Error => DstMark::Nonexistent,
+                    };
+                    (resolved(instant(&shift(&datetime, zone), &local), Some(mark)), None)
+                }
+            },
+        };
+        std::iter::once(first).chain(second)
+    }
+}
+
+/// interprets a nonexistent local time using the offset which was valid
+/// before the gap
+pub(crate) fn shift(datetime: &NaiveDateTime, zone: &Tz) -> DateTime<Tz> {
+    let offset = zone
+        .offset_from_utc_datetime(&(*datetime - Duration::days(1)))
+        .fix()
+        .local_minus_utc();
+    zone.from_utc_datetime(&(*datetime - Duration::seconds(offset.into())))
+}
+
+/// converts a local time in `zone`, as `mark` requires, which has been
+/// determined by [`DstPolicy::resolve`]. Returns `None` if the local time
+/// does not exist and has not been shifted.
+pub(crate) fn from_local(local: &NaiveDateTime, mark: Option<DstMark>, zone: &Tz) -> Option<DateTime<Tz>> {
+    match (zone.from_local_datetime(local), mark) {
+        (LocalResult::Single(t), _) => Some(t),
+        (LocalResult::Ambiguous(_t1, t2), Some(DstMark::Latest)) => Some(t2),
+        (LocalResult::Ambiguous(t1, _t2), _) => Some(t1),
+        (LocalResult::None, Some(DstMark::Shifted)) => Some(shift(local, zone)),
+        (LocalResult::None, _) => None,
+    }
+}
+
+pub(crate) fn local_datetime(local: &Timestamp) -> Option<NaiveDateTime> {
+    DateTime::from_timestamp(local.secs(), local.nanos()).map(|t| t.naive_utc())
+}
+
+fn instant(datetime: &DateTime<Tz>, local: &Timestamp) -> Timestamp {
+    Timestamp::new(datetime.timestamp(), local.nanos())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AmbiguousPolicy, DstMark, DstPolicy, GapPolicy};
+    use crate::Timestamp;
+    use chrono_tz::Tz;
+
+    // 2022-10-30T02:30:00 occurred twice in Berlin
+    const AMBIGUOUS: i64 = 1667097000;
+
+    // 2022-03-27T02:30:00 did not exist in Berlin
+    const NONEXISTENT: i64 = 1648348200;
+
+    fn resolve(policy: DstPolicy, local: i64) -> Vec<(i64, Option<DstMark>)> {
+        policy
+            .resolve(local.into(), &Tz::Europe__Berlin)
+            .map(|r| (r.instant.secs(), r.mark))
+            .collect()
+    }
+
+    #[test]
+    fn test_ambiguous() {
+        let policy = |ambiguous| DstPolicy {
+            ambiguous,
+            ..Default::default()
+        };
+        let earliest = (AMBIGUOUS - 7200, Some(DstMark::Earliest));
+        let latest = (AMBIGUOUS - 3600, Some(DstMark::Latest));
+        assert_eq!(resolve(policy(AmbiguousPolicy::Earliest), AMBIGUOUS), vec![earliest]);
+        assert_eq!(resolve(policy(AmbiguousPolicy::Latest), AMBIGUOUS), vec![latest]);
+        assert_eq!(resolve(policy(AmbiguousPolicy::Both), AMBIGUOUS), vec![earliest, latest]);
+        assert_eq!(resolve(policy(AmbiguousPolicy::Both), AMBIGUOUS + 3600), vec![(AMBIGUOUS, None)]);
+    }
+
+    #[test]
+    fn test_gap() {
+        let policy = |gap| DstPolicy {
+            gap: Some(gap),
+            ..Default::default()
+        };
+
+        // 02:30 is interpreted as 02:30+01:00, which is 03:30+02:00
+        let shifted = NONEXISTENT - 3600;
+        assert_eq!(resolve(policy(GapPolicy::Shift), NONEXISTENT), vec![(shifted, Some(DstMark::Shifted))]);
+        assert_eq!(resolve(policy(GapPolicy::Mark), NONEXISTENT), vec![(shifted, Some(DstMark::Nonexistent))]);
+        assert_eq!(resolve(policy(GapPolicy::Mark), NONEXISTENT - 3600), vec![(NONEXISTENT - 7200, None)]);
+
+        let utc: Vec<_> = DstPolicy::default()
+            .resolve(Timestamp::new(NONEXISTENT, 5), &Tz::UTC)
+            .collect();
+        assert_eq!(utc.len(), 1);
+        assert_eq!(utc[0].instant, Timestamp::new(NONEXISTENT, 5));
+        assert_eq!(utc[0].mark, None);
+    }
+
+    #[test]
+    fn test_gap_policy() {
+        // strict mode is only the default, an explicit policy wins
+        assert_eq!(DstPolicy::default().gap_policy(false), GapPolicy::Mark);
+        assert_eq!(DstPolicy::default().gap_policy(true), GapPolicy::Error);
+        for gap in [GapPolicy::Shift, GapPolicy::Mark, GapPolicy::Error].iter().copied() {
+            let policy = DstPolicy {
+                gap: Some(gap),
+                ..Default::default()
+            };
+            assert_eq!(policy.gap_policy(false), gap);
+            assert_eq!(policy.gap_policy(true), gap);
+        }
+    }
+}
@@ -1,7 +1,136 @@
+use chrono_tz::Tz;
+use std::path::PathBuf;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum MactimeError {
     #[error("ambigious file name: '{0}'")]
     AmbiguousFilename(String),
-}
\ No newline at end of file
+
+    #[error("IO error: {0}")]
+    IoError(std::io::Error),
+
+    /// the output has been closed by the reader, e.g. by `head`
+    #[error("broken pipe")]
+    BrokenPipe,
+
+    #[error("bulk request failed: {0}")]
+    BulkRequestFailed(String),
+
+    /// an input line could not be read
+    #[error("unable to read line {line}: {source}")]
+    ReadError { line: usize, source: std::io::Error },
+
+    /// an input line could not be parsed (only in strict mode)
+    #[error("invalid line {line}: {message}")]
+    ParseError { line: usize, message: String },
+
+    /// a timestamp does not exist in the source timezone, e.g. because it
+    /// is inside of a DST gap (only in strict mode, or if
+    /// [`GapPolicy::Error`](crate::GapPolicy) has been chosen)
+    #[error("invalid timestamp in line {line}: {timestamp} does not exist in timezone {zone}")]
+    TimezoneError { line: usize, timestamp: i64, zone: Tz },
+
+    /// a timestamp occurs twice in the source timezone, because the clock
+    /// has been turned back (only if [`AmbiguousPolicy::Error`](crate::AmbiguousPolicy)
+    /// has been chosen)
+    #[error("ambiguous timestamp in line {line}: {timestamp} occurs twice in timezone {zone}")]
+    AmbiguousTimestamp { line: usize, timestamp: i64, zone: Tz },
+
+    /// a timeline entry could not be converted into JSON. Because lines
+    /// are converted after they have been decoded, the line number is not
+    /// known anymore, so the name of the entry is used instead.
+    #[error("unable to serialize '{name}': {message}")]
+    SerializationError { name: String, message: String },
+
+    /// an input has not been read completely, because its decoder stopped
+    /// with an error. Nothing is written in this case, because the timeline
+    /// would be incomplete.
+    #[error("an input has been aborted")]
+    InputAborted,
+
+    /// the SQLite database could not be written
+    #[cfg(feature = "sqlite")]
+    #[error("database error: {0}")]
+    DatabaseError(#[from] rusqlite::Error),
+}
+
+impl MactimeError {
+    /// returns the exit code of mactime2 if it fails because of this error:
+    ///
+    /// | code | reason                                    |
+    /// |------|-------------------------------------------|
+    /// | 1    | any other error                           |
+    /// | 2    | invalid command line or configuration     |
+    /// | 3    | input or output could not be read/written |
+    /// | 4    | invalid input line                        |
+    /// | 5    | invalid timestamp in the source timezone  |
+    /// | 6    | an entry could not be serialized          |
+    /// | 7    | a bulk request to elasticsearch failed    |
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::IoError(_) | Self::BrokenPipe | Self::ReadError { .. } => 3,
+            Self::AmbiguousFilename(_) | Self::ParseError { .. } => 4,
+            Self::TimezoneError { .. } | Self::AmbiguousTimestamp { .. } => 5,
+            Self::SerializationError { .. } => 6,
+            Self::BulkRequestFailed(_) => 7,
+            Self::InputAborted => 1,
+
+            #[cfg(feature = "sqlite")]
+            Self::DatabaseError(_) => 3,
+        }
+    }
+}
+
+impl From<std::io::Error> for MactimeError {
+    fn from(why: std::io::Error) -> Self {
+        match why.kind() {
+            std::io::ErrorKind::BrokenPipe => Self::BrokenPipe,
+            _ => Self::IoError(why),
+        }
+    }
+}
+
+/// errors which are detected while a
+/// [`Mactime2Application`](crate::Mactime2Application) is being configured
+#[derive(Error, Debug, PartialEq)]
+pub enum ConfigError {
+    #[error("missing configuration value: {0}")]
+    UninitializedField(&'static str),
+
+    #[error("invalid timezone: '{0}'")]
+    InvalidTimezone(String),
+
+    #[error("no input file has been specified")]
+    NoInput,
+
+    #[error("the number of threads must be at least 1")]
+    InvalidThreadCount,
+
+    #[error("the memory limit must be larger than 0")]
+    InvalidMemoryLimit,
+
+    #[error("the batch size must be larger than 0")]
+    InvalidBatchSize,
+
+    #[error("the directory '{}' does not exist", .0.display())]
+    InvalidTempDir(PathBuf),
+
+    #[error("the date range does not contain any point in time")]
+    EmptyDateRange,
+
+    #[error("more than one skew has been specified for {0}")]
+    DuplicateSkew(String),
+
+    #[error("'{0}' can only be used together with '{1}'")]
+    MissingOption(&'static str, &'static str),
+
+    #[error("'{0}' cannot be used together with '{1}'")]
+    ConflictingOptions(&'static str, &'static str),
+}
+
+impl From<derive_builder::UninitializedFieldError> for ConfigError {
+    fn from(why: derive_builder::UninitializedFieldError) -> Self {
+        Self::UninitializedField(why.field_name())
+    }
+}
@@ -0,0 +1,400 @@
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+
+use crate::Timestamp;
+
+/// options which control how timelines are sorted
+#[derive(Clone, Debug, Default)]
+pub struct SortOptions {
+    /// approximate number of bytes which may be used to buffer entries. If
+    /// this limit is exceeded, the buffered entries are sorted and written
+    /// into a temporary file, and all temporary files are merged at the end.
+    /// If this is `None`, all entries are kept in memory.
+    pub memory_limit: Option<usize>,
+
+    /// directory where temporary files are created. If this is `None`, the
+    /// default directory for temporary files is used.
+    pub temp_dir: Option<PathBuf>,
+
+    /// do not sort at all, but write every entry as soon as it has been read.
+    /// This is useful if the input is already sorted, or if the output will
+    /// be sorted by some other tool anyway.
+    pub convert_only: bool,
+}
+
+/// parses a size like `512M` or `2G`. Suffixes use powers of 1024.
+pub fn parse_size(s: &str) -> Result<usize, String> {
+    let s = s.trim();
+    let (digits, factor) = match s.char_indices().last() {
+        Some((idx, 'k' | 'K')) => (&s[..idx], 1 << 10),
+        Some((idx, 'm' | 'M')) => (&s[..idx], 1 << 20),
+        Some((idx, 'g' | 'G')) => (&s[..idx], 1 << 30),
+        Some((idx, 't' | 'T')) => (&s[..idx], 1 << 40),
+        _ => (s, 1),
+    };
+    digits
+        .parse::<usize>()
+        .ok()
+        .and_then(|n| n.checked_mul(factor))
+        .filter(|n| *n > 0)
+        .ok_or_else(|| format!("invalid size '{}', expected something like '512M' or '2G'", s))
+}
+
+/// a record which can be sorted by [`ExternalSorter`]
+pub(crate) trait SortRecord: Ord + Sized {
+    /// approximate number of bytes which this record occupies in memory
+    fn memory_size(&self) -> usize;
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    /// reads the next record, or returns `None` at the end of the file
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Self>>;
+}
+
+/// the maximum number of runs which are open at the same time, and thus
+/// also the maximum number of runs which are merged at once. Every run keeps
+/// its temporary file open, so this limits the number of open files.
+const MAX_OPEN_RUNS: usize = 64;
+
+/// the number of runs of the same level which are merged into a single run
+/// of the next level
+const RUNS_PER_LEVEL: usize = 16;
+
+/// sorts records which might not fit into memory. Records are buffered
+/// until the memory limit has been reached. Then, the buffer is sorted and
+/// written into a temporary file (a *run*). At the end, all runs are merged.
+///
+/// As soon as there are [`RUNS_PER_LEVEL`] runs of the same level, they are
+/// merged into a single run of the next level, so that every record is only
+/// merged a few times. If there are still [`MAX_OPEN_RUNS`] runs, which
+/// happens only for millions of runs, all runs are merged into one.
+pub(crate) struct ExternalSorter<T: SortRecord> {
+    options: SortOptions,
+    buffer: Vec<T>,
+    buffer_size: usize,
+
+    /// the runs together with their levels, where runs of higher levels
+    /// come first
+    runs: Vec<(usize, File)>,
+    count: usize,
+}
+
+impl<T: SortRecord> ExternalSorter<T> {
+    pub fn new(options: SortOptions) -> Self {
+        Self {
+            options,
+            buffer: Vec::new(),
+            buffer_size: 0,
+            runs: Vec::new(),
+            count: 0,
+        }
+    }
+
+    /// returns the number of records which have been pushed so far
+    pub fn len(&self) -> usize {
+        self.count
+    }
+
+    pub fn push(&mut self, record: T) -> io::Result<()> {
+        self.buffer_size += record.memory_size();
+        self.buffer.push(record);
+        self.count += 1;
+
+        match self.options.memory_limit {
+            Some(limit) if self.buffer_size >= limit => self.spill(),
+            _ => Ok(()),
+        }
+    }
+
+    fn spill(&mut self) -> io::Result<()> {
+        self.buffer.sort();
+        let records = std::mem::take(&mut self.buffer).into_iter().map(Ok);
+        let file = self.write_run(records)?;
+
+        log::debug!(
+            "wrote {} bytes of buffered entries into temporary file #{}",
+            self.buffer_size,
+            self.runs.len() + 1
+        );
+        self.runs.push((0, file));
+        self.buffer_size = 0;
+
+        while let Some(&(level, _)) = self.runs.last() {
+            if self.runs.len() < RUNS_PER_LEVEL || self.runs[self.runs.len() - RUNS_PER_LEVEL].0 != level {
+                break;
+            }
+            let file = self.merge_last_runs(RUNS_PER_LEVEL)?;
+            self.runs.push((level + 1, file));
+        }
+        if self.runs.len() >= MAX_OPEN_RUNS {
+            let level = self.runs[0].0 + 1;
+            let file = self.merge_last_runs(self.runs.len())?;
+            self.runs.push((level, file));
+        }
+        Ok(())
+    }
+
+    /// writes sorted records into a new temporary file
+    fn write_run<I: Iterator<Item = io::Result<T>>>(&self, records: I) -> io::Result<File> {
+        let file = match self.options.temp_dir.as_ref() {
+            Some(dir) => tempfile::tempfile_in(dir)?,
+            None => tempfile::tempfile()?,
+        };
+        let mut writer = BufWriter::new(file);
+        for record in records {
+            record?.write_to(&mut writer)?;
+        }
+        let mut file = writer.into_inner().map_err(|e| e.into_error())?;
+        file.seek(SeekFrom::Start(0))?;
+        Ok(file)
+    }
+
+    /// merges the last `count` runs into a new temporary file
+    fn merge_last_runs(&mut self, count: usize) -> io::Result<File> {
+        let runs = self
+            .runs
+            .split_off(self.runs.len() - count)
+            .into_iter()
+            .map(|(_, file)| Run::File(BufReader::new(file)))
+            .collect();
+        log::debug!("merging {} temporary files", count);
+        self.write_run(SortedRecords::new(runs)?)
+    }
+
+    /// returns all records in ascending order
+    pub fn into_sorted(mut self) -> io::Result<SortedRecords<T>> {
+
+        self.buffer.sort();
+        let mut runs: Vec<Run<T>> = self
+            .runs
+            .into_iter()
+            .map(|(_, file)| Run::File(BufReader::new(file)))
+            .collect();
+        runs.push(Run::Memory(self.buffer.into_iter()));
+        SortedRecords::new(runs)
+    }
+}
+
+enum Run<T> {
+    File(BufReader<File>),
+    Memory(std::vec::IntoIter<T>),
+}
+
+impl<T: SortRecord> Run<T> {
+    fn next_record(&mut self) -> io::Result<Option<T>> {
+        match self {
+            Self::File(reader) => T::read_from(reader),
+            Self::Memory(records) => Ok(records.next()),
+        }
+    }
+}
+
+/// merges the sorted runs of an [`ExternalSorter`]
+pub(crate) struct SortedRecords<T: SortRecord> {
+    runs: Vec<Run<T>>,
+    heap: BinaryHeap<Reverse<(T, usize)>>,
+}
+
+impl<T: SortRecord> SortedRecords<T> {
+    fn new(mut runs: Vec<Run<T>>) -> io::Result<Self> {
+        let mut heap = BinaryHeap::with_capacity(runs.len());
+        for (idx, run) in runs.iter_mut().enumerate() {
+            if let Some(record) = run.next_record()? {
+                heap.push(Reverse((record, idx)));
+            }
+        }
+        Ok(Self { runs, heap })
+    }
+}
+
+impl<T: SortRecord> Iterator for SortedRecords<T> {
+    type Item = io::Result<T>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let Reverse((record, idx)) = self.heap.pop()?;
+        match self.runs[idx].next_record() {
+            Ok(Some(next)) => self.heap.push(Reverse((next, idx))),
+            Ok(None) => (),
+            Err(why) => return Some(Err(why)),
+        }
+        Some(Ok(record))
+    }
+}
+
+pub(crate) fn write_i64<W: Write>(writer: &mut W, value: i64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn write_u64<W: Write>(writer: &mut W, value: u64) -> io::Result<()> {
+    writer.write_all(&value.to_le_bytes())
+}
+
+pub(crate) fn write_str<W: Write>(writer: &mut W, value: &str) -> io::Result<()> {
+    write_u64(writer, value.len() as u64)?;
+    writer.write_all(value.as_bytes())
+}
+
+/// reads the first value of a record, and returns `None` if the end of file
+/// has been reached before
+pub(crate) fn read_first_i64<R: Read>(reader: &mut R) -> io::Result<Option<i64>> {
+    let mut buffer = [0u8; 8];
+    let mut filled = 0;
+    while filled < buffer.len() {
+        match reader.read(&mut buffer[filled..])? {
+            0 if filled == 0 => return Ok(None),
+            0 => return Err(io::ErrorKind::UnexpectedEof.into()),
+            n => filled += n,
+        }
+    }
+    Ok(Some(i64::from_le_bytes(buffer)))
+}
+
+pub(crate) fn read_i64<R: Read>(reader: &mut R) -> io::Result<i64> {
+    let mut buffer = [0u8; 8];
+    reader.read_exact(&mut buffer)?;
+    Ok(i64::from_le_bytes(buffer))
+}
+
+pub(crate) fn read_u64<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut buffer = [0u8; 8];
+    reader.read_exact(&mut buffer)?;
+    Ok(u64::from_le_bytes(buffer))
+}
+
+pub(crate) fn read_string<R: Read>(reader: &mut R) -> io::Result<String> {
+    let mut buffer = vec![0u8; read_u64(reader)? as usize];
+    reader.read_exact(&mut buffer)?;
+    String::from_utf8(buffer).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+}
+
+/// the nanoseconds which are written instead of a missing timestamp
+const NO_TIMESTAMP: u64 = u64::MAX;
+
+/// writes a timestamp, which may be missing, as its seconds followed by its
+/// nanoseconds
+pub(crate) fn write_timestamp<W: Write>(writer: &mut W, value: Option<Timestamp>) -> io::Result<()> {
+    match value {
+        Some(ts) => {
+            write_i64(writer, ts.secs())?;
+            write_u64(writer, ts.nanos().into())
+        }
+        None => {
+            write_i64(writer, 0)?;
+            write_u64(writer, NO_TIMESTAMP)
+        }
+    }
+}
+
+pub(crate) fn read_timestamp<R: Read>(reader: &mut R) -> io::Result<Option<Timestamp>> {
+    let secs = read_i64(reader)?;
+    read_timestamp_nanos(reader, secs)
+}
+
+/// reads the remainder of a timestamp whose seconds have already been read,
+/// e.g. using [`read_first_i64`]
+pub(crate) fn read_timestamp_nanos<R: Read>(reader: &mut R, secs: i64) -> io::Result<Option<Timestamp>> {
+    match read_u64(reader)? {
+        NO_TIMESTAMP => Ok(None),
+        nanos if nanos < 1_000_000_000 => Ok(Some(Timestamp::new(secs, nanos as u32))),
+        nanos => Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            format!("invalid number of nanoseconds: {}", nanos),
+        )),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(PartialEq, Eq, PartialOrd, Ord, Debug)]
+    struct Record(i64, String);
+
+    impl SortRecord for Record {
+        fn memory_size(&self) -> usize {
+            8 + self.1.len()
+        }
+
+        fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+            write_i64(writer, self.0)?;
+            write_str(writer, &self.1)
+        }
+
+        fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+            match read_first_i64(reader)? {
+                None => Ok(None),
+                Some(ts) => Ok(Some(Self(ts, read_string(reader)?))),
+            }
+        }
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("1000"), Ok(1000));
+        assert_eq!(parse_size("512k"), Ok(512 * 1024));
+        assert_eq!(parse_size("2G"), Ok(2 * 1024 * 1024 * 1024));
+        assert!(parse_size("0").is_err());
+        assert!(parse_size("M").is_err());
+        assert!(parse_size("1.5G").is_err());
+    }
+
+    #[test]
+    fn test_timestamps() {
+        let values = [Some(Timestamp::new(-86400, 5)), None, Some(Timestamp::new(0, 0))];
+        let mut buffer = Vec::new();
+        for value in values.iter() {
+            write_timestamp(&mut buffer, *value).unwrap();
+        }
+        let mut reader = &buffer[..];
+        for value in values.iter() {
+            assert_eq!(read_timestamp(&mut reader).unwrap(), *value);
+        }
+        assert!(reader.is_empty());
+    }
+
+    #[test]
+    fn test_spill() {
+        let values: Vec<i64> = (0..1000).map(|i| (i * 7919) % 1000 - 500).collect();
+        let mut sorter = ExternalSorter::new(SortOptions {
+            memory_limit: Some(200),
+            ..Default::default()
+        });
+        for v in values.iter() {
+            sorter.push(Record(*v, format!("entry {}", v))).unwrap();
+        }
+        assert_eq!(sorter.len(), values.len());
+        assert!(sorter.runs.len() > 1);
+
+        let sorted: Vec<Record> = sorter.into_sorted().unwrap().map(Result::unwrap).collect();
+        let mut expected: Vec<Record> = values.iter().map(|v| Record(*v, format!("entry {}", v))).collect();
+        expected.sort();
+        assert_eq!(sorted, expected);
+    }
+    #[test]
+    fn test_many_runs() {
+        // every record is a run of its own
+        let values: Vec<i64> = (0..10_000).map(|i| (i * 7919) % 10_000).collect();
+        let mut sorter = ExternalSorter::new(SortOptions {
+            memory_limit: Some(1),
+            ..Default::default()
+        });
+        let mut open_runs = 0;
+        for v in values.iter() {
+            sorter.push(Record(*v, String::new())).unwrap();
+            open_runs = open_runs.max(sorter.runs.len());
+        }
+
+        // 10000 runs end up on four levels
+        assert!(open_runs < MAX_OPEN_RUNS, "{} runs are open", open_runs);
+        assert!(sorter.runs.iter().all(|(level, _)| *level < 4));
+
+        // the buffer is merged together with the runs
+        let sorted = sorter.into_sorted().unwrap();
+        assert!(sorted.runs.len() <= MAX_OPEN_RUNS);
+        let sorted: Vec<i64> = sorted.map(|r| r.unwrap().0).collect();
+        assert_eq!(sorted, (0..10_000).collect::<Vec<i64>>());
+    }
+}
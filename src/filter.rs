@@ -1,13 +1,72 @@
-use std::sync::mpsc::{Sender, Receiver};
+use std::sync::mpsc::{Receiver, SyncSender};
 
 use chrono_tz::Tz;
 
-#[derive(Copy, Clone)]
+use crate::{ClockSkew, DateRange, DstPolicy, HashFilterRules, MacbFilter, ParseReport, PathFilterRules, SortOptions, TimestampPrecision};
+
+#[derive(Clone)]
 pub struct RunOptions {
     pub strict_mode: bool,
-    pub src_zone: Tz
+    pub src_zone: Tz,
+
+    /// only entries with a timestamp inside this range will be emitted
+    pub range: DateRange,
+
+    /// used by [`PathFilter`](crate::PathFilter) to decide which entries are passed
+    pub path_filter: PathFilterRules,
+
+    /// used by [`HashFilter`](crate::HashFilter) to drop known-good and to
+    /// mark known-bad entries
+    pub hash_filter: HashFilterRules,
+
+    /// only entries with matching MACB flags will be emitted
+    pub macb_filter: MacbFilter,
+
+    /// used by the sorters to decide if entries are sorted in memory or on disk
+    pub sort_options: SortOptions,
+
+    /// number of threads used by [`BodyfileDecoder`](crate::BodyfileDecoder) to parse lines
+    pub threads: usize,
+
+    /// collects statistics and rejected lines of the decoders
+    pub report: ParseReport,
+
+    /// number of digits of the fraction of a second which are written by
+    /// the sorters
+    pub precision: TimestampPrecision,
+
+    /// decides how timestamps are converted from `src_zone` if they are
+    /// affected by a DST transition
+    pub dst_policy: DstPolicy,
+
+    /// correction which the decoders add to every timestamp they read
+    pub skew: Option<ClockSkew>,
 }
 
+impl Default for RunOptions {
+    fn default() -> Self {
+        Self {
+            strict_mode: false,
+            src_zone: Tz::UTC,
+            range: DateRange::default(),
+            path_filter: PathFilterRules::default(),
+            hash_filter: HashFilterRules::default(),
+            macb_filter: MacbFilter::default(),
+            sort_options: SortOptions::default(),
+            threads: 1,
+            report: ParseReport::default(),
+            precision: TimestampPrecision::default(),
+            dst_policy: DstPolicy::default(),
+            skew: None,
+        }
+    }
+}
+
+/// the number of items which may be queued between two stages of a pipeline.
+/// The inputs of a timeline are read one after another, so this bounds the
+/// memory which is used by all inputs which are not being read yet.
+pub const CHANNEL_CAPACITY: usize = 4096;
+
 pub trait Provider<To, R>: Joinable<R> {
     fn get_receiver(&mut self) -> Receiver<To>;
 }
@@ -17,7 +76,7 @@ pub trait Consumer<From> {
 }
 
 pub trait Filter<From, To, R> : Consumer<From> + Provider<To, R> {
-    fn worker(reader: Receiver<From>, tx: Sender<To>, options: RunOptions);
+    fn worker(reader: Receiver<From>, tx: SyncSender<To>, options: RunOptions) -> R;
 }
 
 pub trait Joinable<R> {
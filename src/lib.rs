@@ -1,21 +1,41 @@
-use anyhow::{Result};
-use chrono::offset::TimeZone;
-use chrono::{LocalResult, NaiveDateTime};
+use anyhow::{bail, Context, Result};
 use chrono_tz::Tz;
+use derive_builder::Builder;
+use std::convert::TryFrom;
+use std::path::PathBuf;
+use std::sync::mpsc::Receiver;
 
 pub mod bodyfile;
+mod clock_skew;
+mod date_format;
+mod date_range;
+mod dst_policy;
+mod external_sort;
 pub mod error;
 pub mod filter;
 mod output;
-//use derive_builder::Builder;
+pub use clock_skew::*;
+pub use date_format::*;
+pub use date_range::*;
+pub use dst_policy::*;
+pub use external_sort::SortOptions;
 pub use error::*;
 mod stream;
+mod timeline;
+mod timestamp;
 
 pub use crate::bodyfile::*;
 use crate::stream::*;
 use clap::clap_derive::ValueEnum;
 pub use filter::*;
+pub use timeline::*;
+pub use timestamp::*;
 use output::*;
+pub use output::{raw_timestamp, CsvColumn, CsvOptions, CsvOutput, JsonSorter, MactimeDates, MactimeOutput, OutputFile, SharedBuffer, TxtOutput};
+#[cfg(feature = "elastic")]
+pub use output::ElasticOptions;
+#[cfg(feature = "sqlite")]
+pub use output::SqliteOutput;
 mod cli;
 pub use cli::Cli;
 
@@ -33,78 +53,618 @@ pub enum OutputFormat {
     TXT,
     JSON,
 
+    /// the output of the original `mactime`
+    #[value(name = "mactime-compat")]
+    MactimeCompat,
+
     #[cfg(feature = "elastic")]
     ELASTIC,
+
+    /// a SQLite database, which must be written to a file
+    #[cfg(feature = "sqlite")]
+    SQLITE,
+}
+
+impl OutputFormat {
+    /// returns `true` if timestamps are displayed using a [`DateFormat`]
+    fn uses_date_format(&self) -> bool {
+        match self {
+            OutputFormat::CSV | OutputFormat::TXT => true,
+
+            #[cfg(feature = "sqlite")]
+            OutputFormat::SQLITE => true,
+
+            _ => false,
+        }
+    }
+}
+
+/// where a timeline is written to
+enum Output {
+    /// a textual timeline, which is created using [`Timeline`]
+    Text(Box<dyn Mactime2Writer>, OutputFile),
+
+    /// a database, which is filled using [`Timeline`]
+    #[cfg(feature = "sqlite")]
+    Database(SqliteOutput),
+
+    /// an output format which requires a special sorter
+    Sorter(SorterFormat, OutputFile),
 }
 
-//#[derive(Builder)]
+/// an output format which is written by a [`Sorter`] instead of [`Timeline`]
+enum SorterFormat {
+    Json,
+
+    #[cfg(feature = "elastic")]
+    Elastic,
+}
+
+/// the configuration of a mactime2 run. Use [`Mactime2Application::builder`]
+/// to create a validated configuration:
+///
+/// ```
+/// use libmactime2::{Mactime2Application, OutputFormat};
+///
+/// let app = Mactime2Application::builder()
+///     .bodyfile("sample.bodyfile")
+///     .format(OutputFormat::CSV)
+///     .src_zone(chrono_tz::Europe::Berlin)
+///     .threads(0_usize)
+///     .build();
+/// assert!(app.is_err());
+/// ```
+///
+/// Options which have not been set explicitly use the values of
+/// [`Mactime2Application::default`].
+#[derive(Builder)]
+#[builder(
+    pattern = "owned",
+    default,
+    build_fn(private, name = "build_unvalidated", error = "ConfigError")
+)]
 pub struct Mactime2Application {
+    input_format: InputFormat,
     format: OutputFormat,
-    bodyfile: Option<String>,
+
+    /// input files, which may be paths, glob patterns or `'-'` for stdin,
+    /// optionally prefixed with a label like in `host1=/cases/host1.bodyfile`
+    #[builder(setter(each(name = "bodyfile", into)))]
+    bodyfiles: Vec<String>,
+
+    /// file into which the timeline is written. If this is not set, the
+    /// timeline is written to stdout
+    #[builder(setter(into, strip_option))]
+    output: Option<PathBuf>,
+
+    /// file into which all lines are written which could not be parsed
+    #[builder(setter(into, strip_option))]
+    rejects: Option<PathBuf>,
+
     src_zone: Tz,
     dst_zone: Tz,
     strict_mode: bool,
+    range: DateRange,
+    path_filter: PathFilterRules,
+
+    /// known-good entries are dropped, and known-bad entries are marked
+    hash_filter: HashFilterRules,
+
+    macb_filter: MacbFilter,
+    sort_options: SortOptions,
+    threads: usize,
+
+    /// number of digits of the fraction of a second in the output
+    precision: TimestampPrecision,
+
+    /// how timestamps are displayed in TXT and CSV output
+    date_format: DateFormat,
+
+    /// decides how timestamps are converted from `src_zone` if they are
+    /// affected by a DST transition
+    dst_policy: DstPolicy,
+
+    /// corrections for the clocks of all inputs, or of the inputs with a
+    /// specific label
+    skew: Vec<SkewSpec>,
+
+    /// displays the MD5 hash of every entry in TXT and CSV output
+    md5_column: bool,
+
+    /// displays all four timestamps of every entry in TXT and CSV output
+    timestamp_columns: bool,
+
+    /// delimiter, header and columns of [`OutputFormat::CSV`]
+    csv_options: CsvOptions,
+
+    /// how dates are displayed with [`OutputFormat::MactimeCompat`]
+    mactime_dates: MactimeDates,
+
+    /// writes comma delimited output with [`OutputFormat::MactimeCompat`]
+    mactime_delimited: bool,
+
+    /// text which is displayed instead of a missing timestamp. If this is
+    /// not set, [`Mactime2Application::format_missing`] is used
+    #[builder(setter(into, strip_option))]
+    missing_timestamp: Option<String>,
+
+    #[cfg(feature = "elastic")]
+    elastic_options: ElasticOptions,
+}
+
+impl Mactime2ApplicationBuilder {
+    /// creates the application, after all options have been validated
+    pub fn build(self) -> Result<Mactime2Application, ConfigError> {
+        let app = self.build_unvalidated()?;
+        app.validate()?;
+        Ok(app)
+    }
 }
 
 impl Mactime2Application {
+    pub fn builder() -> Mactime2ApplicationBuilder {
+        Mactime2ApplicationBuilder::default()
+    }
 
-    fn create_sorter(&self, decoder: &mut BodyfileDecoder) -> Box<dyn Sorter<Result<(), MactimeError>>> {
-        let options = RunOptions {
-            strict_mode: self.strict_mode,
-            src_zone: self.src_zone,
+    /// checks for options which are invalid or cannot be combined
+    fn validate(&self) -> Result<(), ConfigError> {
+        if self.bodyfiles.is_empty() {
+            return Err(ConfigError::NoInput);
+        }
+        if self.threads == 0 {
+            return Err(ConfigError::InvalidThreadCount);
+        }
+        // the timeline and the rejected lines cannot both be written to stdout
+        let is_stdout = |path: &Option<PathBuf>| path.as_deref().map_or(true, |p| p.as_os_str() == "-");
+        if self.rejects.is_some() && is_stdout(&self.rejects) && is_stdout(&self.output) {
+            return Err(ConfigError::ConflictingOptions("rejects", "output"));
+        }
+        if self.range.is_empty() {
+            return Err(ConfigError::EmptyDateRange);
+        }
+        if !matches!(self.format, OutputFormat::CSV) && self.csv_options != CsvOptions::default() {
+            return Err(ConfigError::MissingOption("csv_options", "csv"));
+        }
+        #[cfg(feature = "sqlite")]
+        if matches!(self.format, OutputFormat::SQLITE) && is_stdout(&self.output) {
+            return Err(ConfigError::MissingOption("sqlite", "output"));
+        }
+        if !matches!(self.format, OutputFormat::MactimeCompat) {
+            if self.mactime_dates != MactimeDates::default() {
+                return Err(ConfigError::MissingOption("mactime_dates", "mactime_compat"));
+            }
+            if self.mactime_delimited {
+                return Err(ConfigError::MissingOption("mactime_delimited", "mactime_compat"));
+            }
+        }
+        for (index, spec) in self.skew.iter().enumerate() {
+            if self.skew[..index].iter().any(|other| other.label == spec.label) {
+                return Err(ConfigError::DuplicateSkew(spec.label.clone().unwrap_or_else(|| "all inputs".to_owned())));
+            }
+        }
+
+        let sort_options = &self.sort_options;
+        if sort_options.memory_limit == Some(0) {
+            return Err(ConfigError::InvalidMemoryLimit);
+        }
+        if sort_options.convert_only && sort_options.memory_limit.is_some() {
+            return Err(ConfigError::ConflictingOptions("convert_only", "memory_limit"));
+        }
+        if let Some(temp_dir) = sort_options.temp_dir.as_ref() {
+            if sort_options.memory_limit.is_none() {
+                return Err(ConfigError::MissingOption("temp_dir", "memory_limit"));
+            }
+            if !temp_dir.is_dir() {
+                return Err(ConfigError::InvalidTempDir(temp_dir.clone()));
+            }
+        }
+
+        #[cfg(feature = "elastic")]
+        {
+            // timestamps in JSON files are always in UTC
+            if matches!(self.input_format, InputFormat::JSON) && self.src_zone != Tz::UTC {
+                return Err(ConfigError::ConflictingOptions("input_format", "src_zone"));
+            }
+
+            // JSON documents do not contain the MD5 hashes of the files
+            if matches!(self.input_format, InputFormat::JSON) && !self.hash_filter.is_empty() {
+                return Err(ConfigError::ConflictingOptions("input_format", "hash_filter"));
+            }
+
+            let elastic_options = &self.elastic_options;
+            if elastic_options.batch_size == 0 {
+                return Err(ConfigError::InvalidBatchSize);
+            }
+            if elastic_options.username.is_some() && elastic_options.url.is_none() {
+                return Err(ConfigError::MissingOption("username", "url"));
+            }
+            if elastic_options.password.is_some() && elastic_options.username.is_none() {
+                return Err(ConfigError::MissingOption("password", "username"));
+            }
+        }
+        Ok(())
+    }
+
+    fn create_decoder(
+        &self,
+        reader: &mut BodyfileReader,
+        options: RunOptions,
+    ) -> Box<dyn Provider<BodyfileLine, Result<(), MactimeError>>> {
+        match self.input_format {
+            InputFormat::BODYFILE => Box::new(BodyfileDecoder::with_receiver(reader.get_receiver(), options)),
+
+            #[cfg(feature = "elastic")]
+            InputFormat::JSON => Box::new(JsonDecoder::with_receiver(reader.get_receiver(), options)),
+        }
+    }
+
+    fn create_sorter(
+        &self,
+        format: SorterFormat,
+        inputs: Vec<(Receiver<BodyfileLine>, Option<String>)>,
+        sink: OutputFile,
+        options: RunOptions,
+    ) -> Box<dyn Sorter<Result<(), MactimeError>>> {
+        match format {
+            SorterFormat::Json => Box::new(
+                inputs.into_iter().fold(JsonSorter::new(options), |sorter, (receiver, label)| {
+                    sorter.with_input(receiver, label.as_deref())
+                }).with_output_file(sink),
+            ),
+
+            #[cfg(feature = "elastic")]
+            SorterFormat::Elastic => Box::new(
+                inputs
+                    .into_iter()
+                    .fold(ElasticSorter::new(options), |sorter, (receiver, label)| {
+                        sorter.with_input(receiver, label.as_deref())
+                    })
+                    .with_elastic_options(self.elastic_options.clone())
+                    .with_output_file(sink),
+            ),
+        }
+    }
+
+    /// merges all inputs into a timeline, which is sorted while it is read
+    fn create_timeline(inputs: Vec<(Receiver<BodyfileLine>, Option<String>)>, options: RunOptions) -> Timeline {
+        inputs
+            .into_iter()
+            .fold(Timeline::new(options), |timeline, (receiver, label)| {
+                timeline.with_lines(receiver, label.as_deref())
+            })
+    }
+
+    /// creates the output file or database, before any input has been read,
+    /// so that we fail early if it cannot be written. The returned
+    /// [`PendingFile`] must be persisted after all parts of the pipeline
+    /// succeeded.
+    fn create_output(&self, input_files: &[InputFile], show_source: bool) -> Result<(Output, Option<PendingFile>)> {
+        #[cfg(not(feature = "sqlite"))]
+        let _ = input_files;
+
+        let sink = || OutputFile::create(self.output.as_deref());
+        let mut output = match self.format {
+            OutputFormat::CSV => Output::Text(Box::new(self.create_csv_output(show_source)), sink()?),
+            OutputFormat::TXT => Output::Text(Box::new(self.create_txt_output()), sink()?),
+            OutputFormat::MactimeCompat => Output::Text(Box::new(self.create_mactime_output()), sink()?),
+            OutputFormat::JSON => Output::Sorter(SorterFormat::Json, sink()?),
+
+            #[cfg(feature = "elastic")]
+            OutputFormat::ELASTIC => Output::Sorter(SorterFormat::Elastic, sink()?),
+
+            #[cfg(feature = "sqlite")]
+            OutputFormat::SQLITE => {
+                let path = self.output.as_deref().expect("the output of a database must be a file");
+                let database = SqliteOutput::create(path, self.src_zone, self.dst_zone)?
+                    .with_precision(self.precision)
+                    .with_date_format(self.date_format.clone());
+                for input_file in input_files {
+                    database.add_metadata("input", input_file.path())?;
+                    if show_source {
+                        database.add_metadata("label", &input_file.label())?;
+                    }
+                }
+                Output::Database(database)
+            }
         };
+        let pending_file = match &mut output {
+            Output::Text(_, sink) | Output::Sorter(_, sink) => sink.take_pending_file(),
 
-        if matches!(self.format, OutputFormat::JSON) {
-            Box::new(JsonSorter::with_receiver(decoder.get_receiver(), options))
-        } else {
-            let mut sorter = BodyfileSorter::default().with_receiver(decoder.get_receiver(), options);
+            #[cfg(feature = "sqlite")]
+            Output::Database(database) => database.take_pending_file(),
+        };
+        Ok((output, pending_file))
+    }
 
-            sorter = sorter.with_output(match self.format {
-                OutputFormat::CSV => Box::new(CsvOutput::new(self.src_zone, self.dst_zone)),
-                OutputFormat::TXT => Box::new(TxtOutput::new(self.src_zone, self.dst_zone)),
-                _ => panic!("invalid execution path"),
-            });
-            Box::new(sorter)
+    /// creates the writer of CSV timelines
+    fn create_csv_output(&self, show_source: bool) -> CsvOutput {
+        let csv_options = &self.csv_options;
+        let mut output = CsvOutput::new(self.src_zone, self.dst_zone)
+            .with_precision(self.precision)
+            .with_date_format(self.date_format.clone())
+            .with_delimiter(csv_options.delimiter)
+            .with_header(csv_options.header);
+        if let Some(placeholder) = self.missing_timestamp.as_ref() {
+            output = output.with_placeholder(placeholder);
+        }
+        if csv_options.bom {
+            output = output.with_bom();
+        }
+
+        // columns which have been selected by the user are not extended
+        match csv_options.columns.as_ref() {
+            Some(columns) => output = output.with_columns(columns.clone()),
+            None => {
+                if show_source {
+                    output = output.with_source_column();
+                }
+                if !self.skew.is_empty() {
+                    output = output.with_skew_column();
+                }
+                if self.md5_column {
+                    output = output.with_md5_column();
+                }
+                if self.timestamp_columns {
+                    output = output.with_timestamp_columns();
+                }
+                if self.hash_filter.has_known_bad() {
+                    output = output.with_known_bad_column();
+                }
+            }
         }
+        output
     }
 
-    pub fn run(&self) -> Result<()> {
-        let options = RunOptions {
+    /// creates the writer of TXT timelines
+    fn create_txt_output(&self) -> TxtOutput {
+        let mut output = TxtOutput::new(self.src_zone, self.dst_zone)
+            .with_precision(self.precision)
+            .with_date_format(self.date_format.clone());
+        if let Some(placeholder) = self.missing_timestamp.as_ref() {
+            output = output.with_placeholder(placeholder);
+        }
+        if !self.skew.is_empty() {
+            output = output.with_skew_column();
+        }
+        if self.md5_column {
+            output = output.with_md5_column();
+        }
+        if self.timestamp_columns {
+            output = output.with_timestamp_columns();
+        }
+        if self.hash_filter.has_known_bad() {
+            output = output.with_known_bad_column();
+        }
+        output
+    }
+
+    /// creates the writer of timelines like the original `mactime` writes them
+    fn create_mactime_output(&self) -> MactimeOutput {
+        let mut output = MactimeOutput::new(self.src_zone, self.dst_zone).with_dates(self.mactime_dates);
+        if self.mactime_delimited {
+            output = output.delimited();
+        }
+        output
+    }
+
+    fn run_options(&self) -> RunOptions {
+        RunOptions {
             strict_mode: self.strict_mode,
             src_zone: self.src_zone,
+            range: self.range,
+            path_filter: self.path_filter.clone(),
+            hash_filter: self.hash_filter.clone(),
+            macb_filter: self.macb_filter.clone(),
+            sort_options: self.sort_options.clone(),
+            threads: self.threads,
+            report: ParseReport::default(),
+            precision: self.precision,
+            dst_policy: self.dst_policy,
+            skew: None,
+        }
+    }
+
+    pub fn run(&self) -> Result<()> {
+        let options = self.run_options();
+
+        let mut input_files = Vec::new();
+        for spec in self.bodyfiles.iter() {
+            input_files.extend(InputFile::expand(spec)?);
+        }
+        if input_files.iter().filter(|f| f.path() == "-").count() > 1 {
+            bail!("stdin can be used only once as input");
+        }
+        for label in self.skew.iter().filter_map(|spec| spec.label.as_ref()) {
+            if !input_files.iter().any(|f| f.label() == *label) {
+                bail!("there is no input file with the label '{}'", label);
+            }
+        }
+
+        // the source of an entry is only displayed if there is more than one
+        // input file, or if the user explicitly specified a label
+        let show_source = input_files.len() > 1 || input_files.iter().any(|f| f.explicit_label().is_some());
+
+        // the output file is only renamed to its final name if all parts of
+        // the pipeline succeeded
+        let (output, pending_file) = self.create_output(&input_files, show_source)?;
+        let has_sorter = matches!(output, Output::Sorter(_, _));
+
+        let report = match self.rejects.as_deref() {
+            Some(rejects) => ParseReport::with_rejects(OutputFile::create(Some(rejects))?)?,
+            None => ParseReport::default(),
+        };
+
+        // all stages share the report, so that the outputs learn about
+        // decoders which failed before they write anything
+        let options = RunOptions {
+            report: report.clone(),
+            ..options
+        };
+
+        let mut pipelines = Vec::new();
+        let mut inputs = Vec::new();
+        for input_file in input_files.iter() {
+            let mut reader = <BodyfileReader as StreamReader<String, Result<(), MactimeError>>>::from(&Some(input_file.path().to_owned()))?;
+            let decoder_options = RunOptions {
+                report: report.for_input(input_file.path()),
+                skew: self.skew_of(input_file),
+                ..options.clone()
+            };
+            let mut decoders = vec![self.create_decoder(&mut reader, decoder_options)];
+
+            // hashes are filtered by their own stage for every output, while
+            // a timeline applies the path filter by itself
+            if !options.hash_filter.is_empty() {
+                let filter = HashFilter::with_receiver(decoders.last_mut().unwrap().get_receiver(), options.clone());
+                decoders.push(Box::new(filter));
+            }
+            if has_sorter && !options.path_filter.is_empty() {
+                let filter = PathFilter::with_receiver(decoders.last_mut().unwrap().get_receiver(), options.clone());
+                decoders.push(Box::new(filter));
+            }
+            let label = if show_source { Some(input_file.label()) } else { None };
+            inputs.push((decoders.last_mut().unwrap().get_receiver(), label));
+            pipelines.push((input_file.path(), reader, decoders));
+        }
+
+        let (written, sorter) = match output {
+            Output::Sorter(format, sink) => {
+                let mut sorter = self.create_sorter(format, inputs, sink, options);
+                sorter.run();
+                (Ok(()), Some(sorter))
+            }
+            Output::Text(writer, mut sink) => {
+                let written = Self::create_timeline(inputs, options)
+                    .write(writer.as_ref(), &mut sink)
+                    .and_then(|_| Ok(sink.finish()?));
+                (written, None)
+            }
+
+            #[cfg(feature = "sqlite")]
+            Output::Database(mut database) => {
+                let written = Self::create_timeline(inputs, options)
+                    .ordered()
+                    .and_then(|entries| database.write(entries))
+                    .and_then(|_| database.finish());
+                (written, None)
+            }
+        };
+
+        // all threads must be joined, but only the first error is reported
+        let mut result = Ok(());
+        for (path, mut reader, decoders) in pipelines {
+            let mut input_result = joined(reader.join());
+            for mut decoder in decoders {
+                input_result = input_result.and(joined(decoder.join()));
+            }
+            if result.is_ok() {
+                result = input_result.with_context(|| format!("unable to read '{}'", path));
+            }
+        }
+        let written = match sorter {
+            Some(mut sorter) => joined(sorter.join()),
+            None => written,
         };
 
-        let mut reader = <BodyfileReader as StreamReader<String, ()>>::from(&self.bodyfile)?;
-        let mut decoder = BodyfileDecoder::with_receiver(reader.get_receiver(), options);
-        let mut sorter = self.create_sorter(&mut decoder);
-        sorter.run();
+        // rejected lines are most interesting if mactime2 failed, so they are
+        // written even in that case
+        let rejects_written = report.finish();
+        let statistics = report.statistics();
+        if statistics.rejected_lines > 0 || statistics.ambiguous_timestamps > 0 || statistics.nonexistent_timestamps > 0 {
+            log::warn!("{}", statistics);
+        } else {
+            log::info!("{}", statistics);
+        }
+
+        result?;
+        written?;
+        rejects_written?;
 
-        let _ = reader.join();
-        let _ = decoder.join();
-        sorter.join().unwrap()?;
+        if let Some(pending_file) = pending_file {
+            pending_file.persist()?;
+        }
         Ok(())
     }
 
+    /// returns the skew which has been specified for the label of
+    /// `input_file`, or for all inputs
+    fn skew_of(&self, input_file: &InputFile) -> Option<ClockSkew> {
+        let label = input_file.label();
+        let skew_for = |label: Option<&str>| self.skew.iter().find(|spec| spec.label.as_deref() == label);
+        skew_for(Some(&label)).or_else(|| skew_for(None)).map(|spec| spec.skew)
+    }
+
     pub fn format_date(unix_ts: i64, src_zone: &Tz, dst_zone: &Tz) -> String {
-        if unix_ts >= 0 {
-            let src_timestamp =
-                match src_zone.from_local_datetime(&NaiveDateTime::from_timestamp_opt(unix_ts, 0).unwrap()) {
-                    LocalResult::None => {
-                        return "INVALID DATETIME".to_owned();
-                    }
-                    LocalResult::Single(t) => t,
-                    LocalResult::Ambiguous(t1, _t2) => t1,
-                };
-            let dst_timestamp = src_timestamp.with_timezone(dst_zone);
-            dst_timestamp.to_rfc3339()
-        } else {
-            "0000-00-00T00:00:00+00:00".to_owned()
+        Self::format_timestamp(&unix_ts.into(), TimestampPrecision::Seconds, src_zone, dst_zone)
+    }
+
+    /// formats a timestamp like [`Mactime2Application::format_date`], but
+    /// displays as many digits of the fraction of a second as `precision`
+    /// requires. Timestamps before 1970 are displayed as well.
+    pub fn format_timestamp(timestamp: &Timestamp, precision: TimestampPrecision, src_zone: &Tz, dst_zone: &Tz) -> String {
+        Self::format_resolved(timestamp, None, precision, &DateFormat::default(), src_zone, dst_zone)
+    }
+
+    /// formats a timestamp like [`Mactime2Application::format_timestamp`],
+    /// but converts it from `src_zone` as `mark` requires, which has been
+    /// determined by a [`DstPolicy`], and displays it using `date_format`.
+    /// Marked timestamps are followed by their mark, so that they can be
+    /// spotted in the output.
+    pub fn format_resolved(
+        timestamp: &Timestamp,
+        mark: Option<DstMark>,
+        precision: TimestampPrecision,
+        date_format: &DateFormat,
+        src_zone: &Tz,
+        dst_zone: &Tz,
+    ) -> String {
+        let local = match dst_policy::local_datetime(&timestamp.truncate(precision)) {
+            Some(local) => local,
+            None => return "INVALID DATETIME".to_owned(),
+        };
+        let src_timestamp = match (dst_policy::from_local(&local, mark, src_zone), mark) {
+            (Some(t), _) => t,
+
+            // the local time is displayed as it is, because it has no offset
+            (None, Some(DstMark::Nonexistent)) => {
+                return format!("{} {}", date_format.format_local(&local, precision), DstMark::Nonexistent);
+            }
+            (None, _) => {
+                return "INVALID DATETIME".to_owned();
+            }
+        };
+        let dst_timestamp = date_format.format(&src_timestamp.with_timezone(dst_zone), precision);
+        match mark {
+            Some(mark) => format!("{} {}", dst_timestamp, mark),
+            None => dst_timestamp,
         }
     }
+
+    /// returns the text which is displayed instead of a missing timestamp,
+    /// unless some other text has been configured
+    pub fn format_missing(precision: TimestampPrecision) -> String {
+        let fraction = match precision.digits() {
+            0 => String::new(),
+            digits => format!(".{}", "0".repeat(digits)),
+        };
+        format!("0000-00-00T00:00:00{}+00:00", fraction)
+    }
+}
+
+/// returns the result of a thread, and continues to panic if the thread panicked
+fn joined(result: std::thread::Result<Result<(), MactimeError>>) -> Result<(), MactimeError> {
+    result.unwrap_or_else(|why| std::panic::resume_unwind(why))
 }
 
-impl From<Cli> for Mactime2Application {
-    fn from(cli: Cli) -> Self {
+fn parse_zone(name: &str) -> Result<Tz, ConfigError> {
+    name.parse()
+        .map_err(|_| ConfigError::InvalidTimezone(name.to_owned()))
+}
+
+impl TryFrom<Cli> for Mactime2Application {
+    type Error = ConfigError;
+
+    fn try_from(cli: Cli) -> Result<Self, Self::Error> {
         let format = match cli.output_format {
             Some(f) => f,
             None => {
@@ -118,30 +678,137 @@ impl From<Cli> for Mactime2Application {
             }
         };
 
-        Self {
-            format,
-            bodyfile: Some(cli.input_file),
-            src_zone: cli
-                .src_zone
-                .map(|tz| tz.parse().unwrap())
-                .unwrap_or(Tz::UTC),
-            dst_zone: cli
-                .dst_zone
-                .map(|tz| tz.parse().unwrap())
-                .unwrap_or(Tz::UTC),
-            strict_mode: cli.strict_mode,
+        let mactime_delimited = cli.csv_format && matches!(format, OutputFormat::MactimeCompat);
+        if cli.date_format.is_some() && !format.uses_date_format() {
+            log::warn!("the date format is only used for TXT, CSV and SQLite output, ignoring it");
+        }
+        if (cli.md5 || cli.macb_times) && !matches!(format, OutputFormat::CSV | OutputFormat::TXT) {
+            log::warn!("additional columns are only displayed in TXT and CSV output, ignoring them");
+        }
+        if (cli.md5 || cli.macb_times) && cli.csv_columns.is_some() {
+            log::warn!("the columns of CSV output have been selected explicitly, use '--csv-columns' to add more columns");
         }
+
+        #[cfg(feature = "elastic")]
+        let src_zone = if matches!(cli.input_format, InputFormat::JSON) {
+            if cli.src_zone.is_some() {
+                log::warn!("timestamps in JSON files are always in UTC, ignoring the source timezone");
+            }
+            None
+        } else {
+            cli.src_zone
+        };
+
+        #[cfg(not(feature = "elastic"))]
+        let src_zone = cli.src_zone;
+
+        let src_zone = src_zone.as_deref().map(parse_zone).transpose()?.unwrap_or(Tz::UTC);
+        let dst_zone = cli.dst_zone.as_deref().map(parse_zone).transpose()?.unwrap_or(Tz::UTC);
+
+        let mut builder = Self::builder()
+            .input_format(cli.input_format)
+            .format(format)
+            .bodyfiles(cli.input_files)
+            .src_zone(src_zone)
+            .dst_zone(dst_zone)
+            .strict_mode(cli.strict_mode)
+            .range(DateRange::with_bounds(cli.from.as_ref(), cli.to.as_ref(), &dst_zone))
+            .path_filter(PathFilterRules::new(
+                cli.include.into_iter().chain(cli.include_from.into_iter().flat_map(Vec::from)).collect(),
+                cli.exclude.into_iter().chain(cli.exclude_from.into_iter().flat_map(Vec::from)).collect(),
+            ))
+            .hash_filter(HashFilterRules::new(
+                cli.known_good.into_iter().fold(Md5Set::default(), Md5Set::merge),
+                cli.known_bad.into_iter().fold(Md5Set::default(), Md5Set::merge),
+            ))
+            .macb_filter(MacbFilter::new(cli.macb))
+            .sort_options(SortOptions {
+                memory_limit: cli.memory_limit,
+                temp_dir: cli.temp_dir,
+                convert_only: cli.convert_only,
+            })
+            .threads(cli.threads.into())
+            .precision(cli.precision)
+            .mactime_dates(if cli.mactime_iso8601 {
+                MactimeDates::Iso8601
+            } else if cli.mactime_month_number {
+                MactimeDates::Numbers
+            } else {
+                MactimeDates::Words
+            })
+            .mactime_delimited(mactime_delimited)
+            .md5_column(cli.md5)
+            .timestamp_columns(cli.macb_times)
+            .csv_options(CsvOptions {
+                delimiter: cli.csv_delimiter,
+                header: !cli.no_header,
+                bom: cli.csv_bom,
+                columns: cli.csv_columns,
+            })
+            .skew(cli.skew)
+            .date_format(cli.date_format.clone().unwrap_or_default())
+            .dst_policy(DstPolicy {
+                ambiguous: cli.dst_ambiguous,
+                gap: cli.dst_gap,
+            });
+
+        if let Some(output) = cli.output {
+            builder = builder.output(output);
+        }
+        if let Some(rejects) = cli.rejects {
+            builder = builder.rejects(rejects);
+        }
+        if let Some(missing_timestamp) = cli.missing_timestamp {
+            builder = builder.missing_timestamp(missing_timestamp);
+        }
+
+        #[cfg(feature = "elastic")]
+        {
+            builder = builder.elastic_options(ElasticOptions {
+                index_name: cli.index_name,
+                url: cli.elastic_url,
+                username: cli.elastic_user,
+                password: cli.elastic_password,
+                batch_size: cli.batch_size,
+                retries: cli.retries,
+                retry_delay: std::time::Duration::from_millis(cli.retry_delay),
+            });
+        }
+
+        builder.build()
     }
 }
 
 impl Default for Mactime2Application {
     fn default() -> Self {
         Self {
+            input_format: InputFormat::BODYFILE,
             format: OutputFormat::CSV,
-            bodyfile: None,
+            bodyfiles: vec!["-".to_owned()],
+            output: None,
+            rejects: None,
             src_zone: Tz::UTC,
             dst_zone: Tz::UTC,
             strict_mode: false,
+            range: DateRange::default(),
+            path_filter: PathFilterRules::default(),
+            hash_filter: HashFilterRules::default(),
+            macb_filter: MacbFilter::default(),
+            sort_options: SortOptions::default(),
+            threads: 1,
+            precision: TimestampPrecision::default(),
+            date_format: DateFormat::default(),
+            dst_policy: DstPolicy::default(),
+            skew: Vec::new(),
+            md5_column: false,
+            timestamp_columns: false,
+            csv_options: CsvOptions::default(),
+            mactime_dates: MactimeDates::default(),
+            mactime_delimited: false,
+            missing_timestamp: None,
+
+            #[cfg(feature = "elastic")]
+            elastic_options: ElasticOptions::default(),
         }
     }
 }
@@ -1,10 +1,19 @@
 use anyhow::Result;
 use clap::Parser;
 use simplelog::{TermLogger, Config, TerminalMode, ColorChoice};
-use libmactime2::{Mactime2Application, Cli};
+use libmactime2::{Mactime2Application, Cli, ConfigError, MactimeError};
 use chrono_tz::TZ_VARIANTS;
+use std::convert::TryFrom;
+use std::io::{stdout, Write};
 
-fn main() -> Result<()> {
+fn main() {
+    if let Err(why) = ignore_broken_pipe(run()) {
+        eprintln!("Error: {:?}", why);
+        std::process::exit(exit_code(&why));
+    }
+}
+
+fn run() -> Result<()> {
     let cli = Cli::parse();
 
     let _ = TermLogger::init(
@@ -13,21 +22,41 @@ fn main() -> Result<()> {
         TerminalMode::Stderr,
         ColorChoice::Auto);
 
-    let _list = "list".to_owned();
-    if matches!(cli.src_zone(), Some(_list)) {
-        display_zones(); return Ok(());
-    }
-    if matches!(cli.dst_zone(), Some(_list)) {
-        display_zones(); return Ok(());
+    if cli.src_zone().as_deref() == Some("list") || cli.dst_zone().as_deref() == Some("list") {
+        return display_zones();
     }
 
-    let app: Mactime2Application = cli.into();
+    let app = Mactime2Application::try_from(cli)?;
 
     app.run()
 }
 
-fn display_zones() {
+/// see [`MactimeError::exit_code`] for a list of all exit codes
+fn exit_code(why: &anyhow::Error) -> i32 {
+    if let Some(why) = why.downcast_ref::<MactimeError>() {
+        why.exit_code()
+    } else if why.is::<ConfigError>() {
+        2
+    } else if why.is::<std::io::Error>() {
+        3
+    } else {
+        1
+    }
+}
+
+/// if the reader of our output has gone away (like `head` does), there is
+/// nothing left to do, but this is not an error
+fn ignore_broken_pipe(result: Result<()>) -> Result<()> {
+    match result {
+        Err(why) if matches!(why.downcast_ref(), Some(MactimeError::BrokenPipe)) => Ok(()),
+        result => result,
+    }
+}
+
+fn display_zones() -> Result<()> {
+    let mut stdout = stdout().lock();
     for v in TZ_VARIANTS.iter() {
-        println!("{}", v);
+        writeln!(stdout, "{}", v).map_err(MactimeError::from)?;
     }
+    Ok(())
 }
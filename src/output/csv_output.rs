@@ -1,45 +1,355 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+
 use chrono_tz::Tz;
-use crate::{Mactime2Application, Mactime2Writer};
+use clap::clap_derive::ValueEnum;
+use crate::{DateFormat, Mactime2Application, Mactime2Writer, SharedBuffer, Timestamp, TimestampPrecision};
 use crate::bodyfile::*;
 
+/// the byte order mark of UTF-8, which Excel needs to detect the encoding
+const BOM: &[u8] = b"\xEF\xBB\xBF";
+
+/// marks entries whose hashes are known-bad, also as tag in JSON output
+pub(crate) const KNOWN_BAD: &str = "known-bad";
+
+/// a column of [`CsvOutput`]
+#[derive(ValueEnum, Clone, Copy, Debug, PartialEq, Eq)]
+pub enum CsvColumn {
+    Date,
+    Size,
+    Type,
+    Mode,
+    Uid,
+    Gid,
+    Meta,
+    Name,
+
+    /// the MD5 hash of the file, if it has been computed by the collector
+    Md5,
+
+    /// the modification time of the file, as it is written in a bodyfile
+    Mtime,
+
+    /// the access time of the file, as it is written in a bodyfile
+    Atime,
+
+    /// the time when the metadata of the file has been changed, as it is
+    /// written in a bodyfile
+    Ctime,
+
+    /// the creation time of the file, as it is written in a bodyfile
+    Crtime,
+
+    /// the label of the input which the entry has been read from
+    Source,
+
+    /// the correction which has been applied to the timestamps of the entry
+    Skew,
+
+    /// `known-bad` if the MD5 hash of the entry is known-bad, or empty
+    KnownBad,
+}
+
+impl CsvColumn {
+    /// the columns which are written if no other columns have been selected
+    pub fn defaults() -> Vec<Self> {
+        vec![
+            Self::Date,
+            Self::Size,
+            Self::Type,
+            Self::Mode,
+            Self::Uid,
+            Self::Gid,
+            Self::Meta,
+            Self::Name,
+        ]
+    }
+
+    /// the name of the column in the header line, which matches the header
+    /// of `mactime -d`
+    pub fn header(&self) -> &'static str {
+        match self {
+            Self::Date => "Date",
+            Self::Size => "Size",
+            Self::Type => "Type",
+            Self::Mode => "Mode",
+            Self::Uid => "UID",
+            Self::Gid => "GID",
+            Self::Meta => "Meta",
+            Self::Name => "File Name",
+            Self::Md5 => "MD5",
+            Self::Mtime => "Mtime",
+            Self::Atime => "Atime",
+            Self::Ctime => "Ctime",
+            Self::Crtime => "Crtime",
+            Self::Source => "Source",
+            Self::Skew => "Skew",
+            Self::KnownBad => "Known Bad",
+        }
+    }
+}
+
+/// options of CSV output which are set by the user
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct CsvOptions {
+    /// the character which separates the fields
+    pub delimiter: u8,
+
+    /// writes a line with the names of all columns before the first entry
+    pub header: bool,
+
+    /// writes a UTF-8 byte order mark at the beginning of the output
+    pub bom: bool,
+
+    /// the columns which are written, and their order. If this is `None`,
+    /// the default columns are written, together with the source and skew
+    /// columns if they are needed.
+    pub columns: Option<Vec<CsvColumn>>,
+}
+
+impl Default for CsvOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: b',',
+            header: true,
+            bom: false,
+            columns: None,
+        }
+    }
+}
+
+/// displays a timestamp like it is written in a bodyfile, which means as
+/// seconds since 1970 with an optional fraction, or `-1` if it is not set
+pub fn raw_timestamp(timestamp: Option<Timestamp>) -> String {
+    timestamp.map_or_else(|| "-1".to_owned(), |ts| ts.to_string())
+}
+
+/// returns the text which marks entries whose hashes are known-bad
+pub(crate) fn known_bad_marker(entry: &ListEntry) -> &'static str {
+    if entry.line.is_known_bad() {
+        KNOWN_BAD
+    } else {
+        ""
+    }
+}
+
+/// parses the delimiter of CSV output, which must be a single ASCII
+/// character or `tab`
+pub(crate) fn parse_delimiter(s: &str) -> Result<u8, String> {
+    match s.as_bytes() {
+        b"tab" | b"\\t" => Ok(b'\t'),
+        [delimiter] if delimiter.is_ascii() && *delimiter != b'"' && *delimiter != b'\n' => Ok(*delimiter),
+        _ => Err(format!("invalid delimiter '{}', expected a single ASCII character or 'tab'", s)),
+    }
+}
+
+/// writes the timeline as CSV according to RFC 4180, which means that fields
+/// are quoted if they contain the delimiter, a quote or a line break
 pub struct CsvOutput {
-    src_zone: Tz, dst_zone: Tz
+    src_zone: Tz, dst_zone: Tz,
+    precision: TimestampPrecision,
+    date_format: DateFormat,
+    placeholder: Option<String>,
+    columns: Vec<CsvColumn>,
+    header: bool,
+    bom: bool,
+
+    /// the writer which serializes all records of the run. It writes into
+    /// `buffer`, which is moved into the sink after every record.
+    writer: RefCell<csv::Writer<SharedBuffer>>,
+    buffer: SharedBuffer,
 }
 
 impl CsvOutput {
     pub fn new(src_zone: Tz, dst_zone: Tz) -> Self {
+        let buffer = SharedBuffer::new();
         Self {
-            src_zone, dst_zone
+            src_zone, dst_zone,
+            precision: TimestampPrecision::default(),
+            date_format: DateFormat::default(),
+            placeholder: None,
+            columns: CsvColumn::defaults(),
+            header: false,
+            bom: false,
+            writer: RefCell::new(Self::csv_writer(b',', buffer.clone())),
+            buffer,
+        }
+    }
+
+    fn csv_writer(delimiter: u8, buffer: SharedBuffer) -> csv::Writer<SharedBuffer> {
+        csv::WriterBuilder::new()
+            .delimiter(delimiter)
+            .terminator(csv::Terminator::Any(b'\n'))
+            .from_writer(buffer)
+    }
+
+    /// sets the number of digits of the fraction of a second
+    pub fn with_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// sets how timestamps are displayed
+    pub fn with_date_format(mut self, date_format: DateFormat) -> Self {
+        self.date_format = date_format;
+        self
+    }
+
+    /// sets which columns are written, and in which order
+    pub fn with_columns(mut self, columns: Vec<CsvColumn>) -> Self {
+        self.columns = columns;
+        self
+    }
+
+    /// adds a column which contains the label of the input which every entry
+    /// has been read from. Entries without a source have an empty field.
+    pub fn with_source_column(self) -> Self {
+        self.with_column(CsvColumn::Source)
+    }
+
+    /// adds a column which contains the correction which has been applied
+    /// to the timestamps of every entry (see [`BodyfileLine::with_skew`](crate::BodyfileLine::with_skew))
+    pub fn with_skew_column(self) -> Self {
+        self.with_column(CsvColumn::Skew)
+    }
+
+    /// adds a column which contains the MD5 hash of every entry
+    pub fn with_md5_column(self) -> Self {
+        self.with_column(CsvColumn::Md5)
+    }
+
+    /// adds columns which contain all four timestamps of every entry, as
+    /// they are written in a bodyfile (see [`raw_timestamp`])
+    pub fn with_timestamp_columns(self) -> Self {
+        self.with_column(CsvColumn::Mtime)
+            .with_column(CsvColumn::Atime)
+            .with_column(CsvColumn::Ctime)
+            .with_column(CsvColumn::Crtime)
+    }
+
+    /// adds a column which marks the entries whose hashes are known-bad (see
+    /// [`HashFilter`](crate::HashFilter))
+    pub fn with_known_bad_column(self) -> Self {
+        self.with_column(CsvColumn::KnownBad)
+    }
+
+    fn with_column(mut self, column: CsvColumn) -> Self {
+        if !self.columns.contains(&column) {
+            self.columns.push(column);
         }
+        self
+    }
+
+    /// sets the character which separates the fields, like `b';'`
+    pub fn with_delimiter(mut self, delimiter: u8) -> Self {
+        self.writer = RefCell::new(Self::csv_writer(delimiter, self.buffer.clone()));
+        self
+    }
+
+    /// writes a line with the names of all columns before the first entry
+    pub fn with_header(mut self, header: bool) -> Self {
+        self.header = header;
+        self
+    }
+
+    /// writes a UTF-8 byte order mark at the beginning of the output, so
+    /// that Excel does not mistake non-ASCII names for some other encoding
+    pub fn with_bom(mut self) -> Self {
+        self.bom = true;
+        self
+    }
+
+    /// sets the text which is displayed instead of a missing timestamp
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = Some(placeholder.into());
+        self
+    }
+
+    fn format_timestamp(&self, timestamp: Option<&Timestamp>, entry: &ListEntry) -> String {
+        match timestamp {
+            Some(ts) => Mactime2Application::format_resolved(
+                ts,
+                entry.dst,
+                self.precision,
+                &self.date_format,
+                &self.src_zone,
+                &self.dst_zone,
+            ),
+            None => match self.placeholder.as_ref() {
+                Some(placeholder) => placeholder.clone(),
+                None => Mactime2Application::format_missing(self.precision),
+            },
+        }
+    }
+
+    /// writes one record into `sink`, including the line terminator
+    fn write_record<I, T>(&self, sink: &mut dyn Write, fields: I) -> io::Result<()>
+    where
+        I: IntoIterator<Item = T>,
+        T: AsRef<[u8]>,
+    {
+        let mut writer = self.writer.borrow_mut();
+        writer.write_record(fields)?;
+        writer.flush()?;
+        sink.write_all(&self.buffer.take())
     }
 }
 
 impl Mactime2Writer for CsvOutput {
-    fn fmt(&self, timestamp: &i64, entry: &ListEntry) -> String {
-        let timestamp = Mactime2Application::format_date(*timestamp, &self.src_zone, &self.dst_zone);
-        format!(
-            "{},{},{},{},{},{},{},\"{}\"",
-            timestamp,
-            entry.line.get_size(),
-            entry.flags,
-            entry.line.get_mode(),
-            entry.line.get_uid(),
-            entry.line.get_gid(),
-            entry.line.get_inode(),
-            entry.line.get_name()
-        )
+    fn write(&self, sink: &mut dyn Write, timestamp: Option<&Timestamp>, entry: &ListEntry) -> io::Result<()> {
+        let fields = self.columns.iter().map(|column| match column {
+            CsvColumn::Date => self.format_timestamp(timestamp, entry),
+            CsvColumn::Size => entry.line.get_size().to_string(),
+            CsvColumn::Type => entry.flags.to_string(),
+            CsvColumn::Mode => entry.line.get_mode().to_string(),
+            CsvColumn::Uid => entry.line.get_uid().to_string(),
+            CsvColumn::Gid => entry.line.get_gid().to_string(),
+            CsvColumn::Meta => entry.line.get_inode().to_string(),
+            CsvColumn::Name => entry.line.get_name().to_string(),
+            CsvColumn::Md5 => entry.line.get_md5().to_string(),
+            CsvColumn::Mtime => raw_timestamp(entry.line.mtime()),
+            CsvColumn::Atime => raw_timestamp(entry.line.atime()),
+            CsvColumn::Ctime => raw_timestamp(entry.line.ctime()),
+            CsvColumn::Crtime => raw_timestamp(entry.line.crtime()),
+            CsvColumn::Source => entry.source.as_deref().unwrap_or_default().to_owned(),
+            CsvColumn::Skew => entry.line.skew().unwrap_or_default().to_string(),
+            CsvColumn::KnownBad => known_bad_marker(entry).to_owned(),
+        });
+        self.write_record(sink, fields)
+    }
+
+    /// returns the record of the entry, without the line terminator
+    fn fmt(&self, timestamp: Option<&Timestamp>, entry: &ListEntry) -> String {
+        let mut record = Vec::new();
+        if let Err(why) = self.write(&mut record, timestamp, entry) {
+            log::warn!("unable to serialize CSV record: {}", why);
+        }
+        record.pop();
+        String::from_utf8_lossy(&record).into_owned()
+    }
+
+    fn write_header(&self, sink: &mut dyn Write) -> io::Result<()> {
+        if self.bom {
+            sink.write_all(BOM)?;
+        }
+        if self.header {
+            self.write_record(sink, self.columns.iter().map(CsvColumn::header))?;
+        }
+        Ok(())
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use super::CsvOutput;
+    use super::{parse_delimiter, CsvColumn, CsvOutput};
     use chrono::DateTime;
     use chrono_tz::TZ_VARIANTS;
     use chrono_tz::Tz;
+    use std::io::{self, Write};
     use std::sync::Arc;
     use bodyfile::Bodyfile3Line;
     use crate::bodyfile::{MACBFlags, ListEntry, Mactime2Writer};
+    use crate::{DateFormat, Timestamp, TimestampPrecision};
 
     fn random_tz() -> Tz {
         let index = rand::random::<usize>() % TZ_VARIANTS.len();
@@ -55,10 +365,12 @@ mod tests {
             let bf_line = Bodyfile3Line::new().with_crtime(unix_ts);
             let entry = ListEntry {
                 flags: MACBFlags::B,
-                line: Arc::new(bf_line)
+                line: Arc::new(bf_line.into()),
+                source: None,
+                dst: None
             };
 
-            let out_line = output.fmt(&unix_ts, &entry);
+            let out_line = output.fmt(Some(&unix_ts.into()), &entry);
             let out_ts = out_line.split(',').next().unwrap();
             let rfc3339 = DateTime::parse_from_rfc3339(out_ts).expect(out_ts);
             assert_eq!(unix_ts, rfc3339.timestamp(), "Timestamp {} converted to '{}' and back to {}", unix_ts, out_ts, rfc3339.timestamp());
@@ -75,10 +387,12 @@ mod tests {
             let bf_line = Bodyfile3Line::new().with_crtime(unix_ts);
             let entry = ListEntry {
                 flags: MACBFlags::B,
-                line: Arc::new(bf_line)
+                line: Arc::new(bf_line.into()),
+                source: None,
+                dst: None
             };
 
-            let out_line = output.fmt(&unix_ts, &entry);
+            let out_line = output.fmt(Some(&unix_ts.into()), &entry);
             let out_ts = out_line.split(',').next().unwrap();
             let rfc3339 = match DateTime::parse_from_rfc3339(out_ts) {
                 Ok(ts) => ts,
@@ -90,4 +404,176 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_source() {
+        let output = CsvOutput::new(Tz::UTC, Tz::UTC).with_source_column();
+        let bf_line = Bodyfile3Line::new().with_name("/etc/passwd").with_inode("17").with_crtime(0);
+        let entry = ListEntry {
+            flags: MACBFlags::B,
+            line: Arc::new(bf_line.into()),
+            source: Some(Arc::from("host1")),
+            dst: None
+        };
+
+        let out_line = output.fmt(Some(&0.into()), &entry);
+        assert!(out_line.ends_with(",17,/etc/passwd,host1"), "unexpected line: '{}'", out_line);
+    }
+
+    #[test]
+    fn test_escaping() {
+        let output = CsvOutput::new(Tz::UTC, Tz::UTC);
+        let bf_line = Bodyfile3Line::new().with_name("/tmp/a \"b\",\nc").with_inode("17").with_crtime(0);
+        let entry = ListEntry {
+            flags: MACBFlags::B,
+            line: Arc::new(bf_line.into()),
+            source: None,
+            dst: None
+        };
+
+        let out_line = output.fmt(Some(&0.into()), &entry);
+        assert!(out_line.ends_with(",17,\"/tmp/a \"\"b\"\",\nc\""), "unexpected line: '{}'", out_line);
+    }
+
+    #[test]
+    fn test_columns_and_delimiter() {
+        let output = CsvOutput::new(Tz::UTC, Tz::UTC)
+            .with_columns(vec![CsvColumn::Name, CsvColumn::Type, CsvColumn::Date])
+            .with_delimiter(b';')
+            .with_header(true)
+            .with_bom();
+        let bf_line = Bodyfile3Line::new().with_name("/tmp/a;b").with_crtime(0);
+        let entry = ListEntry {
+            flags: MACBFlags::B,
+            line: Arc::new(bf_line.into()),
+            source: None,
+            dst: None
+        };
+
+        let mut header = Vec::new();
+        output.write_header(&mut header).unwrap();
+        assert_eq!(header, b"\xEF\xBB\xBFFile Name;Type;Date\n");
+        assert_eq!(output.fmt(Some(&0.into()), &entry), "\"/tmp/a;b\";...b;1970-01-01T00:00:00+00:00");
+
+        // fields are only quoted if they need to be
+        let output = CsvOutput::new(Tz::UTC, Tz::UTC).with_delimiter(b';').with_header(true);
+        let bf_line = Bodyfile3Line::new().with_name("/tmp/a,b").with_inode("2").with_crtime(0);
+        let entry = ListEntry {
+            flags: MACBFlags::B,
+            line: Arc::new(bf_line.into()),
+            source: None,
+            dst: None
+        };
+
+        let mut header = Vec::new();
+        output.write_header(&mut header).unwrap();
+        assert_eq!(header, b"Date;Size;Type;Mode;UID;GID;Meta;File Name\n");
+        assert_eq!(output.fmt(Some(&0.into()), &entry), "1970-01-01T00:00:00+00:00;0;...b;;0;0;2;/tmp/a,b");
+    }
+
+    #[test]
+    fn test_write_error() {
+        struct BrokenPipe;
+        impl Write for BrokenPipe {
+            fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let output = CsvOutput::new(Tz::UTC, Tz::UTC);
+        let bf_line = Bodyfile3Line::new().with_name("/etc/passwd").with_crtime(0);
+        let entry = ListEntry {
+            flags: MACBFlags::B,
+            line: Arc::new(bf_line.into()),
+            source: None,
+            dst: None
+        };
+
+        let error = output.write(&mut BrokenPipe, Some(&0.into()), &entry).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::BrokenPipe);
+
+        // the failed record is not written again with the next one
+        let mut sink = Vec::new();
+        output.write(&mut sink, None, &entry).unwrap();
+        assert_eq!(String::from_utf8(sink).unwrap().lines().count(), 1);
+    }
+
+    #[test]
+    fn test_precision() {
+        let bf_line = Bodyfile3Line::new().with_name("/etc/passwd").with_crtime(0);
+        let entry = ListEntry {
+            flags: MACBFlags::B,
+            line: Arc::new(bf_line.into()),
+            source: None,
+            dst: None
+        };
+        let date = |output: CsvOutput, ts: Timestamp| output.fmt(Some(&ts), &entry).split(',').next().unwrap().to_owned();
+
+        let ts = Timestamp::new(10, 123_456_789);
+        assert_eq!(date(CsvOutput::new(Tz::UTC, Tz::UTC), ts), "1970-01-01T00:00:10+00:00");
+        let millis = || CsvOutput::new(Tz::UTC, Tz::UTC).with_precision(TimestampPrecision::Millis);
+        assert_eq!(date(millis(), ts), "1970-01-01T00:00:10.123+00:00");
+        assert_eq!(date(millis(), Timestamp::new(10, 500_000_000)), "1970-01-01T00:00:10.500+00:00");
+        assert_eq!(date(millis(), Timestamp::from(11)), "1970-01-01T00:00:11.000+00:00");
+        let nanos = CsvOutput::new(Tz::UTC, Tz::UTC).with_precision(TimestampPrecision::Nanos);
+        assert_eq!(date(nanos, ts), "1970-01-01T00:00:10.123456789+00:00");
+    }
+
+    #[test]
+    fn test_date_format() {
+        let bf_line = Bodyfile3Line::new().with_name("/etc/passwd").with_atime(1662035696);
+        let entry = ListEntry {
+            flags: MACBFlags::A,
+            line: Arc::new(bf_line.into()),
+            source: None,
+            dst: None
+        };
+        let date = |output: CsvOutput, ts: Option<Timestamp>| output.fmt(ts.as_ref(), &entry).split(',').next().unwrap().to_owned();
+        let format = |format: &str| format.parse::<DateFormat>().unwrap();
+
+        let excel = CsvOutput::new(Tz::UTC, Tz::Europe__Berlin).with_date_format(format("excel"));
+        assert_eq!(date(excel, Some(Timestamp::from(1662035696))), "2022-09-01 14:34:56");
+
+        let epoch = || CsvOutput::new(Tz::UTC, Tz::UTC)
+            .with_date_format(format("epoch"))
+            .with_precision(TimestampPrecision::Millis);
+        assert_eq!(date(epoch(), Some(Timestamp::from(1662035696))), "1662035696.000");
+        assert_eq!(date(epoch(), Some(Timestamp::new(1662035696, 500_000_000))), "1662035696.500");
+
+        // missing timestamps are not affected by the date format
+        let mactime = || CsvOutput::new(Tz::UTC, Tz::UTC).with_date_format(format("mactime"));
+        assert_eq!(date(mactime(), None), "0000-00-00T00:00:00+00:00");
+        assert_eq!(date(mactime(), Some(Timestamp::from(1662035696))), "Thu Sep 01 2022 12:34:56");
+    }
+
+    #[test]
+    fn test_missing_timestamps() {
+        let bf_line = Bodyfile3Line::new().with_name("/etc/passwd");
+        let entry = ListEntry {
+            flags: MACBFlags::NONE,
+            line: Arc::new(bf_line.into()),
+            source: None,
+            dst: None
+        };
+        let date = |output: CsvOutput, ts: Option<Timestamp>| output.fmt(ts.as_ref(), &entry).split(',').next().unwrap().to_owned();
+
+        assert_eq!(date(CsvOutput::new(Tz::UTC, Tz::UTC), None), "0000-00-00T00:00:00+00:00");
+        assert_eq!(date(CsvOutput::new(Tz::UTC, Tz::UTC).with_placeholder("n/a"), None), "n/a");
+
+        let millis = || CsvOutput::new(Tz::UTC, Tz::UTC).with_precision(TimestampPrecision::Millis);
+        assert_eq!(date(millis(), None), "0000-00-00T00:00:00.000+00:00");
+        assert_eq!(date(millis(), Some(Timestamp::new(-86401, 750_000_000))), "1969-12-30T23:59:59.750+00:00");
+    }
+
+    #[test]
+    fn test_parse_delimiter() {
+        assert_eq!(parse_delimiter(";"), Ok(b';'));
+        assert_eq!(parse_delimiter("tab"), Ok(b'\t'));
+        assert!(parse_delimiter("\"").is_err());
+        assert!(parse_delimiter(";;").is_err());
+        assert!(parse_delimiter("ä").is_err());
+    }
 }
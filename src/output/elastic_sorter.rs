@@ -0,0 +1,251 @@
+use std::{
+    io::Write,
+    sync::{mpsc::Receiver, Arc},
+    thread::JoinHandle,
+    time::Duration,
+};
+
+use base64::{encode_config, URL_SAFE_NO_PAD};
+use serde_json::{json, Value};
+use sha2::{Digest, Sha256};
+
+use crate::{BodyfileLine, Consumer, Joinable, LabeledReceiver, MactimeError, OutputFile, RunOptions, Runnable, Sorter};
+
+use super::{line_documents, BulkClient};
+
+#[derive(Clone)]
+pub struct ElasticOptions {
+    /// name of the index into which the documents will be inserted
+    pub index_name: String,
+
+    /// base URL of the elasticsearch or opensearch instance
+    pub url: Option<String>,
+    pub username: Option<String>,
+    pub password: Option<String>,
+
+    /// number of documents per bulk request
+    pub batch_size: usize,
+
+    /// number of retries after a failed bulk request
+    pub retries: usize,
+
+    /// delay before the first retry, which is increased for every further retry
+    pub retry_delay: Duration,
+}
+
+impl Default for ElasticOptions {
+    fn default() -> Self {
+        Self {
+            index_name: "mactime2".to_owned(),
+            url: None,
+            username: None,
+            password: None,
+            batch_size: 1000,
+            retries: 3,
+            retry_delay: Duration::from_millis(1000),
+        }
+    }
+}
+
+/// creates `_bulk` requests for elasticsearch, which contain the timeline
+/// objects also generated by [`JsonSorter`](super::JsonSorter). Because
+/// elasticsearch does not need the documents to be sorted, every line is
+/// converted and written as soon as it has been received.
+///
+/// The request body is written to the output file as well, unless the
+/// documents are sent to an elasticsearch instance and no output file has
+/// been specified.
+pub struct ElasticSorter {
+    worker: Option<JoinHandle<Result<(), MactimeError>>>,
+    receivers: Vec<LabeledReceiver>,
+    sink: Option<OutputFile>,
+    run_options: RunOptions,
+    elastic_options: ElasticOptions,
+}
+
+impl Joinable<Result<(), MactimeError>> for ElasticSorter {
+    fn join(&mut self) -> std::thread::Result<Result<(), MactimeError>> {
+        self.worker.take().unwrap().join()
+    }
+}
+
+impl Consumer<BodyfileLine> for ElasticSorter {
+    fn with_receiver(previous: Receiver<BodyfileLine>, options: RunOptions) -> Self {
+        Self::new(options).with_input(previous, None)
+    }
+}
+
+impl Runnable for ElasticSorter {
+    fn run(&mut self) {
+        assert!(
+            !self.receivers.is_empty(),
+            "no receiver provided; please call with_receiver()"
+        );
+        let receivers = std::mem::take(&mut self.receivers);
+        let run_options = self.run_options.clone();
+        let elastic_options = self.elastic_options.clone();
+        let sink = self.sink.take().unwrap_or_else(OutputFile::stdout);
+        self.worker = Some(std::thread::spawn(move || {
+            Self::elastic_worker(receivers, sink, run_options, elastic_options)
+        }));
+    }
+}
+
+impl Sorter<Result<(), MactimeError>> for ElasticSorter {}
+
+impl ElasticSorter {
+    pub fn new(options: RunOptions) -> Self {
+        Self {
+            receivers: Vec::new(),
+            worker: None,
+            sink: None,
+            run_options: options,
+            elastic_options: ElasticOptions::default(),
+        }
+    }
+
+    /// adds another input. Documents of inputs with a label contain this
+    /// label as `labels.source`.
+    pub fn with_input(mut self, decoder: Receiver<BodyfileLine>, label: Option<&str>) -> Self {
+        self.receivers.push((decoder, label.map(Arc::from)));
+        self
+    }
+
+    pub fn with_elastic_options(mut self, elastic_options: ElasticOptions) -> Self {
+        self.elastic_options = elastic_options;
+        self
+    }
+
+    /// sets the file to which the bulk request body will be written
+    pub fn with_output_file(mut self, sink: OutputFile) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    fn elastic_worker(
+        receivers: Vec<LabeledReceiver>,
+        sink: OutputFile,
+        run_options: RunOptions,
+        elastic_options: ElasticOptions,
+    ) -> Result<(), MactimeError> {
+        let mut output = if elastic_options.url.is_some() && sink.is_stdout() {
+            None
+        } else {
+            Some(sink)
+        };
+
+        let mut client = elastic_options.url.as_ref().map(|url| {
+            let client = BulkClient::new(url)
+                .with_batch_size(elastic_options.batch_size)
+                .with_retries(elastic_options.retries, elastic_options.retry_delay);
+            match elastic_options.username.as_ref() {
+                Some(username) => client.with_credentials(
+                    username,
+                    elastic_options.password.as_deref().unwrap_or_default(),
+                ),
+                None => client,
+            }
+        });
+
+        for (decoder, source) in receivers {
+            while let Ok(line) = decoder.recv() {
+                let documents = match line_documents(&line, &run_options) {
+                    Ok(documents) => documents,
+                    Err(why) if run_options.strict_mode => return Err(why),
+                    Err(why) => {
+                        log::warn!("{}", why);
+                        continue;
+                    }
+                };
+
+                for (_, mut value) in documents {
+                    if let Some(source) = source.as_ref() {
+                        value["labels"]["source"] = json!(source.as_ref());
+                    }
+                    let document = Self::bulk_document(&elastic_options.index_name, value);
+                    if let Some(output) = output.as_mut() {
+                        output.write_all(document.as_bytes())?;
+                    }
+                    if let Some(client) = client.as_mut() {
+                        client.add_document(&document)?;
+                    }
+                }
+            }
+        }
+
+        if run_options.report.is_aborted() {
+            return Err(MactimeError::InputAborted);
+        }
+        if let Some(output) = output {
+            output.finish()?;
+        }
+        if let Some(client) = client.as_mut() {
+            client.flush()?;
+        }
+        Ok(())
+    }
+
+    /// generates the action and source lines for one document. The id of
+    /// the document is derived from its contents, the same way as
+    /// `es4forensics` does it.
+    fn bulk_document(index_name: &str, value: Value) -> String {
+        let source = value.to_string();
+        let id = encode_config(Sha256::digest(source.as_bytes()), URL_SAFE_NO_PAD);
+        let action = json!({"create": {"_index": index_name, "_id": id}});
+        format!("{}\n{}\n", action, source)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{ElasticOptions, ElasticSorter};
+    use crate::output::bulk_client::tests::mock_server;
+    use crate::{Consumer, Joinable, OutputFile, RunOptions, Runnable};
+    use bodyfile::Bodyfile3Line;
+    use serde_json::Value;
+    use std::sync::mpsc;
+
+    #[test]
+    fn test_bulk_file_and_endpoint() {
+        let (url, requests) = mock_server(vec![(200, r#"{"took":1,"errors":false,"items":[]}"#)]);
+        let dir = tempfile::tempdir().unwrap();
+        let bulk_file = dir.path().join("bulk.json");
+
+        let (tx, rx) = mpsc::channel();
+        let mut sorter = ElasticSorter::with_receiver(rx, RunOptions::default())
+            .with_output_file(OutputFile::create(Some(&bulk_file)).unwrap())
+            .with_elastic_options(ElasticOptions {
+                index_name: "timeline".to_owned(),
+                url: Some(url),
+                ..Default::default()
+            });
+        sorter.run();
+
+        tx.send(
+            Bodyfile3Line::new()
+                .with_name("/etc/passwd")
+                .with_mtime(1661772116)
+                .with_atime(1661774614)
+                .into(),
+        )
+        .unwrap();
+        drop(tx);
+        sorter.join().unwrap().unwrap();
+
+        let written = std::fs::read_to_string(&bulk_file).unwrap();
+
+        let lines: Vec<Value> = written
+            .lines()
+            .map(|l| serde_json::from_str(l).unwrap())
+            .collect();
+        assert_eq!(lines.len(), 4);
+        for pair in lines.chunks(2) {
+            assert_eq!(pair[0]["create"]["_index"], "timeline");
+            assert!(pair[0]["create"]["_id"].is_string());
+            assert_eq!(pair[1]["file"]["path"], "/etc/passwd");
+        }
+
+        let request = requests.recv().unwrap();
+        assert_eq!(request.body, written);
+    }
+}
@@ -1,20 +1,227 @@
 use std::{
-    borrow::Borrow,
-    collections::{BTreeMap, BTreeSet},
+    borrow::{Borrow, Cow},
+    io::{self, Read, Write},
     sync::{mpsc::Receiver, Arc},
     thread::JoinHandle,
 };
 
 use bodyfile::Bodyfile3Line;
 use chrono_tz::Tz;
-use es4forensics::{objects::PosixFile, Timestamp, TimelineObject};
+use es4forensics::{objects::PosixFile, Timestamp as EsTimestamp, TimelineObject};
+use serde_json::{json, Value};
 use std::convert::TryFrom;
 
-use crate::{Consumer, Joinable, MactimeError, Runnable, Sorter};
+use crate::external_sort::*;
+use crate::{BodyfileLine, Consumer, DstMark, OutputFile, Joinable, LabeledReceiver, MACBFlags, MactimeError, ResolvedTimestamp, RunOptions, Runnable, Sorter, Timestamp, TimestampPrecision};
+use super::KNOWN_BAD;
+
+/// returns the MACB flags of the timeline objects generated from `line`,
+/// which are identified by their (already converted) timestamps
+pub(crate) fn timestamp_flags(line: &BodyfileLine, src_zone: &Tz) -> Vec<(EsTimestamp, MACBFlags)> {
+    MACBFlags::of_line(line)
+        .into_iter()
+        .filter_map(|(ts, flags)| EsTimestamp::try_from((ts.secs(), src_zone)).ok().map(|t| (t, flags)))
+        .collect()
+}
+
+pub(crate) fn flags_at(timestamp_flags: &[(EsTimestamp, MACBFlags)], ts: &EsTimestamp) -> MACBFlags {
+    timestamp_flags
+        .iter()
+        .filter(|(t, _)| t == ts)
+        .fold(MACBFlags::NONE, |flags, (_, f)| flags | *f)
+}
+
+/// returns the JSON documents of a bodyfile line, which are accepted by the
+/// filters in `options`, together with their timestamps. Documents whose
+/// timestamp is affected by a DST transition are tagged with their
+/// [`DstMark`], and the skew of the line is stored as a label.
+///
+/// Fails with [`MactimeError::SerializationError`] if the line cannot be
+/// converted. Callers should only skip such lines if
+/// [`RunOptions::strict_mode`] is not set.
+pub(crate) fn line_documents(line: &BodyfileLine, options: &RunOptions) -> Result<Vec<(Timestamp, Value)>, MactimeError> {
+    let mut documents: Vec<(Timestamp, Value)> = Vec::new();
+    for (index, (utc_line, marks)) in utc_lines(line, options).into_iter().enumerate() {
+        let bf3line: &Bodyfile3Line = &utc_line;
+        let pf = PosixFile::try_from((bf3line, &Tz::UTC)).map_err(|why| MactimeError::SerializationError {
+            name: line.get_name().to_owned(),
+            message: why.to_string(),
+        })?;
+        let flags = if options.macb_filter.is_empty() {
+            Vec::new()
+        } else {
+            timestamp_flags(&utc_line, &Tz::UTC)
+        };
+
+        for (ts, mut value) in pf.into_tuples() {
+            let secs = ts.timestamp_millis().div_euclid(1000);
+            if !options.range.contains(secs) {
+                continue;
+            }
+            if !options.macb_filter.is_empty() && !options.macb_filter.accepts(flags_at(&flags, &ts)) {
+                continue;
+            }
+
+            // all other lines only contain the latest occurrences of
+            // ambiguous timestamps, so their other documents are redundant
+            let marks: Vec<DstMark> = marks.iter().filter(|(t, _)| *t == secs).map(|(_, mark)| *mark).collect();
+            if index > 0 && marks.is_empty() {
+                continue;
+            }
+
+            let timestamp = add_fractions(&mut value, &utc_line, options.precision);
+            if let Some(tags) = value["tags"].as_array_mut() {
+                tags.extend(marks.iter().map(|mark| json!(mark.as_str())));
+                if line.is_known_bad() {
+                    tags.push(json!(KNOWN_BAD));
+                }
+            }
+            if let Some(skew) = line.skew() {
+                value["labels"]["skew"] = json!(skew.to_string());
+            }
+            if !documents.iter().any(|(_, document)| *document == value) {
+                documents.push((timestamp, value));
+            }
+        }
+    }
+    Ok(documents)
+}
+
+/// a bodyfile line whose timestamps are in UTC, together with the marks of
+/// its timestamps
+type UtcLine<'l> = (Cow<'l, BodyfileLine>, Vec<(i64, DstMark)>);
+
+/// converts all timestamps of `line` from the source timezone into UTC, as
+/// `options.dst_policy` requires. If both occurrences of ambiguous
+/// timestamps should be displayed, a second line contains their latest
+/// occurrences. Every line is returned together with the marks of its
+/// timestamps, which are identified by their seconds in UTC.
+fn utc_lines<'l>(line: &'l BodyfileLine, options: &RunOptions) -> Vec<UtcLine<'l>> {
+    if options.src_zone == Tz::UTC {
+        return vec![(Cow::Borrowed(line), Vec::new())];
+    }
+
+    let resolve = |ts: Option<Timestamp>| -> Vec<ResolvedTimestamp> {
+        ts.map(|ts| options.dst_policy.resolve(ts, &options.src_zone).collect())
+            .unwrap_or_default()
+    };
+    let [mtime, atime, ctime, crtime] = line.timestamps().map(resolve);
+    let count = [&mtime, &atime, &ctime, &crtime].iter().map(|r| r.len()).max().unwrap_or(1).max(1);
+
+    (0..count)
+        .map(|index| {
+            let mut marks = Vec::new();
+            let mut select = |resolved: &Vec<ResolvedTimestamp>| {
+                let ts = resolved.get(index).or_else(|| resolved.first())?;
+                if let Some(mark) = ts.mark {
+                    marks.push((ts.instant.secs(), mark));
+                }
+                Some(ts.instant)
+            };
+            let utc_line = line
+                .clone()
+                .with_mtime(select(&mtime))
+                .with_atime(select(&atime))
+                .with_ctime(select(&ctime))
+                .with_crtime(select(&crtime));
+            (Cow::Owned(utc_line), marks)
+        })
+        .collect()
+}
+
+/// es4forensics only supports whole seconds, so this adds the fractions of
+/// the timestamps of `line` to a document which has been generated from
+/// `line`, as far as `precision` allows. Timestamps of the same second share
+/// one document, whose `@timestamp` is the earliest of them. Returns the
+/// value of `@timestamp`.
+pub(crate) fn add_fractions(document: &mut Value, line: &BodyfileLine, precision: TimestampPrecision) -> Timestamp {
+    let millis = document["@timestamp"].as_i64().unwrap_or_default();
+    let fields = [
+        ("mtime", line.mtime()),
+        ("accessed", line.atime()),
+        ("ctime", line.ctime()),
+        ("created", line.crtime()),
+    ];
+
+    let mut timestamp: Option<Timestamp> = None;
+    for (field, ts) in fields.iter() {
+        let ts = match ts {
+            Some(ts) => ts,
+            None => continue,
+        };
+        let value = match document.get_mut("file").and_then(|file| file.get_mut(*field)) {
+            Some(value) => value,
+            None => continue,
+        };
+        let field_millis = match value.as_i64() {
+            Some(field_millis) => field_millis,
+            None => continue,
+        };
+
+        // the field has already been converted into UTC
+        let precise = Timestamp::new(Timestamp::from_millis(field_millis).secs(), ts.truncate(precision).nanos());
+        *value = epoch_millis(precise);
+        if field_millis == millis {
+            timestamp = Some(timestamp.map_or(precise, |t| t.min(precise)));
+        }
+    }
+
+    let timestamp = timestamp.unwrap_or_else(|| Timestamp::from_millis(millis));
+    document["@timestamp"] = epoch_millis(timestamp);
+    timestamp
+}
+
+/// converts a timestamp into milliseconds since the epoch. Fractions of a
+/// millisecond cannot be stored in a JSON integer, so such timestamps are
+/// converted into strings like `"1661772116123.456789"`.
+fn epoch_millis(ts: Timestamp) -> Value {
+    let millis = ts.secs() * 1000 + i64::from(ts.nanos() / 1_000_000);
+    let fraction = ts.nanos() % 1_000_000;
+    if fraction == 0 {
+        return json!(millis);
+    }
+
+    // the fraction of a negative timestamp moves it towards zero
+    let (sign, millis, fraction) = if millis < 0 {
+        ("-", -(millis + 1), 1_000_000 - fraction)
+    } else {
+        ("", millis, fraction)
+    };
+    let fraction = format!("{:06}", fraction);
+    json!(format!("{}{}.{}", sign, millis, fraction.trim_end_matches('0')))
+}
+
+/// a JSON document, together with its timestamp
+#[derive(PartialEq, Eq, PartialOrd, Ord)]
+struct JsonEntry(Timestamp, String);
+
+impl SortRecord for JsonEntry {
+    fn memory_size(&self) -> usize {
+        std::mem::size_of::<Self>() + self.1.len()
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_i64(writer, self.0.secs())?;
+        write_u64(writer, self.0.nanos().into())?;
+        write_str(writer, &self.1)
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+        match read_first_i64(reader)? {
+            None => Ok(None),
+            Some(secs) => {
+                let ts = Timestamp::new(secs, read_u64(reader)? as u32);
+                Ok(Some(Self(ts, read_string(reader)?)))
+            }
+        }
+    }
+}
+
 pub struct JsonSorter {
     worker: Option<JoinHandle<Result<(), MactimeError>>>,
-    receiver: Option<Receiver<Bodyfile3Line>>,
-    src_zone: Tz,
+    receivers: Vec<LabeledReceiver>,
+    sink: Option<OutputFile>,
+    options: RunOptions,
 }
 
 impl Joinable<Result<(), MactimeError>> for JsonSorter {
@@ -23,25 +230,23 @@ impl Joinable<Result<(), MactimeError>> for JsonSorter {
     }
 }
 
-impl Consumer<Bodyfile3Line> for JsonSorter {
-    fn with_receiver(previous: Receiver<Bodyfile3Line>, options: crate::RunOptions) -> Self {
-        Self {
-            receiver: Some(previous),
-            worker: None,
-            src_zone: options.src_zone,
-        }
+impl Consumer<BodyfileLine> for JsonSorter {
+    fn with_receiver(previous: Receiver<BodyfileLine>, options: RunOptions) -> Self {
+        Self::new(options).with_input(previous, None)
     }
 }
 
 impl Runnable for JsonSorter {
     fn run(&mut self) {
-        let receiver = self
-            .receiver
-            .take()
-            .expect("no receiver provided; please call with_receiver()");
-        let src_zone = self.src_zone;
+        assert!(
+            !self.receivers.is_empty(),
+            "no receiver provided; please call with_receiver()"
+        );
+        let receivers = std::mem::take(&mut self.receivers);
+        let sink = self.sink.take().unwrap_or_else(OutputFile::stdout);
+        let options = self.options.clone();
         self.worker = Some(std::thread::spawn(move || {
-            Self::json_worker(receiver, src_zone)
+            Self::json_worker(receivers, sink, options)
         }));
     }
 }
@@ -49,8 +254,69 @@ impl Runnable for JsonSorter {
 impl Sorter<Result<(), MactimeError>> for JsonSorter {}
 
 impl JsonSorter {
-    fn json_worker(decoder: Receiver<Bodyfile3Line>, src_zone: Tz) -> Result<(), MactimeError> {
-        let mut entries: BTreeMap<Timestamp, BTreeSet<String>> = BTreeMap::new();
+    pub fn new(options: RunOptions) -> Self {
+        Self {
+            receivers: Vec::new(),
+            worker: None,
+            sink: None,
+            options,
+        }
+    }
+
+    /// sets the destination of the timeline. If no output file has been
+    /// specified, the timeline is written to stdout.
+    pub fn with_output_file(mut self, sink: OutputFile) -> Self {
+        self.sink = Some(sink);
+        self
+    }
+
+    /// adds another input. All inputs are merged into the same timeline,
+    /// and documents of inputs with a label contain this label as
+    /// `labels.source`.
+    pub fn with_input(mut self, decoder: Receiver<BodyfileLine>, label: Option<&str>) -> Self {
+        self.receivers.push((decoder, label.map(Arc::from)));
+        self
+    }
+
+    fn json_worker(receivers: Vec<LabeledReceiver>, mut sink: OutputFile, options: RunOptions) -> Result<(), MactimeError> {
+        if options.sort_options.convert_only {
+            for (decoder, source) in receivers {
+                Self::insert_lines(decoder, &source, &options, |entry| writeln!(sink, "{}", entry.1))?;
+            }
+        } else {
+            Self::sorting_worker(receivers, &mut sink, options)?;
+        }
+        sink.finish()?;
+        Ok(())
+    }
+
+    fn sorting_worker(receivers: Vec<LabeledReceiver>, sink: &mut OutputFile, options: RunOptions) -> Result<(), MactimeError> {
+        let mut entries = ExternalSorter::new(options.sort_options.clone());
+        for (decoder, source) in receivers {
+            Self::insert_lines(decoder, &source, &options, |entry| entries.push(entry))?;
+        }
+
+        // identical documents are displayed only once
+        let mut previous: Option<JsonEntry> = None;
+        for entry in entries.into_sorted()? {
+            let entry = entry?;
+            if previous.as_ref() != Some(&entry) {
+                writeln!(sink, "{}", entry.1)?;
+                previous = Some(entry);
+            }
+        }
+        Ok(())
+    }
+
+    fn insert_lines<F>(
+        decoder: Receiver<BodyfileLine>,
+        source: &Option<Arc<str>>,
+        options: &RunOptions,
+        mut insert: F,
+    ) -> Result<(), MactimeError>
+    where
+        F: FnMut(JsonEntry) -> io::Result<()>,
+    {
         loop {
             let line = Arc::new(match decoder.recv() {
                 Err(_) => {
@@ -59,27 +325,49 @@ impl JsonSorter {
                 Ok(l) => l,
             });
 
-            let bfline: &Bodyfile3Line = line.borrow();
-            let pf = PosixFile::try_from((bfline, &src_zone)).unwrap();
+            let bfline: &BodyfileLine = line.borrow();
+            let documents = match line_documents(bfline, options) {
+                Ok(documents) => documents,
+                Err(why) if options.strict_mode => return Err(why),
+                Err(why) => {
+                    log::warn!("{}", why);
+                    continue;
+                }
+            };
 
-            let lines: Vec<(Timestamp, String)> = pf.into_tuples()
-                .map(|(t, v)| (t, serde_json::to_string(&v).unwrap()))
-                .collect();
+            let mut lines: Vec<(Timestamp, String)> = documents
+                .into_iter()
+                .map(|(t, mut v)| {
+                    if let Some(source) = source {
+                        v["labels"]["source"] = json!(source.as_ref());
+                    }
+                    serde_json::to_string(&v)
+                        .map(|s| (t, s))
+                        .map_err(|why| MactimeError::SerializationError {
+                            name: line.get_name().to_owned(),
+                            message: why.to_string(),
+                        })
+                })
+                .collect::<Result<_, _>>()?;
 
             if lines.is_empty() {
+                if !options.range.is_unbounded() || !options.macb_filter.is_empty() {
+                    continue;
+                }
                 log::warn!("file {} has no timestamp entries", line.get_name());
                 log::warn!("raw entry is {}", line.to_string());
             } else {
+                // the documents are generated in random order, so we sort
+                // them to get a reproducible output, even without sorting
+                // the whole timeline
+                lines.sort();
                 for (ts, line) in lines {
-                    entries.entry(ts).or_insert(BTreeSet::new()).insert(line);
+                    insert(JsonEntry(ts, line))?;
                 }
             }
         }
-
-        for lines in entries.into_values() {
-            for line in lines {
-                println!("{}", line);
-            }
+        if options.report.is_aborted() {
+            return Err(MactimeError::InputAborted);
         }
         Ok(())
     }
@@ -0,0 +1,221 @@
+use std::cell::RefCell;
+use std::io::{self, Write};
+
+use chrono::TimeZone;
+use chrono_tz::Tz;
+use clap::clap_derive::ValueEnum;
+
+use crate::bodyfile::ListEntry;
+use crate::{dst_policy, Mactime2Writer, Timestamp};
+
+/// how dates are displayed by [`MactimeOutput`], like by the options of the
+/// original `mactime`
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum MactimeDates {
+    /// like `Thu Sep 01 2022 12:34:56`
+    #[default]
+    Words,
+
+    /// like `Thu 09 01 2022 12:34:56` (`mactime -m`)
+    Numbers,
+
+    /// like `2022-09-01T12:34:56Z`, always in UTC (`mactime -y`)
+    Iso8601,
+}
+
+impl MactimeDates {
+    fn format(&self) -> &'static str {
+        match self {
+            Self::Words => "%a %b %d %Y %H:%M:%S",
+            Self::Numbers => "%a %m %d %Y %H:%M:%S",
+            Self::Iso8601 => "%Y-%m-%dT%H:%M:%SZ",
+        }
+    }
+
+    /// `mactime` treats `0` as a timestamp which is not set, and displays
+    /// such timestamps like this
+    fn missing(&self) -> &'static str {
+        match self {
+            Self::Words => "Xxx Xxx 00 0000 00:00:00",
+            Self::Numbers => "Xxx 00 00 0000 00:00:00",
+            Self::Iso8601 => "0000-00-00T00:00:00Z",
+        }
+    }
+
+    /// returns the day of a formatted date, which means the date without
+    /// the time of day
+    fn day<'a>(&self, date: &'a str) -> &'a str {
+        match self {
+            Self::Words | Self::Numbers => &date[..date.len() - " 00:00:00".len()],
+            Self::Iso8601 => &date[..date.len() - "T00:00:00Z".len()],
+        }
+    }
+}
+
+/// the entries which have the same date and which have not been written yet
+#[derive(Default)]
+struct PendingEntries {
+    date: Option<String>,
+
+    /// the names and all fields except the date of every entry
+    entries: Vec<(String, String)>,
+
+    /// the day of the last entry which has been written. In text output,
+    /// every day starts with a line which displays it.
+    last_day: Option<String>,
+}
+
+/// the output format of the original `mactime` of The Sleuth Kit, which is
+/// meant for tools which parse its output. Only whole seconds are displayed,
+/// and DST marks, sources and skews are omitted.
+///
+/// Like `mactime`, entries with the same date are ordered by their names.
+/// They are held back until an entry with another date arrives, or until
+/// [`Mactime2Writer::write_footer`] is called.
+pub struct MactimeOutput {
+    src_zone: Tz,
+    dst_zone: Tz,
+    dates: MactimeDates,
+
+    /// comma delimited output with a header line (`mactime -d`)
+    delimited: bool,
+
+    pending: RefCell<PendingEntries>,
+}
+
+impl MactimeOutput {
+    pub fn new(src_zone: Tz, dst_zone: Tz) -> Self {
+        Self {
+            src_zone,
+            dst_zone,
+            dates: MactimeDates::default(),
+            delimited: false,
+            pending: RefCell::new(PendingEntries::default()),
+        }
+    }
+
+    pub fn with_dates(mut self, dates: MactimeDates) -> Self {
+        self.dates = dates;
+        self
+    }
+
+    /// writes comma delimited output, like `mactime -d`
+    pub fn delimited(mut self) -> Self {
+        self.delimited = true;
+        self
+    }
+
+    fn format_date(&self, timestamp: Option<&Timestamp>, entry: &ListEntry) -> String {
+        let secs = match timestamp {
+            None => return self.dates.missing().to_owned(),
+            Some(ts) if ts.secs() == 0 => return self.dates.missing().to_owned(),
+            Some(ts) => ts.secs(),
+        };
+        let local = match dst_policy::local_datetime(&Timestamp::from(secs)) {
+            Some(local) => local,
+            None => return self.dates.missing().to_owned(),
+        };
+        let format = self.dates.format();
+        match dst_policy::from_local(&local, entry.dst, &self.src_zone) {
+            Some(t) if self.dates == MactimeDates::Iso8601 => t.with_timezone(&Tz::UTC).format(format).to_string(),
+            Some(t) => t.with_timezone(&self.dst_zone).format(format).to_string(),
+
+            // the local time does not exist, so it cannot be converted
+            None => Tz::UTC.from_utc_datetime(&local).format(format).to_string(),
+        }
+    }
+
+    /// all fields of an entry except its date
+    fn fields(&self, entry: &ListEntry) -> String {
+        if self.delimited {
+            format!(
+                "{},{},{},{},{},{},\"{}\"",
+                entry.line.get_size(),
+                entry.flags,
+                entry.line.get_mode(),
+                entry.line.get_uid(),
+                entry.line.get_gid(),
+                entry.line.get_inode(),
+                entry.line.get_name()
+            )
+        } else {
+            format!(
+                "{:>8} {} {} {:<8} {:<8} {:<8} {}",
+                entry.line.get_size(),
+                entry.flags,
+                entry.line.get_mode(),
+                entry.line.get_uid(),
+                entry.line.get_gid(),
+                entry.line.get_inode(),
+                entry.line.get_name()
+            )
+        }
+    }
+
+    fn separator(&self) -> char {
+        if self.delimited {
+            ','
+        } else {
+            ' '
+        }
+    }
+
+    /// writes all pending entries, ordered by their names. In text output,
+    /// the date is only displayed once, and a new day starts with a line
+    /// which displays only the day.
+    fn write_pending(&self, pending: &mut PendingEntries, sink: &mut dyn Write) -> io::Result<()> {
+        let date = match pending.date.take() {
+            Some(date) => date,
+            None => return Ok(()),
+        };
+        pending.entries.sort_by(|(lhs, _), (rhs, _)| lhs.cmp(rhs));
+
+        if self.delimited {
+            for (_, fields) in pending.entries.drain(..) {
+                writeln!(sink, "{},{}", date, fields)?;
+            }
+            return Ok(());
+        }
+
+        let day = self.dates.day(&date);
+        if pending.last_day.as_deref() != Some(day) {
+            writeln!(sink, "{}", day)?;
+            pending.last_day = Some(day.to_owned());
+        }
+        let blank = " ".repeat(date.len());
+        for (index, (_, fields)) in pending.entries.drain(..).enumerate() {
+            let date = if index == 0 { &date } else { &blank };
+            writeln!(sink, "{} {}", date, fields)?;
+        }
+        Ok(())
+    }
+}
+
+impl Mactime2Writer for MactimeOutput {
+    fn write(&self, sink: &mut dyn Write, timestamp: Option<&Timestamp>, entry: &ListEntry) -> io::Result<()> {
+        let date = self.format_date(timestamp, entry);
+        let mut pending = self.pending.borrow_mut();
+        if pending.date.as_ref() != Some(&date) {
+            self.write_pending(&mut pending, sink)?;
+            pending.date = Some(date);
+        }
+        pending.entries.push((entry.line.get_name().to_owned(), self.fields(entry)));
+        Ok(())
+    }
+
+    /// returns the line of a single entry, which always displays its date
+    fn fmt(&self, timestamp: Option<&Timestamp>, entry: &ListEntry) -> String {
+        format!("{}{}{}", self.format_date(timestamp, entry), self.separator(), self.fields(entry))
+    }
+
+    fn write_header(&self, sink: &mut dyn Write) -> io::Result<()> {
+        if self.delimited {
+            writeln!(sink, "Date,Size,Type,Mode,UID,GID,Meta,File Name")?;
+        }
+        Ok(())
+    }
+
+    fn write_footer(&self, sink: &mut dyn Write) -> io::Result<()> {
+        self.write_pending(&mut self.pending.borrow_mut(), sink)
+    }
+}
@@ -1,7 +1,25 @@
 mod csv_output;
+mod mactime_output;
 mod txt_output;
 mod json_sorter;
+mod output_file;
+
+#[cfg(feature = "elastic")]
+mod bulk_client;
+#[cfg(feature = "elastic")]
+mod elastic_sorter;
+#[cfg(feature = "sqlite")]
+mod sqlite_output;
 
 pub use csv_output::*;
+pub use mactime_output::*;
 pub use txt_output::*;
-pub use json_sorter::*;
\ No newline at end of file
+pub use json_sorter::*;
+pub use output_file::*;
+
+#[cfg(feature = "elastic")]
+pub use bulk_client::*;
+#[cfg(feature = "elastic")]
+pub use elastic_sorter::*;
+#[cfg(feature = "sqlite")]
+pub use sqlite_output::*;
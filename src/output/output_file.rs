@@ -0,0 +1,300 @@
+use std::fs::File;
+use std::io::{self, stdout, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use tempfile::TempPath;
+
+#[cfg(feature = "gzip")]
+use flate2::{write::GzEncoder, Compression};
+
+/// the destination of a timeline, which is either stdout, a file or any
+/// other [`Write`] implementation.
+///
+/// Files are written into a temporary file in the same directory first,
+/// which is renamed when [`OutputFile::finish`] is called. So there will never
+/// be an incomplete output file, even if mactime2 fails. The output will be
+/// compressed if the file name ends with `.gz`, `.zst` or `.xz` (if the
+/// according feature has been enabled).
+pub struct OutputFile {
+    writer: Box<dyn FinishWrite>,
+    destination: Destination,
+}
+
+enum Destination {
+    /// stdout or some other writer
+    Writer,
+
+    /// a temporary file, which will be renamed when it is finished
+    File(PendingFile),
+
+    /// a temporary file, which will be renamed by the owner of its
+    /// [`PendingFile`]
+    Detached,
+}
+
+/// a temporary file, which becomes the output file as soon as
+/// [`PendingFile::persist`] is called. If it is dropped instead, the
+/// temporary file is deleted.
+pub(crate) struct PendingFile {
+    temp_path: TempPath,
+    path: PathBuf,
+}
+
+impl PendingFile {
+    /// creates a temporary file in the directory of `path`
+    pub fn create(path: &Path) -> io::Result<(File, Self)> {
+        let directory = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let (file, temp_path) = tempfile::Builder::new()
+            .prefix(".mactime2")
+            .tempfile_in(directory)?
+            .into_parts();
+
+        // temporary files are only readable by their owner, but the output
+        // file should have the same permissions as any other new file
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            file.set_permissions(std::fs::Permissions::from_mode(0o644))?;
+        }
+
+        Ok((
+            file,
+            Self {
+                temp_path,
+                path: path.to_owned(),
+            },
+        ))
+    }
+
+    /// returns the path of the temporary file
+    #[cfg(feature = "sqlite")]
+    pub fn temp_path(&self) -> &Path {
+        &self.temp_path
+    }
+
+    pub fn persist(self) -> io::Result<()> {
+        self.temp_path.persist(self.path)?;
+        Ok(())
+    }
+}
+
+impl OutputFile {
+    pub fn stdout() -> Self {
+        Self::from_writer(BufWriter::new(stdout()))
+    }
+
+    /// writes the timeline into `writer`, which will be flushed by
+    /// [`OutputFile::finish`]. Use a [`SharedBuffer`] to capture the
+    /// timeline in memory.
+    pub fn from_writer<W: Write + Send + 'static>(writer: W) -> Self {
+        Self {
+            writer: Box::new(Flushing(writer)),
+            destination: Destination::Writer,
+        }
+    }
+
+    /// creates an output file, or uses stdout if `path` is `None` or `"-"`
+    pub fn create(path: Option<&Path>) -> io::Result<Self> {
+        let path = match path {
+            Some(path) if path != Path::new("-") => path,
+            _ => return Ok(Self::stdout()),
+        };
+
+        let (file, pending_file) = PendingFile::create(path)?;
+        Ok(Self {
+            writer: Self::compressed(path, BufWriter::new(file))?,
+            destination: Destination::File(pending_file),
+        })
+    }
+
+    /// returns `true` if the output is not written to a file
+    pub fn is_stdout(&self) -> bool {
+        matches!(self.destination, Destination::Writer)
+    }
+
+    /// flushes all buffers and renames the temporary file to its final name
+    pub fn finish(self) -> io::Result<()> {
+        self.writer.finish()?;
+        if let Destination::File(pending_file) = self.destination {
+            pending_file.persist()?;
+        }
+        Ok(())
+    }
+
+    /// takes over the responsibility to rename the temporary file. This
+    /// allows to discard the output if some other part of the pipeline
+    /// fails, even after the output has been finished.
+    pub(crate) fn take_pending_file(&mut self) -> Option<PendingFile> {
+        match std::mem::replace(&mut self.destination, Destination::Detached) {
+            Destination::File(pending_file) => Some(pending_file),
+            destination => {
+                self.destination = destination;
+                None
+            }
+        }
+    }
+
+    fn compressed(path: &Path, file: BufWriter<File>) -> io::Result<Box<dyn FinishWrite>> {
+        match path.extension().and_then(|e| e.to_str()) {
+            #[cfg(feature = "gzip")]
+            Some("gz") => Ok(Box::new(GzEncoder::new(file, Compression::default()))),
+
+            #[cfg(feature = "zstd")]
+            Some("zst") => Ok(Box::new(zstd::Encoder::new(file, 0)?)),
+
+            #[cfg(feature = "xz")]
+            Some("xz") => Ok(Box::new(xz2::write::XzEncoder::new(file, 6))),
+
+            Some(ext) if ["gz", "zst", "xz"].contains(&ext) => Err(io::Error::new(
+                io::ErrorKind::Unsupported,
+                format!("this version of mactime2 has been built without support for '.{}' files", ext),
+            )),
+
+            _ => Ok(Box::new(file)),
+        }
+    }
+}
+
+impl Write for OutputFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.writer.write(buf)
+    }
+
+    fn write_all(&mut self, buf: &[u8]) -> io::Result<()> {
+        self.writer.write_all(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.writer.flush()
+    }
+}
+
+/// a writer which needs to be finished explicitly, such as a compressor
+trait FinishWrite: Write + Send {
+    fn finish(self: Box<Self>) -> io::Result<()>;
+}
+
+/// a writer which only needs to be flushed
+struct Flushing<W: Write + Send>(W);
+
+impl<W: Write + Send> Write for Flushing<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl<W: Write + Send> FinishWrite for Flushing<W> {
+    fn finish(mut self: Box<Self>) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+impl FinishWrite for BufWriter<File> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        let file = self.into_inner().map_err(|e| e.into_error())?;
+        file.sync_all()
+    }
+}
+
+#[cfg(feature = "gzip")]
+impl FinishWrite for GzEncoder<BufWriter<File>> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Box::new(GzEncoder::finish(*self)?).finish()
+    }
+}
+
+#[cfg(feature = "zstd")]
+impl FinishWrite for zstd::Encoder<'static, BufWriter<File>> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Box::new(zstd::Encoder::finish(*self)?).finish()
+    }
+}
+
+#[cfg(feature = "xz")]
+impl FinishWrite for xz2::write::XzEncoder<BufWriter<File>> {
+    fn finish(self: Box<Self>) -> io::Result<()> {
+        Box::new(xz2::write::XzEncoder::finish(*self)?).finish()
+    }
+}
+
+/// an in-memory buffer which can be shared between threads, e.g. to read a
+/// timeline after it has been written by a sorter
+#[derive(Clone, Debug, Default)]
+pub struct SharedBuffer(Arc<Mutex<Vec<u8>>>);
+
+impl SharedBuffer {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// returns a copy of all bytes which have been written so far
+    pub fn contents(&self) -> Vec<u8> {
+        self.0.lock().unwrap().clone()
+    }
+
+    /// removes all bytes which have been written so far, and returns them
+    pub(crate) fn take(&self) -> Vec<u8> {
+        std::mem::take(&mut *self.0.lock().unwrap())
+    }
+}
+
+impl Write for SharedBuffer {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0.lock().unwrap().write(buf)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::OutputFile;
+    use std::io::Write;
+
+    #[test]
+    fn test_atomic_write() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timeline.csv");
+
+        let mut output = OutputFile::create(Some(&path)).unwrap();
+        output.write_all(b"some data\n").unwrap();
+        assert!(!path.exists());
+        output.finish().unwrap();
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "some data\n");
+
+        // an unfinished output file must not replace an existing file
+        let mut output = OutputFile::create(Some(&path)).unwrap();
+        output.write_all(b"incomplete data\n").unwrap();
+        drop(output);
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "some data\n");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("timeline.csv.gz");
+
+        let mut output = OutputFile::create(Some(&path)).unwrap();
+        output.write_all(b"some data\n").unwrap();
+        output.finish().unwrap();
+
+        use std::io::Read;
+        let mut content = String::new();
+        flate2::read::GzDecoder::new(std::fs::File::open(&path).unwrap())
+            .read_to_string(&mut content)
+            .unwrap();
+        assert_eq!(content, "some data\n");
+    }
+}
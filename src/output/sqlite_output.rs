@@ -0,0 +1,202 @@
+use std::path::Path;
+
+use chrono::{SecondsFormat, Utc};
+use chrono_tz::Tz;
+use rusqlite::{params, Connection};
+
+use super::PendingFile;
+use crate::{dst_policy, DateFormat, ListEntry, MACBFlags, Mactime2Application, MactimeError, TimelineItem, Timestamp, TimestampPrecision};
+
+/// every entry of the timeline is one row of the table `timeline`, and the
+/// table `metadata` describes how the timeline has been created
+const SCHEMA: &str = "
+CREATE TABLE metadata (
+    key TEXT NOT NULL,
+    value TEXT NOT NULL
+);
+CREATE TABLE timeline (
+    id INTEGER PRIMARY KEY,
+    timestamp INTEGER,
+    nanos INTEGER,
+    date TEXT NOT NULL,
+    dst TEXT,
+    macb TEXT NOT NULL,
+    modified INTEGER NOT NULL,
+    accessed INTEGER NOT NULL,
+    changed INTEGER NOT NULL,
+    born INTEGER NOT NULL,
+    size INTEGER NOT NULL,
+    mode TEXT NOT NULL,
+    uid INTEGER NOT NULL,
+    gid INTEGER NOT NULL,
+    inode TEXT NOT NULL,
+    name TEXT NOT NULL,
+    md5 TEXT NOT NULL,
+    source TEXT,
+    skew TEXT,
+    known_bad INTEGER NOT NULL
+);
+";
+
+/// the indexes are created after all rows have been inserted, which is
+/// faster than updating them for every row
+const INDEXES: &str = "
+CREATE INDEX IF NOT EXISTS timeline_timestamp ON timeline (timestamp, nanos);
+CREATE INDEX IF NOT EXISTS timeline_name ON timeline (name);
+";
+
+/// writes the timeline into a SQLite database, so that it can be queried
+/// using SQL. The entries are inserted in the order of the timeline.
+///
+/// The column `timestamp` contains the seconds since 1970 in UTC, and `nanos`
+/// contains the fraction of the second. Both are `NULL` if an entry has no
+/// timestamp. The column `date` contains the timestamp as it would be
+/// displayed in CSV output.
+pub struct SqliteOutput {
+    connection: Connection,
+    src_zone: Tz,
+    dst_zone: Tz,
+    precision: TimestampPrecision,
+    date_format: DateFormat,
+
+    /// the database is written into a temporary file, which is renamed by
+    /// [`SqliteOutput::finish`]
+    pending_file: Option<PendingFile>,
+}
+
+impl SqliteOutput {
+    /// creates a new database at `path`, which must not be `-`. Like with
+    /// [`OutputFile`](crate::OutputFile), the database only exists after
+    /// [`SqliteOutput::finish`] has been called.
+    pub fn create(path: &Path, src_zone: Tz, dst_zone: Tz) -> Result<Self, MactimeError> {
+        let (_, pending_file) = PendingFile::create(path)?;
+        let mut output = Self::from_connection(Connection::open(pending_file.temp_path())?, src_zone, dst_zone)?;
+        output.pending_file = Some(pending_file);
+        Ok(output)
+    }
+
+    /// writes into an open database, such as an in-memory database, which
+    /// must not contain the tables of a timeline yet
+    pub fn from_connection(connection: Connection, src_zone: Tz, dst_zone: Tz) -> Result<Self, MactimeError> {
+        connection.execute_batch(SCHEMA)?;
+        let output = Self {
+            connection,
+            src_zone,
+            dst_zone,
+            precision: TimestampPrecision::default(),
+            date_format: DateFormat::default(),
+            pending_file: None,
+        };
+        output.add_metadata("tool", "mactime2")?;
+        output.add_metadata("version", env!("CARGO_PKG_VERSION"))?;
+        output.add_metadata("created", &Utc::now().to_rfc3339_opts(SecondsFormat::Secs, true))?;
+        output.add_metadata("src_zone", src_zone.name())?;
+        output.add_metadata("dst_zone", dst_zone.name())?;
+        Ok(output)
+    }
+
+    /// sets the number of digits of the fraction of a second in the column
+    /// `date`
+    pub fn with_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// sets how timestamps are displayed in the column `date`
+    pub fn with_date_format(mut self, date_format: DateFormat) -> Self {
+        self.date_format = date_format;
+        self
+    }
+
+    /// adds a row to the table `metadata`. A key may occur more than once,
+    /// like `input`.
+    pub fn add_metadata(&self, key: &str, value: &str) -> Result<(), MactimeError> {
+        self.connection
+            .execute("INSERT INTO metadata (key, value) VALUES (?1, ?2)", params![key, value])?;
+        Ok(())
+    }
+
+    pub fn connection(&self) -> &Connection {
+        &self.connection
+    }
+
+    /// inserts all entries, like the entries of a [`Timeline`](crate::Timeline)
+    pub fn write<I>(&mut self, entries: I) -> Result<(), MactimeError>
+    where
+        I: IntoIterator<Item = TimelineItem>,
+    {
+        let Self { connection, src_zone, dst_zone, precision, date_format, .. } = self;
+        let transaction = connection.transaction()?;
+        {
+            let mut insert = transaction.prepare(
+                "INSERT INTO timeline (timestamp, nanos, date, dst, macb, modified, accessed, changed, born, \
+                 size, mode, uid, gid, inode, name, md5, source, skew, known_bad) \
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14, ?15, ?16, ?17, ?18, ?19)",
+            )?;
+            for entry in entries {
+                let (timestamp, entry) = entry?;
+                let instant = timestamp.as_ref().and_then(|ts| instant(ts, &entry, src_zone));
+                let date = match timestamp.as_ref() {
+                    Some(ts) => Mactime2Application::format_resolved(
+                        ts,
+                        entry.dst,
+                        *precision,
+                        date_format,
+                        src_zone,
+                        dst_zone,
+                    ),
+                    None => Mactime2Application::format_missing(*precision),
+                };
+                let line = &entry.line;
+                insert.execute(params![
+                    instant.map(|ts| ts.secs()),
+                    instant.map(|ts| ts.nanos()),
+                    date,
+                    entry.dst.map(|mark| mark.as_str()),
+                    entry.flags.to_string(),
+                    entry.flags.contains(MACBFlags::M),
+                    entry.flags.contains(MACBFlags::A),
+                    entry.flags.contains(MACBFlags::C),
+                    entry.flags.contains(MACBFlags::B),
+                    line.get_size(),
+                    line.get_mode(),
+                    line.get_uid(),
+                    line.get_gid(),
+                    line.get_inode(),
+                    line.get_name(),
+                    line.get_md5(),
+                    entry.source.as_deref(),
+                    line.skew().map(|skew| skew.to_string()),
+                    line.is_known_bad(),
+                ])?;
+            }
+        }
+        transaction.execute_batch(INDEXES)?;
+        transaction.commit()?;
+        Ok(())
+    }
+
+    /// takes over the responsibility to rename the temporary file (see
+    /// [`OutputFile::take_pending_file`](crate::OutputFile))
+    pub(crate) fn take_pending_file(&mut self) -> Option<PendingFile> {
+        self.pending_file.take()
+    }
+
+    /// closes the database, and renames the temporary file to its final name
+    pub fn finish(mut self) -> Result<(), MactimeError> {
+        let pending_file = self.pending_file.take();
+        self.connection.close().map_err(|(_, why)| why)?;
+        if let Some(pending_file) = pending_file {
+            pending_file.persist()?;
+        }
+        Ok(())
+    }
+}
+
+/// returns the point in time which a local timestamp refers to, which is the
+/// same point in time which has been used to sort the timeline
+fn instant(timestamp: &Timestamp, entry: &ListEntry, src_zone: &Tz) -> Option<Timestamp> {
+    let local = dst_policy::local_datetime(timestamp)?;
+    let datetime = dst_policy::from_local(&local, entry.dst, src_zone).unwrap_or_else(|| dst_policy::shift(&local, src_zone));
+    Some(Timestamp::new(datetime.timestamp(), timestamp.nanos()))
+}
@@ -1,11 +1,32 @@
 use chrono_tz::Tz;
 use std::cell::RefCell;
-use crate::{Mactime2Application, Mactime2Writer};
+use crate::output::{known_bad_marker, KNOWN_BAD};
+use crate::{raw_timestamp, DateFormat, DstMark, Mactime2Application, Mactime2Writer, Timestamp, TimestampPrecision};
 pub (crate) use crate::bodyfile::{ListEntry};
 
+/// is used to determine the width of the timestamp column
+/// (2001-09-09T01:46:40+00:00)
+const TYPICAL_TIMESTAMP: i64 = 1_000_000_000;
+
+/// identifies the text of a timestamp in the output
+type TimestampKey = (Option<Timestamp>, Option<DstMark>);
+
 pub struct TxtOutput {
     src_zone: Tz, dst_zone: Tz,
-    last_ts: (RefCell<i64>, RefCell<String>),
+    precision: TimestampPrecision,
+    date_format: DateFormat,
+    placeholder: Option<String>,
+    skew_column: bool,
+    md5_column: bool,
+    timestamp_columns: bool,
+    known_bad_column: bool,
+
+    /// the last timestamp which has been displayed, together with its DST
+    /// mark. The timestamp is `None` if the last entry did not have one.
+    last_ts: (RefCell<Option<TimestampKey>>, RefCell<String>),
+
+    /// is displayed instead of a repeated timestamp, and has the same width
+    /// as the last timestamp which has been displayed
     empty_ts: RefCell<String>
 }
 
@@ -13,23 +34,125 @@ impl TxtOutput {
     pub fn new(src_zone: Tz, dst_zone: Tz) -> Self {
         Self {
             src_zone, dst_zone,
-            last_ts: (RefCell::new(i64::MIN), RefCell::new("".to_owned())),
-            empty_ts: RefCell::new("                         ".to_owned())
+            precision: TimestampPrecision::default(),
+            date_format: DateFormat::default(),
+            placeholder: None,
+            skew_column: false,
+            md5_column: false,
+            timestamp_columns: false,
+            known_bad_column: false,
+            last_ts: (RefCell::new(None), RefCell::new("".to_owned())),
+            empty_ts: RefCell::new("".to_owned())
+        }
+    }
+
+    /// sets the number of digits of the fraction of a second
+    pub fn with_precision(mut self, precision: TimestampPrecision) -> Self {
+        self.precision = precision;
+        self
+    }
+
+    /// sets how timestamps are displayed
+    pub fn with_date_format(mut self, date_format: DateFormat) -> Self {
+        self.date_format = date_format;
+        self
+    }
+
+    /// sets the text which is displayed instead of a missing timestamp. It
+    /// is padded to the width of the timestamp column.
+    pub fn with_placeholder(mut self, placeholder: impl Into<String>) -> Self {
+        self.placeholder = Some(placeholder.into());
+        self
+    }
+
+    /// adds a column which contains the correction which has been applied
+    /// to the timestamps of every entry (see [`BodyfileLine::with_skew`](crate::BodyfileLine::with_skew))
+    pub fn with_skew_column(mut self) -> Self {
+        self.skew_column = true;
+        self
+    }
+
+    /// adds a column which contains the MD5 hash of every entry
+    pub fn with_md5_column(mut self) -> Self {
+        self.md5_column = true;
+        self
+    }
+
+    /// adds columns which contain all four timestamps of every entry, as
+    /// they are written in a bodyfile (see [`raw_timestamp`](crate::raw_timestamp))
+    pub fn with_timestamp_columns(mut self) -> Self {
+        self.timestamp_columns = true;
+        self
+    }
+
+    /// adds a column which marks the entries whose hashes are known-bad (see
+    /// [`HashFilter`](crate::HashFilter))
+    pub fn with_known_bad_column(mut self) -> Self {
+        self.known_bad_column = true;
+        self
+    }
+
+    fn format_timestamp(&self, timestamp: &Timestamp, mark: Option<DstMark>) -> String {
+        Mactime2Application::format_resolved(
+            timestamp,
+            mark,
+            self.precision,
+            &self.date_format,
+            &self.src_zone,
+            &self.dst_zone,
+        )
+    }
+
+    fn format_missing(&self) -> String {
+        // the width of the column depends on the date format, and might
+        // even vary for some formats, so a typical timestamp is used
+        let width = self.format_timestamp(&TYPICAL_TIMESTAMP.into(), None).chars().count();
+        match self.placeholder.as_ref() {
+            Some(placeholder) => format!("{:<1$}", placeholder, width),
+            None => format!("{:<1$}", Mactime2Application::format_missing(self.precision), width),
         }
     }
 }
 
 impl Mactime2Writer for TxtOutput {
-    fn fmt(&self, timestamp: &i64, entry: &ListEntry) -> String {
-        let ts = if *timestamp != *self.last_ts.0.borrow() {
-            *self.last_ts.1.borrow_mut() = Mactime2Application::format_date(*timestamp, &self.src_zone, &self.dst_zone);
-            *self.last_ts.0.borrow_mut() = *timestamp;
+    fn fmt(&self, timestamp: Option<&Timestamp>, entry: &ListEntry) -> String {
+        // entries which look the same share their timestamp
+        let timestamp = timestamp.map(|ts| ts.truncate(self.precision));
+        let key = (timestamp, entry.dst);
+        let ts = if Some(key) != *self.last_ts.0.borrow() {
+            let text = match timestamp.as_ref() {
+                Some(ts) => self.format_timestamp(ts, entry.dst),
+                None => self.format_missing(),
+            };
+            *self.empty_ts.borrow_mut() = " ".repeat(text.chars().count());
+            *self.last_ts.1.borrow_mut() = text;
+            *self.last_ts.0.borrow_mut() = Some(key);
             self.last_ts.1.borrow()
         } else {
             self.empty_ts.borrow()
         };
+        // the name may contain spaces, so all optional columns are printed
+        // before it
+        let mut metadata = String::new();
+        if let Some(source) = entry.source.as_ref() {
+            metadata = format!("{} ", source);
+        }
+        if self.skew_column {
+            metadata = format!("{}{} ", metadata, entry.line.skew().unwrap_or_default());
+        }
+        if self.md5_column {
+            metadata = format!("{}{:<32} ", metadata, entry.line.get_md5());
+        }
+        if self.timestamp_columns {
+            for timestamp in entry.line.timestamps().iter().copied() {
+                metadata = format!("{}{} ", metadata, raw_timestamp(timestamp));
+            }
+        }
+        if self.known_bad_column {
+            metadata = format!("{}{:<2$} ", metadata, known_bad_marker(entry), KNOWN_BAD.len());
+        }
         format!(
-            "{} {:>8} {} {:<12} {:<7} {:<7} {} {}",
+            "{} {:>8} {} {:<12} {:<7} {:<7} {} {}{}",
             ts,
             entry.line.get_size(),
             entry.flags,
@@ -37,6 +160,7 @@ impl Mactime2Writer for TxtOutput {
             entry.line.get_uid(),
             entry.line.get_gid(),
             entry.line.get_inode(),
+            metadata,
             entry.line.get_name()
         )
     }
@@ -66,11 +190,13 @@ mod tests {
             let bf_line = Bodyfile3Line::new().with_crtime(unix_ts);
             let entry = ListEntry {
                 flags: MACBFlags::B,
-                line: Arc::new(bf_line)
+                line: Arc::new(bf_line.into()),
+                source: None,
+                dst: None
             };
 
-            let out_line = output.fmt(&unix_ts, &entry);
-            let out_line2 = output.fmt(&unix_ts, &entry);
+            let out_line = output.fmt(Some(&unix_ts.into()), &entry);
+            let out_line2 = output.fmt(Some(&unix_ts.into()), &entry);
             assert!(out_line2.starts_with(' '));
             
             let out_ts = out_line.split(' ').next().unwrap();
@@ -89,11 +215,13 @@ mod tests {
             let bf_line = Bodyfile3Line::new().with_crtime(unix_ts);
             let entry = ListEntry {
                 flags: MACBFlags::B,
-                line: Arc::new(bf_line)
+                line: Arc::new(bf_line.into()),
+                source: None,
+                dst: None
             };
 
-            let out_line = output.fmt(&unix_ts, &entry);
-            let out_line2 = output.fmt(&unix_ts, &entry);
+            let out_line = output.fmt(Some(&unix_ts.into()), &entry);
+            let out_line2 = output.fmt(Some(&unix_ts.into()), &entry);
             assert!(out_line2.starts_with(' '));
 
             let out_ts = out_line.split(' ').next().unwrap();
@@ -107,4 +235,19 @@ mod tests {
         }
         Ok(())
     }
+
+    #[test]
+    fn test_source() {
+        let output = TxtOutput::new(Tz::UTC, Tz::UTC);
+        let bf_line = Bodyfile3Line::new().with_name("/etc/passwd").with_inode("17").with_crtime(0);
+        let entry = ListEntry {
+            flags: MACBFlags::B,
+            line: Arc::new(bf_line.into()),
+            source: Some(Arc::from("host1")),
+            dst: None
+        };
+
+        let out_line = output.fmt(Some(&0.into()), &entry);
+        assert!(out_line.ends_with(" 17 host1 /etc/passwd"), "unexpected line: '{}'", out_line);
+    }
 }
@@ -0,0 +1,109 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Result};
+
+/// an input file, as specified on the command line. The specification may be
+/// a path, a glob pattern or `'-'` for stdin, optionally prefixed with a
+/// label, like in `host1=/cases/host1.bodyfile`.
+#[derive(Clone, Debug, PartialEq)]
+pub(crate) struct InputFile {
+    path: String,
+    label: Option<String>,
+}
+
+impl InputFile {
+    /// parses an input specification and expands glob patterns. If a label
+    /// has been specified together with a glob pattern, all matching files
+    /// share the same label.
+    pub fn expand(spec: &str) -> Result<Vec<Self>> {
+        let (label, path) = Self::split_label(spec);
+
+        if path == "-" || !path.contains(['*', '?', '[']) {
+            return Ok(vec![Self {
+                path: path.to_owned(),
+                label: label.map(str::to_owned),
+            }]);
+        }
+
+        let mut files = Vec::new();
+        for entry in glob::glob(path)? {
+            files.push(Self {
+                path: entry?.to_string_lossy().to_string(),
+                label: label.map(str::to_owned),
+            });
+        }
+
+        if files.is_empty() {
+            Err(anyhow!("no file matches the pattern '{}'", path))
+        } else {
+            Ok(files)
+        }
+    }
+
+    pub fn path(&self) -> &str {
+        &self.path
+    }
+
+    /// returns the label which has been specified explicitly, or `None`
+    pub fn explicit_label(&self) -> Option<&str> {
+        self.label.as_deref()
+    }
+
+    /// returns the label of this input file. If no label has been specified,
+    /// the name of the file is used.
+    pub fn label(&self) -> String {
+        match self.label.as_ref() {
+            Some(label) => label.clone(),
+            None if self.path == "-" => "stdin".to_owned(),
+            None => Path::new(&self.path)
+                .file_name()
+                .map(|f| f.to_string_lossy().to_string())
+                .unwrap_or_else(|| self.path.clone()),
+        }
+    }
+
+    /// a label can be prepended to the path, separated by `'='`. To not
+    /// confuse paths which contain a `'='` with labels, a label must not
+    /// contain path separators.
+    fn split_label(spec: &str) -> (Option<&str>, &str) {
+        match spec.split_once('=') {
+            Some((label, path)) if !label.is_empty() && !label.contains(['/', '\\']) => {
+                (Some(label), path)
+            }
+            _ => (None, spec),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InputFile;
+
+    #[test]
+    fn test_labels() {
+        let files = InputFile::expand("host1=/cases/host1.bodyfile").unwrap();
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path(), "/cases/host1.bodyfile");
+        assert_eq!(files[0].label(), "host1");
+
+        let files = InputFile::expand("/cases/host1.bodyfile").unwrap();
+        assert_eq!(files[0].label(), "host1.bodyfile");
+        assert_eq!(files[0].explicit_label(), None);
+
+        let files = InputFile::expand("/cases/a=b/host1.bodyfile").unwrap();
+        assert_eq!(files[0].path(), "/cases/a=b/host1.bodyfile");
+
+        let files = InputFile::expand("-").unwrap();
+        assert_eq!(files[0].label(), "stdin");
+    }
+
+    #[test]
+    fn test_glob() {
+        let pattern = format!("{}/tests/data/*.bodyfile", env!("CARGO_MANIFEST_DIR"));
+        let files = InputFile::expand(&format!("sample={}", pattern)).unwrap();
+        assert!(!files.is_empty());
+        assert!(files.iter().all(|f| f.label() == "sample"));
+
+        assert!(InputFile::expand("/nonexisting/*.bodyfile").is_err());
+    }
+}
@@ -1,7 +1,7 @@
-use std::{io::Read, sync::mpsc::Sender};
+use std::{io::Read, sync::mpsc::SyncSender};
 
-use crate::Provider;
+use crate::{MactimeError, Provider};
 
-pub(crate) trait StreamWorker<T>: Provider<T, ()> {
-    fn worker<R: Read + Send>(input: R, tx: Sender<T>) where T: Send;
+pub(crate) trait StreamWorker<T>: Provider<T, Result<(), MactimeError>> {
+    fn worker<R: Read + Send>(input: R, tx: SyncSender<T>) -> Result<(), MactimeError> where T: Send;
 }
\ No newline at end of file
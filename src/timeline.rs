@@ -0,0 +1,479 @@
+use std::cmp::Ordering;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::io::{self, BufRead, BufReader, Read, Write};
+use std::sync::Arc;
+
+use bodyfile::Bodyfile3Line;
+use encoding_rs_io::DecodeReaderBytesBuilder;
+
+use crate::external_sort::*;
+use crate::{BodyfileDecoder, ClockSkew, BodyfileLine, DstMark, ListEntry, MACBFlags, Mactime2Writer, MactimeError, ResolvedTimestamp, RunOptions, Timestamp};
+
+/// an entry of a [`Timeline`], together with the timestamp which it has
+/// been created for
+pub type TimelineItem = Result<(Option<Timestamp>, ListEntry), MactimeError>;
+
+/// a timeline of one or more inputs, which can be read without setting up
+/// a pipeline of threads and channels:
+///
+/// ```
+/// use libmactime2::Timeline;
+///
+/// let bodyfile = "0|/etc/passwd|1|r|0|0|10|1661774614|1661772116|1661772116|-1\n";
+/// for entry in Timeline::from_reader(bodyfile.as_bytes()).sorted().unwrap() {
+///     let (timestamp, entry) = entry.unwrap();
+///     if let Some(timestamp) = timestamp {
+///         println!("{} {} {}", timestamp, entry.flags, entry.line.get_name());
+///     }
+/// }
+/// ```
+///
+/// Every entry has the timestamp of some of the MACB times of its line, or
+/// `None` if the line does not contain any timestamp. The entries are sorted
+/// by the point in time which their timestamps refer to, which is
+/// determined by [`RunOptions::dst_policy`] if a timestamp is affected by a
+/// DST transition in the source timezone. All filters of [`RunOptions`] are
+/// applied while the inputs are read, except [`RunOptions::hash_filter`]:
+/// known hashes are handled by a [`HashFilter`](crate::HashFilter), whose
+/// lines can be added using [`Timeline::with_lines`].
+pub struct Timeline {
+    inputs: Vec<(Input, Option<Arc<str>>)>,
+    options: RunOptions,
+}
+
+/// where the lines of a timeline are read from
+enum Input {
+    /// a bodyfile, which is parsed while it is being read, together with
+    /// the number of lines read so far
+    Reader(Box<dyn BufRead + Send>, usize),
+
+    /// lines which already have been parsed, e.g. by a [`BodyfileDecoder`]
+    Lines(Box<dyn Iterator<Item = BodyfileLine> + Send>),
+}
+
+impl Input {
+    /// returns the next line, or `None` at the end of the input
+    fn next_line(&mut self, options: &RunOptions) -> Result<Option<BodyfileLine>, MactimeError> {
+        match self {
+            Self::Lines(lines) => Ok(lines.next()),
+            Self::Reader(reader, line_number) => loop {
+                let mut line = String::new();
+                *line_number += 1;
+                let size = reader
+                    .read_line(&mut line)
+                    .map_err(|why| MactimeError::ReadError { line: *line_number, source: why })?;
+                if size == 0 {
+                    return Ok(None);
+                }
+                if let Some(line) = BodyfileDecoder::parse_line(line, *line_number, options)? {
+                    return Ok(Some(line));
+                }
+            },
+        }
+    }
+}
+
+impl Timeline {
+    pub fn new(options: RunOptions) -> Self {
+        Self {
+            inputs: Vec::new(),
+            options,
+        }
+    }
+
+    /// creates the timeline of a single bodyfile, using the default options
+    pub fn from_reader<R: Read + Send + 'static>(reader: R) -> Self {
+        Self::new(RunOptions::default()).with_reader(reader, None)
+    }
+
+    pub fn with_options(mut self, options: RunOptions) -> Self {
+        self.options = options;
+        self
+    }
+
+    /// adds another bodyfile. All inputs are merged into the same timeline,
+    /// and entries of inputs with a label carry this label as their source.
+    pub fn with_reader<R: Read + Send + 'static>(mut self, reader: R, label: Option<&str>) -> Self {
+        let reader = DecodeReaderBytesBuilder::new()
+            .encoding(Some(encoding_rs::UTF_8))
+            .utf8_passthru(true)
+            .build(reader);
+        self.inputs
+            .push((Input::Reader(Box::new(BufReader::new(reader)), 0), label.map(Arc::from)));
+        self
+    }
+
+    /// adds lines which already have been parsed, such as the lines received
+    /// from a [`BodyfileDecoder`]
+    pub fn with_lines<I>(mut self, lines: I, label: Option<&str>) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<BodyfileLine> + 'static,
+        I::IntoIter: Send + 'static,
+    {
+        self.inputs
+            .push((Input::Lines(Box::new(lines.into_iter().map(Into::into))), label.map(Arc::from)));
+        self
+    }
+
+    /// returns all entries in the order in which they have been read. Because
+    /// nothing is buffered, ambigious file names are not detected here.
+    pub fn entries(self) -> TimelineEntries {
+        TimelineEntries {
+            lines: self.lines(),
+            pending: VecDeque::new(),
+        }
+    }
+
+    /// reads all inputs and returns their entries ordered by timestamp.
+    /// Entries with the same timestamp keep the order in which they have
+    /// been read. If [`SortOptions::memory_limit`](crate::SortOptions) is
+    /// exceeded, the entries are sorted using temporary files.
+    pub fn sorted(self) -> Result<SortedTimeline, MactimeError> {
+        let mut lines = self.lines();
+        let mut entries = ExternalSorter::new(lines.options.sort_options.clone());
+
+        // only the hashes of the names are stored, because the set of names
+        // must not grow beyond the memory limit
+        let mut names: HashSet<u64> = HashSet::new();
+
+        while let Some((source, line)) = lines.next_line()? {
+            // each name && inode SHOULD occur only once per source
+            let mut hasher = DefaultHasher::new();
+            (source, line.get_inode(), line.get_name()).hash(&mut hasher);
+            if !names.insert(hasher.finish()) {
+                log::warn!("ambigious file name: '{}' and inode '{}'", line.get_name(), line.get_inode());
+            }
+
+            let line = Arc::new(line);
+            for (timestamp, flags) in timestamps(&line, &lines.options) {
+                let sequence = entries.len() as u64;
+                entries.push(SortEntry {
+                    timestamp,
+                    sequence,
+                    flags,
+                    source,
+                    line: Arc::clone(&line),
+                })?;
+            }
+        }
+
+        Ok(SortedTimeline {
+            records: entries.into_sorted()?,
+            sources: lines.sources,
+        })
+    }
+
+    /// writes the timeline into `sink`. The timeline is sorted, unless
+    /// [`SortOptions::convert_only`](crate::SortOptions) has been set.
+    pub fn write(self, output: &dyn Mactime2Writer, sink: &mut dyn Write) -> Result<(), MactimeError> {
+        write_entries(self.ordered()?, output, sink)
+    }
+
+    /// returns the entries in the order in which [`Timeline::write`] writes
+    /// them, which means that they are sorted, unless only conversion has
+    /// been requested in [`SortOptions`](crate::SortOptions)
+    pub fn ordered(self) -> Result<Box<dyn Iterator<Item = TimelineItem>>, MactimeError> {
+        if self.options.sort_options.convert_only {
+            Ok(Box::new(self.entries()))
+        } else {
+            Ok(Box::new(self.sorted()?))
+        }
+    }
+
+    fn lines(self) -> Lines {
+        Lines {
+            inputs: self.inputs.into_iter(),
+            current: None,
+            sources: Vec::new(),
+            options: self.options,
+        }
+    }
+}
+
+fn write_entries<I>(entries: I, output: &dyn Mactime2Writer, sink: &mut dyn Write) -> Result<(), MactimeError>
+where
+    I: Iterator<Item = TimelineItem>,
+{
+    output.write_header(sink)?;
+    for entry in entries {
+        let (timestamp, entry) = entry?;
+        output.write(sink, timestamp.as_ref(), &entry)?;
+    }
+    output.write_footer(sink)?;
+    Ok(())
+}
+
+/// returns the timestamps of a line which are accepted by the filters in
+/// `options`. A line without any timestamp has a single entry without a
+/// timestamp and without flags.
+fn timestamps(line: &BodyfileLine, options: &RunOptions) -> Vec<(Option<ResolvedTimestamp>, MACBFlags)> {
+    let mut timestamps: Vec<_> = MACBFlags::of_line(line)
+        .into_iter()
+        .flat_map(|(timestamp, flags)| {
+            options
+                .dst_policy
+                .resolve(timestamp, &options.src_zone)
+                .map(move |resolved| (Some(resolved), flags))
+        })
+        .collect();
+
+    // we need *some* value in mactimes!
+    if timestamps.is_empty() {
+        timestamps.push((None, MACBFlags::NONE));
+    }
+
+    // entries outside of the requested range are not stored at all. Entries
+    // without any timestamp cannot be inside a range.
+    timestamps.retain(|(timestamp, flags)| {
+        options.macb_filter.accepts(*flags)
+            && (options.range.is_unbounded()
+                || timestamp.is_some_and(|ts| options.range.contains(ts.instant.secs())))
+    });
+    timestamps
+}
+
+/// reads the lines of all inputs of a [`Timeline`], one input after another,
+/// so that the order of the lines does not depend on thread scheduling
+struct Lines {
+    inputs: std::vec::IntoIter<(Input, Option<Arc<str>>)>,
+    current: Option<(Input, usize)>,
+    sources: Vec<Option<Arc<str>>>,
+    options: RunOptions,
+}
+
+impl Lines {
+    /// returns the next line which is accepted by the path filter,
+    /// together with the index of its source
+    fn next_line(&mut self) -> Result<Option<(usize, BodyfileLine)>, MactimeError> {
+        loop {
+            let (input, source) = match self.current.as_mut() {
+                Some(current) => current,
+                None => match self.inputs.next() {
+                    // lines which have been received from a decoder which
+                    // failed are an incomplete input
+                    None if self.options.report.is_aborted() => return Err(MactimeError::InputAborted),
+                    None => return Ok(None),
+                    Some((input, label)) => {
+                        self.sources.push(label);
+                        self.current.insert((input, self.sources.len() - 1))
+                    }
+                },
+            };
+
+            match input.next_line(&self.options)? {
+                None => self.current = None,
+                Some(line) if self.options.path_filter.accepts(line.get_name()) => return Ok(Some((*source, line))),
+                Some(_) => (),
+            }
+        }
+    }
+}
+
+/// the entries of a [`Timeline`], in the order in which they have been read
+pub struct TimelineEntries {
+    lines: Lines,
+    pending: VecDeque<(Option<Timestamp>, ListEntry)>,
+}
+
+impl Iterator for TimelineEntries {
+    type Item = Result<(Option<Timestamp>, ListEntry), MactimeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        loop {
+            if let Some(entry) = self.pending.pop_front() {
+                return Some(Ok(entry));
+            }
+
+            let (source, line) = match self.lines.next_line() {
+                Err(why) => return Some(Err(why)),
+                Ok(None) => return None,
+                Ok(Some(next)) => next,
+            };
+            let line = Arc::new(line);
+            let source = &self.lines.sources[source];
+            for (timestamp, flags) in timestamps(&line, &self.lines.options) {
+                self.pending.push_back((
+                    timestamp.map(|ts| ts.local),
+                    ListEntry {
+                        flags,
+                        line: Arc::clone(&line),
+                        source: source.clone(),
+                        dst: timestamp.and_then(|ts| ts.mark),
+                    },
+                ));
+            }
+        }
+    }
+}
+
+/// the entries of a [`Timeline`], ordered by their timestamps
+pub struct SortedTimeline {
+    records: SortedRecords<SortEntry>,
+    sources: Vec<Option<Arc<str>>>,
+}
+
+impl Iterator for SortedTimeline {
+    type Item = Result<(Option<Timestamp>, ListEntry), MactimeError>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let entry = match self.records.next()? {
+            Err(why) => return Some(Err(why.into())),
+            Ok(entry) => entry,
+        };
+        Some(Ok((
+            entry.timestamp.map(|ts| ts.local),
+            ListEntry {
+                flags: entry.flags,
+                line: entry.line,
+                source: self.sources[entry.source].clone(),
+                dst: entry.timestamp.and_then(|ts| ts.mark),
+            },
+        )))
+    }
+}
+
+/// an entry of the timeline, as it is being sorted. Entries are ordered by
+/// the point in time of their timestamp in UTC, and entries with the same
+/// timestamp keep the order in which they have been inserted. Entries
+/// without a timestamp come first.
+struct SortEntry {
+    timestamp: Option<ResolvedTimestamp>,
+    sequence: u64,
+    flags: MACBFlags,
+    source: usize,
+    line: Arc<BodyfileLine>,
+}
+
+impl Eq for SortEntry {}
+impl PartialEq for SortEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.key() == other.key()
+    }
+}
+impl PartialOrd for SortEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl Ord for SortEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.key().cmp(&other.key())
+    }
+}
+
+impl SortEntry {
+    fn key(&self) -> (Option<Timestamp>, u64) {
+        (self.timestamp.map(|ts| ts.instant), self.sequence)
+    }
+}
+
+impl SortRecord for SortEntry {
+    /// lines are shared between the entries of their timestamps, so this
+    /// overestimates the memory usage
+    fn memory_size(&self) -> usize {
+        std::mem::size_of::<Self>()
+            + std::mem::size_of::<BodyfileLine>()
+            + self.line.get_md5().len()
+            + self.line.get_name().len()
+            + self.line.get_inode().len()
+            + self.line.get_mode().len()
+    }
+
+    fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write_timestamp(writer, self.timestamp.map(|ts| ts.local))?;
+        write_timestamp(writer, self.timestamp.map(|ts| ts.instant))?;
+        write_u64(writer, mark_to_u64(self.timestamp.and_then(|ts| ts.mark)))?;
+        write_u64(writer, self.sequence)?;
+        write_u64(writer, self.flags.bits().into())?;
+        write_u64(writer, self.source as u64)?;
+        write_str(writer, self.line.get_md5())?;
+        write_str(writer, self.line.get_name())?;
+        write_str(writer, self.line.get_inode())?;
+        write_str(writer, self.line.get_mode())?;
+        write_u64(writer, self.line.get_uid())?;
+        write_u64(writer, self.line.get_gid())?;
+        write_u64(writer, self.line.get_size())?;
+        for timestamp in self.line.timestamps().iter() {
+            write_timestamp(writer, *timestamp)?;
+        }
+        write_u64(writer, self.line.skew().is_some().into())?;
+        write_i64(writer, self.line.skew().unwrap_or_default().as_nanos())?;
+        write_u64(writer, self.line.is_known_bad().into())?;
+        Ok(())
+    }
+
+    fn read_from<R: Read>(reader: &mut R) -> io::Result<Option<Self>> {
+        let local = match read_first_i64(reader)? {
+            None => return Ok(None),
+            Some(secs) => read_timestamp_nanos(reader, secs)?,
+        };
+        let instant = read_timestamp(reader)?;
+        let mark = mark_from_u64(read_u64(reader)?);
+        let timestamp = local.zip(instant).map(|(local, instant)| ResolvedTimestamp { local, instant, mark });
+        let sequence = read_u64(reader)?;
+        let flags = MACBFlags::from_bits_truncate(read_u64(reader)? as u8);
+        let source = read_u64(reader)? as usize;
+        let line = Bodyfile3Line::from_values(
+            read_string(reader)?,
+            read_string(reader)?,
+            read_string(reader)?,
+            read_string(reader)?,
+            read_u64(reader)?,
+            read_u64(reader)?,
+            read_u64(reader)?,
+            -1,
+            -1,
+            -1,
+            -1,
+        );
+
+        // the timestamps have been written by `write_to` in MACB order
+        let mut timestamps = [None; 4];
+        for timestamp in timestamps.iter_mut() {
+            *timestamp = read_timestamp(reader)?;
+        }
+        let [mtime, atime, ctime, crtime] = timestamps;
+        let has_skew = read_u64(reader)? != 0;
+        let skew = Some(ClockSkew::from_nanos(read_i64(reader)?)).filter(|_| has_skew);
+        let known_bad = read_u64(reader)? != 0;
+
+        // the timestamps already have been corrected
+        let line = BodyfileLine::from(line)
+            .with_mtime(mtime)
+            .with_atime(atime)
+            .with_ctime(ctime)
+            .with_crtime(crtime)
+            .with_recorded_skew(skew)
+            .with_known_bad(known_bad);
+        Ok(Some(Self {
+            timestamp,
+            sequence,
+            flags,
+            source,
+            line: Arc::new(line),
+        }))
+    }
+}
+
+fn mark_to_u64(mark: Option<DstMark>) -> u64 {
+    match mark {
+        None => 0,
+        Some(DstMark::Earliest) => 1,
+        Some(DstMark::Latest) => 2,
+        Some(DstMark::Shifted) => 3,
+        Some(DstMark::Nonexistent) => 4,
+    }
+}
+
+fn mark_from_u64(value: u64) -> Option<DstMark> {
+    match value {
+        1 => Some(DstMark::Earliest),
+        2 => Some(DstMark::Latest),
+        3 => Some(DstMark::Shifted),
+        4 => Some(DstMark::Nonexistent),
+        _ => None,
+    }
+}
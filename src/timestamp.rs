@@ -0,0 +1,180 @@
+use std::fmt::{self, Display};
+use std::str::FromStr;
+
+use chrono::SecondsFormat;
+use clap::clap_derive::ValueEnum;
+
+const NANOS_PER_SEC: u32 = 1_000_000_000;
+
+/// a point in time with nanosecond precision, as it is read from a bodyfile.
+/// The timestamp is stored as seconds since the epoch, together with the
+/// nanoseconds since the beginning of this second, so that timestamps
+/// without a fraction behave exactly like plain unix timestamps. Timestamps
+/// before 1970 are negative. A timestamp which is not set is represented by
+/// `None`, not by a special value.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp {
+    secs: i64,
+    nanos: u32,
+}
+
+impl Timestamp {
+    pub fn new(secs: i64, nanos: u32) -> Self {
+        assert!(nanos < NANOS_PER_SEC, "invalid number of nanoseconds: {}", nanos);
+        Self { secs, nanos }
+    }
+
+    pub fn from_millis(millis: i64) -> Self {
+        Self::new(millis.div_euclid(1000), millis.rem_euclid(1000) as u32 * 1_000_000)
+    }
+
+    pub fn secs(&self) -> i64 {
+        self.secs
+    }
+
+    pub fn nanos(&self) -> u32 {
+        self.nanos
+    }
+
+    /// removes all digits of the fraction which cannot be displayed using
+    /// `precision`
+    pub fn truncate(self, precision: TimestampPrecision) -> Self {
+        let unit = precision.unit();
+        Self::new(self.secs, self.nanos - self.nanos % unit)
+    }
+}
+
+impl From<i64> for Timestamp {
+    fn from(secs: i64) -> Self {
+        Self::new(secs, 0)
+    }
+}
+
+/// parses timestamps like `1661772116` or `1661772116.123456789`. Fractions
+/// with more than nine digits are truncated to nanoseconds.
+impl FromStr for Timestamp {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("invalid timestamp: '{}'", s);
+        let (secs, fraction) = match s.split_once('.') {
+            None => return s.parse::<i64>().map(Self::from).map_err(|_| invalid()),
+            Some(parts) => parts,
+        };
+        if fraction.is_empty() || !fraction.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(invalid());
+        }
+        let nanos = format!("{:0<9}", &fraction[..fraction.len().min(9)])
+            .parse::<u32>()
+            .map_err(|_| invalid())?;
+        let negative = secs.starts_with('-');
+        let secs = secs.parse::<i64>().map_err(|_| invalid())?;
+
+        // the fraction of a negative timestamp moves it further into the past
+        if negative && nanos > 0 {
+            Ok(Self::new(secs - 1, NANOS_PER_SEC - nanos))
+        } else {
+            Ok(Self::new(secs, nanos))
+        }
+    }
+}
+
+/// displays the timestamp as it would appear in a bodyfile, without any
+/// trailing zeros in the fraction
+impl Display for Timestamp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.nanos == 0 {
+            return write!(f, "{}", self.secs);
+        }
+        let (secs, nanos) = if self.secs < 0 {
+            // `secs + nanos` lies between `secs` and `secs + 1`
+            (self.secs + 1, NANOS_PER_SEC - self.nanos)
+        } else {
+            (self.secs, self.nanos)
+        };
+        let sign = if self.secs < 0 && secs == 0 { "-" } else { "" };
+        let fraction = format!("{:09}", nanos);
+        write!(f, "{}{}.{}", sign, secs, fraction.trim_end_matches('0'))
+    }
+}
+
+/// the number of digits of the fraction of a second which are displayed
+#[derive(ValueEnum, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum TimestampPrecision {
+    #[default]
+    #[value(name = "s")]
+    Seconds,
+
+    #[value(name = "ms")]
+    Millis,
+
+    #[value(name = "us")]
+    Micros,
+
+    #[value(name = "ns")]
+    Nanos,
+}
+
+impl TimestampPrecision {
+    /// returns the number of digits of the fraction
+    pub fn digits(&self) -> usize {
+        match self {
+            Self::Seconds => 0,
+            Self::Millis => 3,
+            Self::Micros => 6,
+            Self::Nanos => 9,
+        }
+    }
+
+    /// returns the smallest displayable amount of time in nanoseconds
+    fn unit(&self) -> u32 {
+        10_u32.pow(9 - self.digits() as u32)
+    }
+
+    pub(crate) fn seconds_format(&self) -> SecondsFormat {
+        match self {
+            Self::Seconds => SecondsFormat::Secs,
+            Self::Millis => SecondsFormat::Millis,
+            Self::Micros => SecondsFormat::Micros,
+            Self::Nanos => SecondsFormat::Nanos,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Timestamp, TimestampPrecision};
+
+    #[test]
+    fn test_parse() {
+        assert_eq!("1661772116".parse(), Ok(Timestamp::new(1661772116, 0)));
+        assert_eq!("1661772116.5".parse(), Ok(Timestamp::new(1661772116, 500_000_000)));
+        assert_eq!("1661772116.123456789".parse(), Ok(Timestamp::new(1661772116, 123_456_789)));
+        assert_eq!("1661772116.1234567891".parse(), Ok(Timestamp::new(1661772116, 123_456_789)));
+        assert_eq!("-1".parse(), Ok(Timestamp::new(-1, 0)));
+        assert_eq!("-5.25".parse(), Ok(Timestamp::new(-6, 750_000_000)));
+        assert_eq!("-0.25".parse(), Ok(Timestamp::new(-1, 750_000_000)));
+
+        for invalid in ["", "1.", ".5", "1.x", "1.-5", "x"].iter() {
+            assert!(invalid.parse::<Timestamp>().is_err(), "'{}' has been accepted", invalid);
+        }
+    }
+
+    #[test]
+    fn test_display() {
+        for ts in ["1661772116", "1661772116.5", "1661772116.000000001", "-1", "-5.25", "-0.25"].iter() {
+            assert_eq!(ts.parse::<Timestamp>().unwrap().to_string(), *ts);
+        }
+    }
+
+    #[test]
+    fn test_order_and_truncate() {
+        let ts: Timestamp = "1661772116.123456789".parse().unwrap();
+        assert!(Timestamp::from(1661772116) < ts);
+        assert!(ts < Timestamp::from(1661772117));
+        assert_eq!(ts.truncate(TimestampPrecision::Seconds), Timestamp::new(1661772116, 0));
+        assert_eq!(ts.truncate(TimestampPrecision::Millis), Timestamp::new(1661772116, 123_000_000));
+        assert_eq!(ts.truncate(TimestampPrecision::Micros), Timestamp::new(1661772116, 123_456_000));
+        assert_eq!(ts.truncate(TimestampPrecision::Nanos), ts);
+    }
+}
@@ -0,0 +1,33 @@
+//! fixtures which are shared by the integration tests. Every test includes
+//! this module, but not every test uses all of its functions.
+#![allow(dead_code)]
+
+use clap::Parser;
+use libmactime2::*;
+use std::convert::TryFrom;
+
+/// creates a bodyfile which consists of the given lines
+pub fn bodyfile<S: AsRef<str>>(lines: &[S]) -> String {
+    lines.iter().map(|l| format!("{}\n", l.as_ref())).collect()
+}
+
+/// writes the timeline with the given output format, and returns the lines
+/// which have been written
+pub fn write_timeline(timeline: Timeline, output: &dyn Mactime2Writer) -> Vec<String> {
+    try_write_timeline(timeline, output).unwrap()
+}
+
+/// like [`write_timeline`], but returns the error if the timeline cannot be
+/// written
+pub fn try_write_timeline(timeline: Timeline, output: &dyn Mactime2Writer) -> Result<Vec<String>, MactimeError> {
+    let mut buffer = Vec::new();
+    timeline.write(output, &mut buffer)?;
+    Ok(String::from_utf8(buffer).unwrap().lines().map(str::to_owned).collect())
+}
+
+/// configures the application like `mactime2 -b sample.bodyfile`, followed
+/// by the given command line arguments
+pub fn application(args: &[&str]) -> Result<Mactime2Application, ConfigError> {
+    let args = ["mactime2", "-b", "sample.bodyfile"].iter().chain(args);
+    Mactime2Application::try_from(Cli::parse_from(args))
+}
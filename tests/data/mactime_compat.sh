@@ -0,0 +1,10 @@
+#!/bin/sh
+# creates the expected output of tests/test_mactime_compat.rs, using the
+# `mactime` of The Sleuth Kit
+set -e
+cd "$(dirname "$0")"
+
+mactime -b mactime_compat.bodyfile -z UTC > mactime_compat.txt
+mactime -b mactime_compat.bodyfile -z UTC -d > mactime_compat.csv
+mactime -b mactime_compat.bodyfile -z Europe/Berlin -y > mactime_compat_iso8601.txt
+mactime -b mactime_compat.bodyfile -z Europe/Berlin -m > mactime_compat_month_berlin.txt
@@ -1,12 +1,5 @@
-use ::bodyfile::Bodyfile3Line;
 use libmactime2::*;
-use std::borrow::Borrow;
-use std::collections::HashSet;
 use std::sync::mpsc::{self, Sender, Receiver};
-use std::cell::RefCell;
-
-#[macro_use]
-extern crate more_asserts;
 
 #[test]
 fn test_ambiguity1() {
@@ -14,13 +7,16 @@ fn test_ambiguity1() {
 
     let options = RunOptions {
         strict_mode: false,
-        src_zone: chrono_tz::Tz::UTC
+        src_zone: chrono_tz::Tz::UTC,
+        ..Default::default()
     };
 
-    let mut decoder = BodyfileDecoder::with_receiver(rx, options);
+    let buffer = SharedBuffer::new();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
     let mut sorter = BodyfileSorter::default()
         .with_receiver(decoder.get_receiver(), options)
-        .with_output(Box::new(EventCatcher::new()));
+        .with_output(Box::new(CsvOutput::new(chrono_tz::Tz::UTC, chrono_tz::Tz::UTC)))
+        .with_output_file(OutputFile::from_writer(buffer.clone()));
 
     sorter.run();
 
@@ -44,37 +40,20 @@ fn test_ambiguity1() {
 
     drop(tx);
 
-    decoder.join().unwrap();
-    assert!(sorter.join().is_ok());
+    decoder.join().unwrap().unwrap();
+    assert!(sorter.join().unwrap().is_ok());
+
+    // both entries must be displayed, even if they have the same name
+    let output = String::from_utf8(buffer.contents()).unwrap();
+    let lines: Vec<&str> = output.lines().collect();
+    assert_eq!(lines.len(), 2);
+    for (line, ts) in lines.iter().zip([ts1, ts2].iter()) {
+        let date = Mactime2Application::format_date(*ts, &chrono_tz::Tz::UTC, &chrono_tz::Tz::UTC);
+        assert!(line.starts_with(&date), "{}", line);
+        assert!(line.ends_with(",sample1.txt"), "{}", line);
+    }
 }
 
 fn random_ts() -> i64 {
     rand::random::<u32>() as i64
 }
-
-struct EventCatcher {
-    last_timestamp: RefCell<i64>,
-    names: RefCell<HashSet<String>>,
-}
-
-impl EventCatcher {
-    pub fn new () -> Self {
-        Self {
-            last_timestamp: RefCell::new(-1),
-            names: RefCell::new(HashSet::new())
-        }
-    }
-}
-
-impl Mactime2Writer for EventCatcher {
-    fn fmt(&self, timestamp: &i64, entry: &ListEntry) -> String {
-        assert_le!(*self.last_timestamp.borrow(), *timestamp);
-        *self.last_timestamp.borrow_mut() = *timestamp;
-
-        let bf: &Bodyfile3Line = entry.line.borrow();
-        //assert!(! self.names.borrow().contains(bf.get_name()));
-        self.names.borrow_mut().insert(bf.get_name().to_owned());
-
-        "".to_owned()
-    }
-}
\ No newline at end of file
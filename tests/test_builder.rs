@@ -0,0 +1,86 @@
+mod common;
+
+use libmactime2::*;
+
+#[test]
+fn test_valid_configuration() {
+    let app = Mactime2Application::builder()
+        .bodyfile("host1=/cases/host1.bodyfile")
+        .bodyfile("/cases/host2.bodyfile")
+        .format(OutputFormat::JSON)
+        .output("/cases/timeline.json")
+        .src_zone(chrono_tz::Europe::Berlin)
+        .strict_mode(true)
+        .threads(4_usize)
+        .sort_options(SortOptions {
+            memory_limit: Some(1 << 20),
+            temp_dir: Some(std::env::temp_dir()),
+            convert_only: false,
+        })
+        .build();
+    assert!(app.is_ok());
+
+    assert!(Mactime2Application::builder().build().is_ok());
+}
+
+#[test]
+fn test_invalid_configuration() {
+    let error = |builder: Mactime2ApplicationBuilder| builder.build().err().unwrap();
+
+    assert_eq!(
+        error(Mactime2Application::builder().bodyfiles(Vec::new())),
+        ConfigError::NoInput
+    );
+    assert_eq!(
+        error(Mactime2Application::builder().threads(0_usize)),
+        ConfigError::InvalidThreadCount
+    );
+
+    let from: DateBound = "2022-08-30".parse().unwrap();
+    let to: DateBound = "2022-08-29".parse().unwrap();
+    assert_eq!(
+        error(Mactime2Application::builder().range(DateRange::with_bounds(
+            Some(&from),
+            Some(&to),
+            &chrono_tz::Tz::UTC
+        ))),
+        ConfigError::EmptyDateRange
+    );
+
+    assert_eq!(
+        error(Mactime2Application::builder().sort_options(SortOptions {
+            memory_limit: Some(1 << 20),
+            convert_only: true,
+            ..Default::default()
+        })),
+        ConfigError::ConflictingOptions("convert_only", "memory_limit")
+    );
+    assert_eq!(
+        error(Mactime2Application::builder().sort_options(SortOptions {
+            temp_dir: Some(std::env::temp_dir()),
+            ..Default::default()
+        })),
+        ConfigError::MissingOption("temp_dir", "memory_limit")
+    );
+    assert_eq!(
+        error(Mactime2Application::builder().rejects("-")),
+        ConfigError::ConflictingOptions("rejects", "output")
+    );
+    assert!(matches!(
+        error(Mactime2Application::builder().sort_options(SortOptions {
+            memory_limit: Some(1 << 20),
+            temp_dir: Some("/nonexisting/directory".into()),
+            ..Default::default()
+        })),
+        ConfigError::InvalidTempDir(_)
+    ));
+}
+
+#[test]
+fn test_cli() {
+    assert!(common::application(&["-f", "Europe/Berlin", "-d"]).is_ok());
+    assert_eq!(
+        common::application(&["-t", "Mars/Olympus_Mons"]).err(),
+        Some(ConfigError::InvalidTimezone("Mars/Olympus_Mons".to_owned()))
+    );
+}
@@ -0,0 +1,68 @@
+use libmactime2::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+#[test]
+fn test_convert_only() {
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let options = RunOptions {
+        sort_options: SortOptions {
+            convert_only: true,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+
+    let entries = Arc::new(Mutex::new(Vec::new()));
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let mut sorter = BodyfileSorter::default()
+        .with_receiver(decoder.get_receiver(), options)
+        .with_output(Box::new(EntryCollector(Arc::clone(&entries))));
+    sorter.run();
+
+    let bf = ::bodyfile::Bodyfile3Line::new()
+        .with_name("newer.txt")
+        .with_mtime(2000)
+        .with_atime(1000)
+        .with_ctime(2000);
+    tx.send(bf.to_string()).unwrap();
+
+    // entries must be written before the input has been closed
+    let start = Instant::now();
+    while entries.lock().unwrap().len() < 2 {
+        assert!(start.elapsed() < Duration::from_secs(10), "entries have not been written");
+        std::thread::sleep(Duration::from_millis(10));
+    }
+
+    let bf = ::bodyfile::Bodyfile3Line::new()
+        .with_name("older.txt")
+        .with_crtime(500);
+    tx.send(bf.to_string()).unwrap();
+    drop(tx);
+
+    decoder.join().unwrap().unwrap();
+    sorter.join().unwrap().unwrap();
+
+    assert_eq!(
+        *entries.lock().unwrap(),
+        vec![
+            "2000 m.c. newer.txt".to_owned(),
+            "1000 .a.. newer.txt".to_owned(),
+            "500 ...b older.txt".to_owned(),
+        ]
+    );
+}
+
+struct EntryCollector(Arc<Mutex<Vec<String>>>);
+
+impl Mactime2Writer for EntryCollector {
+    fn fmt(&self, timestamp: Option<&Timestamp>, entry: &ListEntry) -> String {
+        let timestamp = timestamp.expect("all lines have timestamps");
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{} {} {}", timestamp, entry.flags, entry.line.get_name()));
+        "".to_owned()
+    }
+}
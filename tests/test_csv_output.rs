@@ -0,0 +1,47 @@
+mod common;
+
+use clap::Parser;
+use chrono_tz::Tz;
+use libmactime2::*;
+
+// names which are not valid in CSV unless they are quoted and escaped. Line
+// breaks cannot occur in bodyfiles.
+const BODYFILE: &str = "0|/tmp/\"quoted\"|1|r/rrw-r--r--|0|0|10|-1|1662035696|-1|-1
+0|/tmp/a,b;c|2|r/rrw-r--r--|0|0|10|-1|1662035697|-1|-1
+";
+
+fn write_timeline(output: &CsvOutput) -> String {
+    common::write_timeline(Timeline::from_reader(BODYFILE.as_bytes()), output).join("\n")
+}
+
+fn names(timeline: &str, delimiter: u8) -> Vec<String> {
+    let mut reader = csv::ReaderBuilder::new().delimiter(delimiter).from_reader(timeline.as_bytes());
+    let headers = reader.headers().unwrap().clone();
+    let name = headers.iter().position(|h| h == "File Name").unwrap();
+    reader.records().map(|r| r.unwrap()[name].to_owned()).collect()
+}
+
+#[test]
+fn test_roundtrip() {
+    let timeline = write_timeline(&CsvOutput::new(Tz::UTC, Tz::UTC).with_header(true));
+    assert!(timeline.starts_with("Date,Size,Type,Mode,UID,GID,Meta,File Name\n"));
+    assert_eq!(names(&timeline, b','), vec!["/tmp/\"quoted\"", "/tmp/a,b;c"]);
+
+    let timeline = write_timeline(&CsvOutput::new(Tz::UTC, Tz::UTC).with_header(true).with_delimiter(b';'));
+    assert_eq!(names(&timeline, b';'), vec!["/tmp/\"quoted\"", "/tmp/a,b;c"]);
+}
+
+#[test]
+fn test_options() {
+    let app = common::application(&["-d", "--csv-delimiter", "tab", "--no-header", "--csv-columns", "date,name,source"]);
+    assert!(app.is_ok());
+
+    assert!(Cli::try_parse_from(["mactime2", "--csv-delimiter", ";;"]).is_err());
+    assert!(Cli::try_parse_from(["mactime2", "--csv-columns", "date,sha1"]).is_err());
+
+    // the CSV options cannot be used with other formats
+    assert!(matches!(
+        common::application(&["--csv-bom"]),
+        Err(ConfigError::MissingOption("csv_options", "csv"))
+    ));
+}
@@ -0,0 +1,44 @@
+mod common;
+
+use chrono_tz::Tz;
+use libmactime2::*;
+
+fn sample_bodyfile() -> String {
+    common::bodyfile(&[
+        "0|/tmp/a|1|r/rrw-r--r--|0|0|10|1662035696|1662035696.5|-1|-1",
+        "0|/tmp/b|2|r/rrw-r--r--|0|0|10|-1|-1|-1|-1",
+        "0|/tmp/c|3|r/rrw-r--r--|0|0|10|-1|1662035697|-1|-1",
+    ])
+}
+
+fn write_timeline(output: &dyn Mactime2Writer) -> Vec<String> {
+    common::write_timeline(Timeline::from_reader(std::io::Cursor::new(sample_bodyfile())), output)
+}
+
+fn date_format(format: &str) -> DateFormat {
+    format.parse().unwrap()
+}
+
+#[test]
+fn test_txt_width() {
+    for format in ["iso8601", "excel", "epoch", "mactime", "%d.%m.%Y %H:%M:%S %Z"].iter() {
+        let output = TxtOutput::new(Tz::UTC, Tz::UTC)
+            .with_date_format(date_format(format))
+            .with_placeholder("-");
+
+        // the second entry has the same timestamp as the first one
+        let lines = write_timeline(&output);
+        let width = lines[1].find(" 10 ").unwrap();
+        assert_eq!(lines[0].find(" 10 "), Some(width), "format: '{}'", format);
+        assert_eq!(lines[2].find(" 10 "), Some(width), "format: '{}'", format);
+        assert_eq!(lines[2][..width].trim(), "", "format: '{}'", format);
+    }
+}
+
+#[test]
+fn test_builder() {
+    let app = Mactime2Application::builder()
+        .date_format(date_format("%Y"))
+        .build();
+    assert!(app.is_ok());
+}
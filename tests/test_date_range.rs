@@ -0,0 +1,53 @@
+use libmactime2::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_date_range() {
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+
+    let from: DateBound = "2022-08-29".parse().unwrap();
+    let to: DateBound = "2022-08-30".parse().unwrap();
+    let options = RunOptions {
+        range: DateRange::with_bounds(Some(&from), Some(&to), &chrono_tz::Tz::UTC),
+        ..Default::default()
+    };
+
+    let timestamps = Arc::new(Mutex::new(Vec::new()));
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let mut sorter = BodyfileSorter::default()
+        .with_receiver(decoder.get_receiver(), options)
+        .with_output(Box::new(TimestampCollector(Arc::clone(&timestamps))));
+    sorter.run();
+
+    // 2022-08-28T23:59:59, 2022-08-29T00:00:00 and 2022-08-30T23:59:59, 2022-08-31T00:00:00
+    let inside = [1661731200, 1661903999];
+    let outside = [1661731199, 1661904000];
+
+    let bf = ::bodyfile::Bodyfile3Line::new()
+        .with_name("sample1.txt")
+        .with_mtime(inside[0])
+        .with_atime(outside[0])
+        .with_ctime(inside[1])
+        .with_crtime(outside[1]);
+    tx.send(bf.to_string()).unwrap();
+
+    let bf = ::bodyfile::Bodyfile3Line::new().with_name("no_timestamps.txt");
+    tx.send(bf.to_string()).unwrap();
+
+    drop(tx);
+    decoder.join().unwrap().unwrap();
+    sorter.join().unwrap().unwrap();
+
+    assert_eq!(*timestamps.lock().unwrap(), inside.to_vec());
+}
+
+struct TimestampCollector(Arc<Mutex<Vec<i64>>>);
+
+impl Mactime2Writer for TimestampCollector {
+    fn fmt(&self, timestamp: Option<&Timestamp>, _entry: &ListEntry) -> String {
+        let timestamp = timestamp.expect("entries without timestamps are never inside a range");
+        self.0.lock().unwrap().push(timestamp.secs());
+        "".to_owned()
+    }
+}
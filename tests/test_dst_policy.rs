@@ -0,0 +1,171 @@
+mod common;
+
+use chrono_tz::Tz;
+use libmactime2::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+// 2022-10-30T02:30:00 occurred twice in Berlin, and 2022-03-27T02:30:00 did
+// not exist at all
+const AMBIGUOUS: i64 = 1667097000;
+const NONEXISTENT: i64 = 1648348200;
+
+fn sample_bodyfile() -> String {
+    common::bodyfile(&[
+        format!("0|/tmp/fold|1|r/rrw-r--r--|0|0|10|-1|{}|-1|-1", AMBIGUOUS),
+        format!("0|/tmp/after_fold|2|r/rrw-r--r--|0|0|10|-1|{}|-1|-1", AMBIGUOUS + 2400),
+        format!("0|/tmp/gap|3|r/rrw-r--r--|0|0|10|-1|{}|-1|-1", NONEXISTENT),
+    ])
+}
+
+fn options(dst_policy: DstPolicy, report: &ParseReport) -> RunOptions {
+    RunOptions {
+        src_zone: Tz::Europe__Berlin,
+        dst_policy,
+        report: report.clone(),
+        ..Default::default()
+    }
+}
+
+fn write_timeline(dst_policy: DstPolicy, memory_limit: Option<usize>) -> Result<Vec<String>, MactimeError> {
+    let mut options = options(dst_policy, &ParseReport::new());
+    options.sort_options.memory_limit = memory_limit;
+
+    let timeline = Timeline::new(options).with_reader(std::io::Cursor::new(sample_bodyfile()), None);
+    common::try_write_timeline(timeline, &CsvOutput::new(Tz::Europe__Berlin, Tz::UTC))
+}
+
+fn timestamps(lines: &[String]) -> Vec<&str> {
+    lines.iter().map(|l| l.split(',').next().unwrap()).collect()
+}
+
+#[test]
+fn test_ambiguous() {
+    let policy = |ambiguous| DstPolicy {
+        ambiguous,
+        ..Default::default()
+    };
+
+    let lines = write_timeline(policy(AmbiguousPolicy::Earliest), None).unwrap();
+    assert_eq!(
+        timestamps(&lines),
+        vec![
+            "2022-03-27T02:30:00 [dst:nonexistent]",
+            "2022-10-30T00:30:00+00:00 [dst:earliest]",
+            "2022-10-30T02:10:00+00:00",
+        ]
+    );
+
+    let lines = write_timeline(policy(AmbiguousPolicy::Latest), None).unwrap();
+    assert_eq!(timestamps(&lines)[1], "2022-10-30T01:30:00+00:00 [dst:latest]");
+
+    // both occurrences are sorted by the point in time they refer to, also
+    // if the timeline is sorted on disk
+    for memory_limit in [None, Some(1)].iter().copied() {
+        let lines = write_timeline(policy(AmbiguousPolicy::Both), memory_limit).unwrap();
+        assert_eq!(
+            timestamps(&lines),
+            vec![
+                "2022-03-27T02:30:00 [dst:nonexistent]",
+                "2022-10-30T00:30:00+00:00 [dst:earliest]",
+                "2022-10-30T01:30:00+00:00 [dst:latest]",
+                "2022-10-30T02:10:00+00:00",
+            ]
+        );
+        assert!(lines[1].ends_with(",/tmp/fold"));
+        assert!(lines[2].ends_with(",/tmp/fold"));
+    }
+}
+
+#[test]
+fn test_gap() {
+    let lines = write_timeline(
+        DstPolicy {
+            gap: Some(GapPolicy::Shift),
+            ..Default::default()
+        },
+        None,
+    )
+    .unwrap();
+    assert_eq!(timestamps(&lines)[0], "2022-03-27T01:30:00+00:00 [dst:shifted]");
+}
+
+#[test]
+fn test_errors() {
+    let ambiguous = DstPolicy {
+        ambiguous: AmbiguousPolicy::Error,
+        ..Default::default()
+    };
+    match write_timeline(ambiguous, None) {
+        Err(MactimeError::AmbiguousTimestamp { line: 1, timestamp: AMBIGUOUS, .. }) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+
+    let gap = DstPolicy {
+        gap: Some(GapPolicy::Error),
+        ..Default::default()
+    };
+    match write_timeline(gap, None) {
+        Err(MactimeError::TimezoneError { line: 3, timestamp: NONEXISTENT, .. }) => (),
+        result => panic!("unexpected result: {:?}", result),
+    }
+}
+
+#[test]
+fn test_statistics() {
+    let report = ParseReport::new();
+    let timeline = Timeline::new(options(DstPolicy::default(), &report))
+        .with_reader(std::io::Cursor::new(sample_bodyfile()), None);
+    common::write_timeline(timeline, &CsvOutput::new(Tz::Europe__Berlin, Tz::UTC));
+
+    let statistics = report.statistics();
+    assert_eq!(statistics.ambiguous_timestamps, 1);
+    assert_eq!(statistics.nonexistent_timestamps, 1);
+    assert!(statistics.to_string().ends_with("found 1 ambiguous and 1 nonexistent timestamps"));
+}
+
+#[cfg(feature = "elastic")]
+#[test]
+fn test_json_tags() {
+    let options = options(
+        DstPolicy {
+            ambiguous: AmbiguousPolicy::Both,
+            ..Default::default()
+        },
+        &ParseReport::new(),
+    );
+
+    let buffer = SharedBuffer::new();
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let mut sorter = JsonSorter::with_receiver(decoder.get_receiver(), options)
+        .with_output_file(OutputFile::from_writer(buffer.clone()));
+    sorter.run();
+    for line in sample_bodyfile().lines() {
+        tx.send(line.to_owned()).unwrap();
+    }
+    drop(tx);
+    decoder.join().unwrap().unwrap();
+    sorter.join().unwrap().unwrap();
+
+    let documents: Vec<serde_json::Value> = String::from_utf8(buffer.contents())
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let documents: Vec<(i64, &str)> = documents
+        .iter()
+        .map(|d| {
+            let tags = d["tags"].as_array().unwrap();
+            (d["@timestamp"].as_i64().unwrap() / 1000, tags.last().unwrap().as_str().unwrap())
+        })
+        .collect();
+    assert_eq!(
+        documents,
+        vec![
+            (NONEXISTENT - 3600, "dst:nonexistent"),
+            (AMBIGUOUS - 7200, "dst:earliest"),
+            (AMBIGUOUS - 3600, "dst:latest"),
+            (AMBIGUOUS + 2400 - 3600, "bodyfile"),
+        ]
+    );
+}
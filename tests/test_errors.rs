@@ -0,0 +1,180 @@
+use libmactime2::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+fn decode(lines: &[String], options: RunOptions) -> Result<usize, MactimeError> {
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options);
+    let receiver = decoder.get_receiver();
+
+    for line in lines {
+        tx.send(line.clone()).unwrap();
+    }
+    drop(tx);
+
+    let count = receiver.iter().count();
+    decoder.join().unwrap().map(|_| count)
+}
+
+fn sample_lines(invalid_line: usize) -> Vec<String> {
+    (1..=5000)
+        .map(|i| {
+            if i == invalid_line {
+                "invalid line".to_owned()
+            } else {
+                ::bodyfile::Bodyfile3Line::new()
+                    .with_name(&format!("/tmp/sample_{}", i))
+                    .with_mtime(i as i64)
+                    .to_string()
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn test_parse_error() {
+    for threads in [1, 4].iter().copied() {
+        let options = RunOptions {
+            threads,
+            ..Default::default()
+        };
+        assert_eq!(decode(&sample_lines(4321), options.clone()).unwrap(), 4999);
+
+        let options = RunOptions {
+            strict_mode: true,
+            ..options
+        };
+        let error = decode(&sample_lines(4321), options).unwrap_err();
+        assert!(matches!(error, MactimeError::ParseError { line: 4321, .. }));
+        assert_eq!(error.exit_code(), 4);
+    }
+}
+
+#[test]
+fn test_timezone_error() {
+    // 2022-03-27 02:30:00 does not exist in Europe/Berlin
+    let lines = vec![::bodyfile::Bodyfile3Line::new()
+        .with_name("/tmp/dst_gap")
+        .with_mtime(1648348200)
+        .to_string()];
+
+    let options = RunOptions {
+        src_zone: chrono_tz::Europe::Berlin,
+        ..Default::default()
+    };
+    assert_eq!(decode(&lines, options.clone()).unwrap(), 1);
+
+    let options = RunOptions {
+        strict_mode: true,
+        ..options
+    };
+
+    // an explicit gap policy wins over strict mode
+    for gap in [GapPolicy::Shift, GapPolicy::Mark].iter().copied() {
+        let options = RunOptions {
+            dst_policy: DstPolicy {
+                gap: Some(gap),
+                ..Default::default()
+            },
+            ..options.clone()
+        };
+        assert_eq!(decode(&lines, options).unwrap(), 1);
+    }
+
+    let error = decode(&lines, options).unwrap_err();
+    assert!(matches!(
+        error,
+        MactimeError::TimezoneError {
+            line: 1,
+            timestamp: 1648348200,
+            ..
+        }
+    ));
+    assert_eq!(error.exit_code(), 5);
+}
+
+/// a reader which fails after it returned `data`
+struct FailingReader(std::io::Cursor<Vec<u8>>);
+
+impl std::io::Read for FailingReader {
+    fn read(&mut self, buf: &mut [u8]) -> std::io::Result<usize> {
+        match self.0.read(buf)? {
+            0 => Err(std::io::ErrorKind::ConnectionReset.into()),
+            size => Ok(size),
+        }
+    }
+}
+
+#[test]
+fn test_read_error() {
+    let input = b"0|/tmp/a|0|0|0|0|0|1|2|3|4\n0|/tmp/b|0|0|0|0|0|1|2|3|4\n".to_vec();
+    let error = Timeline::from_reader(FailingReader(std::io::Cursor::new(input)))
+        .entries()
+        .find_map(Result::err)
+        .unwrap();
+    assert!(matches!(error, MactimeError::ReadError { line: 3, .. }));
+    assert_eq!(error.exit_code(), 3);
+}
+
+/// decodes `lines` and writes the timeline as text and as JSON, and returns
+/// the errors of the writers together with everything they have written
+fn write_outputs(lines: &[String], options: RunOptions) -> Vec<(MactimeError, Vec<u8>)> {
+    let decoder = |options: &RunOptions| {
+        let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+        for line in lines {
+            tx.send(line.clone()).unwrap();
+        }
+        BodyfileDecoder::with_receiver(rx, options.clone())
+    };
+
+    let mut text_decoder = decoder(&options);
+    let mut text = Vec::new();
+    let text_error = Timeline::new(options.clone())
+        .with_lines(text_decoder.get_receiver(), None)
+        .write(&TxtOutput::new(options.src_zone, options.src_zone), &mut text)
+        .unwrap_err();
+    assert!(text_decoder.join().unwrap().is_err());
+
+    let mut json_decoder = decoder(&options);
+    let json = SharedBuffer::new();
+    let mut sorter = JsonSorter::with_receiver(json_decoder.get_receiver(), options)
+        .with_output_file(OutputFile::from_writer(json.clone()));
+    sorter.run();
+    let json_error = sorter.join().unwrap().unwrap_err();
+    assert!(json_decoder.join().unwrap().is_err());
+
+    vec![(text_error, text), (json_error, json.contents())]
+}
+
+#[test]
+fn test_no_output_after_error() {
+    // nothing must be written if a decoder fails, because the timeline
+    // would look complete, although it is not
+    for threads in [1, 4].iter().copied() {
+        let options = RunOptions {
+            strict_mode: true,
+            threads,
+            ..Default::default()
+        };
+        for (error, output) in write_outputs(&sample_lines(4321), options) {
+            assert!(matches!(error, MactimeError::InputAborted));
+            assert!(output.is_empty(), "unexpected output: {} bytes", output.len());
+        }
+    }
+
+    let lines = vec![::bodyfile::Bodyfile3Line::new()
+        .with_name("/tmp/dst_gap")
+        .with_mtime(1648348200)
+        .to_string()];
+    let options = RunOptions {
+        src_zone: chrono_tz::Europe::Berlin,
+        dst_policy: DstPolicy {
+            gap: Some(GapPolicy::Error),
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    for (error, output) in write_outputs(&lines, options) {
+        assert!(matches!(error, MactimeError::InputAborted));
+        assert!(output.is_empty(), "unexpected output: {} bytes", output.len());
+    }
+}
@@ -0,0 +1,74 @@
+use libmactime2::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
+
+#[test]
+fn test_external_sort() {
+    let lines: Vec<String> = (0..5000)
+        .map(|i| {
+            ::bodyfile::Bodyfile3Line::new()
+                .with_name(&format!("sample_{}", i))
+                .with_inode(&format!("{}", i % 17))
+                .with_atime(random_ts())
+                .with_mtime(random_ts())
+                .with_ctime(if i % 3 == 0 { -1 } else { random_ts() })
+                .with_crtime(if i % 5 == 0 { -1 } else { random_ts() })
+                .to_string()
+        })
+        .collect();
+
+    let in_memory = sort_lines(&lines, SortOptions::default());
+    let on_disk = sort_lines(
+        &lines,
+        SortOptions {
+            memory_limit: Some(64 * 1024),
+            temp_dir: Some(std::env::temp_dir()),
+            ..Default::default()
+        },
+    );
+
+    assert!(in_memory.len() > lines.len());
+    assert_eq!(in_memory, on_disk);
+}
+
+fn sort_lines(lines: &[String], sort_options: SortOptions) -> Vec<String> {
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let options = RunOptions {
+        sort_options,
+        ..Default::default()
+    };
+
+    let entries = Arc::new(Mutex::new(Vec::new()));
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let mut sorter = BodyfileSorter::default()
+        .with_receiver(decoder.get_receiver(), options)
+        .with_output(Box::new(EntryCollector(Arc::clone(&entries))));
+    sorter.run();
+
+    for line in lines {
+        tx.send(line.clone()).unwrap();
+    }
+    drop(tx);
+    decoder.join().unwrap().unwrap();
+    sorter.join().unwrap().unwrap();
+
+    let entries = entries.lock().unwrap();
+    entries.clone()
+}
+
+fn random_ts() -> i64 {
+    // use a small range of timestamps, so that there are lots of collisions
+    (rand::random::<u32>() % 1000) as i64
+}
+
+struct EntryCollector(Arc<Mutex<Vec<String>>>);
+
+impl Mactime2Writer for EntryCollector {
+    fn fmt(&self, timestamp: Option<&Timestamp>, entry: &ListEntry) -> String {
+        self.0
+            .lock()
+            .unwrap()
+            .push(format!("{:?} {} {}", timestamp, entry.flags, entry.line));
+        "".to_owned()
+    }
+}
@@ -0,0 +1,67 @@
+mod common;
+
+use chrono_tz::Tz;
+use libmactime2::*;
+
+const BODYFILE: &str = "\
+d41d8cd98f00b204e9800998ecf8427e|/tmp/a|1|r/rrw-r--r--|0|0|10|1662035697|1662035696.5|-1|1662035690
+0|/tmp/b c|2|r/rrw-r--r--|0|0|10|-1|1662035699|-1|-1
+";
+
+fn write_timeline(options: RunOptions, output: &dyn Mactime2Writer) -> Vec<String> {
+    common::write_timeline(Timeline::new(options).with_reader(BODYFILE.as_bytes(), None), output)
+}
+
+#[test]
+fn test_csv() {
+    let output = CsvOutput::new(Tz::UTC, Tz::UTC)
+        .with_header(true)
+        .with_md5_column()
+        .with_timestamp_columns();
+    assert_eq!(
+        write_timeline(RunOptions::default(), &output),
+        vec![
+            "Date,Size,Type,Mode,UID,GID,Meta,File Name,MD5,Mtime,Atime,Ctime,Crtime",
+            "2022-09-01T12:34:50+00:00,10,...b,r/rrw-r--r--,0,0,1,/tmp/a,d41d8cd98f00b204e9800998ecf8427e,1662035696.5,1662035697,-1,1662035690",
+            "2022-09-01T12:34:56+00:00,10,m...,r/rrw-r--r--,0,0,1,/tmp/a,d41d8cd98f00b204e9800998ecf8427e,1662035696.5,1662035697,-1,1662035690",
+            "2022-09-01T12:34:57+00:00,10,.a..,r/rrw-r--r--,0,0,1,/tmp/a,d41d8cd98f00b204e9800998ecf8427e,1662035696.5,1662035697,-1,1662035690",
+            "2022-09-01T12:34:59+00:00,10,m...,r/rrw-r--r--,0,0,2,/tmp/b c,0,1662035699,-1,-1,-1",
+        ]
+    );
+}
+
+#[test]
+fn test_txt() {
+    let output = TxtOutput::new(Tz::UTC, Tz::UTC).with_md5_column().with_timestamp_columns();
+    let lines = write_timeline(RunOptions::default(), &output);
+    assert!(
+        lines[0].ends_with(" 1 d41d8cd98f00b204e9800998ecf8427e 1662035696.5 1662035697 -1 1662035690 /tmp/a"),
+        "unexpected line: '{}'",
+        lines[0]
+    );
+    assert!(
+        lines[3].ends_with(&format!(" 2 {:<32} 1662035699 -1 -1 -1 /tmp/b c", "0")),
+        "unexpected line: '{}'",
+        lines[3]
+    );
+}
+
+#[test]
+fn test_skewed_timestamps() {
+    // the timestamps are displayed after they have been corrected
+    let options = RunOptions {
+        skew: Some("-00:00:10".parse().unwrap()),
+        ..Default::default()
+    };
+    let output = CsvOutput::new(Tz::UTC, Tz::UTC).with_columns(vec![CsvColumn::Name, CsvColumn::Mtime, CsvColumn::Skew]);
+    assert_eq!(
+        write_timeline(options, &output).last().unwrap(),
+        "/tmp/b c,1662035689,-00:00:10"
+    );
+}
+
+#[test]
+fn test_options() {
+    assert!(common::application(&["--md5", "--macb-times"]).is_ok());
+    assert!(common::application(&["-d", "--csv-columns", "date,md5,crtime,name"]).is_ok());
+}
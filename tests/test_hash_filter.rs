@@ -0,0 +1,152 @@
+mod common;
+
+use clap::Parser;
+use chrono_tz::Tz;
+use libmactime2::*;
+use std::io::Write;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+const GOOD: &str = "d41d8cd98f00b204e9800998ecf8427e";
+const BAD: &str = "0cc175b9c0f1b6a831c399e269772661";
+
+fn sample_bodyfile() -> String {
+    common::bodyfile(&[
+        format!("{}|/bin/true|1|r/rrwxr-xr-x|0|0|10|-1|1662035696|-1|-1", GOOD),
+        format!("{}|/tmp/dropper|2|r/rrwxr-xr-x|0|0|10|-1|1662035697|-1|-1", BAD),
+        "0|/tmp/unknown|3|r/rrw-r--r--|0|0|10|-1|1662035698|-1|-1".to_owned(),
+    ])
+}
+
+fn md5_set(hashes: &str) -> Md5Set {
+    Md5Set::from_reader(hashes.as_bytes()).unwrap()
+}
+
+fn options() -> RunOptions {
+    RunOptions {
+        hash_filter: HashFilterRules::new(md5_set(GOOD), md5_set(BAD)),
+        ..Default::default()
+    }
+}
+
+/// passes the sample bodyfile through a [`BodyfileDecoder`] and a
+/// [`HashFilter`], and returns all lines which leave the filter
+fn filtered_lines(options: &RunOptions) -> Vec<BodyfileLine> {
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let mut filter = HashFilter::with_receiver(decoder.get_receiver(), options.clone());
+    let lines = filter.get_receiver();
+    for line in sample_bodyfile().lines() {
+        tx.send(line.to_owned()).unwrap();
+    }
+    drop(tx);
+
+    let lines = lines.iter().collect();
+    decoder.join().unwrap().unwrap();
+    filter.join().unwrap().unwrap();
+    lines
+}
+
+#[test]
+fn test_pipeline() {
+    let lines: Vec<(String, bool)> = filtered_lines(&options())
+        .iter()
+        .map(|line| (line.get_name().to_owned(), line.is_known_bad()))
+        .collect();
+    assert_eq!(
+        lines,
+        vec![("/tmp/dropper".to_owned(), true), ("/tmp/unknown".to_owned(), false)]
+    );
+
+    // without any hash sets, all lines pass unchanged
+    let lines = filtered_lines(&RunOptions::default());
+    assert_eq!(lines.len(), 3);
+    assert!(lines.iter().all(|line| !line.is_known_bad()));
+}
+
+#[test]
+fn test_timeline() {
+    // the marks must survive sorting on disk
+    for memory_limit in [None, Some(1)].iter().copied() {
+        let mut options = options();
+        options.sort_options.memory_limit = memory_limit;
+
+        let lines = filtered_lines(&options);
+        let output = CsvOutput::new(Tz::UTC, Tz::UTC).with_columns(vec![CsvColumn::Name, CsvColumn::KnownBad]);
+        assert_eq!(
+            common::write_timeline(Timeline::new(options).with_lines(lines, None), &output),
+            vec!["/tmp/dropper,known-bad", "/tmp/unknown,"]
+        );
+    }
+}
+
+#[test]
+fn test_txt() {
+    let timeline = Timeline::new(options()).with_lines(filtered_lines(&options()), None);
+    let lines = common::write_timeline(timeline, &TxtOutput::new(Tz::UTC, Tz::UTC).with_known_bad_column());
+    assert!(lines[0].ends_with(" 2 known-bad /tmp/dropper"), "unexpected line: '{}'", lines[0]);
+    assert!(lines[1].ends_with(" 3           /tmp/unknown"), "unexpected line: '{}'", lines[1]);
+}
+
+#[cfg(feature = "elastic")]
+#[test]
+fn test_json_tags() {
+    let options = options();
+    let buffer = SharedBuffer::new();
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let mut filter = HashFilter::with_receiver(decoder.get_receiver(), options.clone());
+    let mut sorter = JsonSorter::with_receiver(filter.get_receiver(), options)
+        .with_output_file(OutputFile::from_writer(buffer.clone()));
+    sorter.run();
+    for line in sample_bodyfile().lines() {
+        tx.send(line.to_owned()).unwrap();
+    }
+    drop(tx);
+    decoder.join().unwrap().unwrap();
+    filter.join().unwrap().unwrap();
+    sorter.join().unwrap().unwrap();
+
+    let documents: Vec<serde_json::Value> = String::from_utf8(buffer.contents())
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    let documents: Vec<(&str, bool)> = documents
+        .iter()
+        .map(|d| {
+            let tags = d["tags"].as_array().unwrap();
+            (d["file"]["path"].as_str().unwrap(), tags.iter().any(|t| t == "known-bad"))
+        })
+        .collect();
+    assert_eq!(documents, vec![("/tmp/dropper", true), ("/tmp/unknown", false)]);
+}
+
+#[test]
+fn test_options() {
+    let mut known_good = tempfile::NamedTempFile::new().unwrap();
+    writeln!(known_good, "\"SHA-1\",\"MD5\",\"CRC32\",\"FileName\"").unwrap();
+    writeln!(known_good, "\"DA39A3EE5E6B4B0D3255BFEF95601890AFD80709\",\"{}\",\"00000000\",\"empty\"", GOOD.to_uppercase()).unwrap();
+    let mut known_bad = tempfile::NamedTempFile::new().unwrap();
+    writeln!(known_bad, "{}  dropper", BAD).unwrap();
+
+    let app = common::application(&[
+        "--known-good",
+        known_good.path().to_str().unwrap(),
+        "--known-bad",
+        known_bad.path().to_str().unwrap(),
+    ]);
+    assert!(app.is_ok());
+
+    // JSON documents do not contain any hashes
+    #[cfg(feature = "elastic")]
+    {
+        assert_eq!(
+            common::application(&["-I", "json", "--known-bad", known_bad.path().to_str().unwrap()]).err(),
+            Some(ConfigError::ConflictingOptions("input_format", "hash_filter"))
+        );
+    }
+
+    writeln!(known_bad, "not a hash").unwrap();
+    assert!(Cli::try_parse_from(["mactime2", "--known-bad", known_bad.path().to_str().unwrap()]).is_err());
+    assert!(Cli::try_parse_from(["mactime2", "--known-good", "/nonexistent/NSRLFile.txt"]).is_err());
+}
@@ -0,0 +1,53 @@
+mod common;
+
+use chrono_tz::Tz;
+use libmactime2::*;
+
+// The expected files must be the output of `mactime` from The Sleuth Kit.
+// tests/data/mactime_compat.sh creates them from the bodyfile, and must be
+// run again whenever the bodyfile changes.
+//
+// Note that `mactime` still writes entries for timestamps of `0`, but it
+// displays them like `Xxx Xxx 00 0000 00:00:00` instead of the epoch.
+const BODYFILE: &str = include_str!("data/mactime_compat.bodyfile");
+
+fn write_timeline(output: &dyn Mactime2Writer) -> Vec<String> {
+    common::write_timeline(Timeline::from_reader(BODYFILE.as_bytes()), output)
+}
+
+#[test]
+fn test_text() {
+    let output = MactimeOutput::new(Tz::UTC, Tz::UTC);
+    assert_eq!(write_timeline(&output), include_str!("data/mactime_compat.txt").lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_delimited() {
+    let output = MactimeOutput::new(Tz::UTC, Tz::UTC).delimited();
+    assert_eq!(write_timeline(&output), include_str!("data/mactime_compat.csv").lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_iso8601() {
+    // ISO 8601 dates are always displayed in UTC
+    let output = MactimeOutput::new(Tz::UTC, Tz::Europe__Berlin).with_dates(MactimeDates::Iso8601);
+    assert_eq!(write_timeline(&output), include_str!("data/mactime_compat_iso8601.txt").lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_month_number() {
+    let output = MactimeOutput::new(Tz::UTC, Tz::Europe__Berlin).with_dates(MactimeDates::Numbers);
+    assert_eq!(write_timeline(&output), include_str!("data/mactime_compat_month_berlin.txt").lines().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_options() {
+    // the date options of mactime cannot be used with other formats
+    let result = Mactime2Application::builder()
+        .format(OutputFormat::CSV)
+        .mactime_dates(MactimeDates::Iso8601)
+        .build();
+    assert!(matches!(result, Err(ConfigError::MissingOption("mactime_dates", "mactime_compat"))));
+
+    assert!(common::application(&["-F", "mactime-compat", "-d", "-y"]).is_ok());
+}
@@ -0,0 +1,64 @@
+mod common;
+
+use chrono_tz::Tz;
+use libmactime2::*;
+
+fn sample_bodyfile() -> String {
+    common::bodyfile(&[
+        "0|/tmp/epoch|1|r/rrw-r--r--|0|0|10|0|0|0|0",
+        "0|/tmp/no_timestamps|2|r/rrw-r--r--|0|0|10|-1|-1|-1|-1",
+        "0|/tmp/fat|3|r/rrw-r--r--|0|0|10|-1|-315619200|-1|-1",
+        "0|/tmp/before_epoch|4|r/rrw-r--r--|0|0|10|-86400|-86400.25|-1|-1",
+    ])
+}
+
+fn write_timeline(options: RunOptions, output: &dyn Mactime2Writer) -> Vec<String> {
+    let timeline = Timeline::new(options).with_reader(std::io::Cursor::new(sample_bodyfile()), None);
+    common::write_timeline(timeline, output)
+}
+
+#[test]
+fn test_before_1970() {
+    for memory_limit in [None, Some(1)].iter().copied() {
+        let options = RunOptions {
+            sort_options: SortOptions {
+                memory_limit,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let lines = write_timeline(options, &CsvOutput::new(Tz::UTC, Tz::UTC));
+        assert_eq!(
+            lines,
+            vec![
+                "0000-00-00T00:00:00+00:00,10,....,r/rrw-r--r--,0,0,2,/tmp/no_timestamps",
+                "1960-01-01T00:00:00+00:00,10,m...,r/rrw-r--r--,0,0,3,/tmp/fat",
+                "1969-12-30T23:59:59+00:00,10,m...,r/rrw-r--r--,0,0,4,/tmp/before_epoch",
+                "1969-12-31T00:00:00+00:00,10,.a..,r/rrw-r--r--,0,0,4,/tmp/before_epoch",
+                "1970-01-01T00:00:00+00:00,10,macb,r/rrw-r--r--,0,0,1,/tmp/epoch",
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_placeholder() {
+    // the placeholder is padded, so that all columns are aligned
+    let output = TxtOutput::new(Tz::UTC, Tz::UTC).with_placeholder("n/a");
+    let lines = write_timeline(RunOptions::default(), &output);
+    assert!(lines[0].starts_with("n/a "), "unexpected line: '{}'", lines[0]);
+    assert_eq!(lines[0].find(" 10 "), lines[1].find(" 10 "));
+}
+
+#[test]
+fn test_statistics() {
+    let report = ParseReport::new();
+    let options = RunOptions {
+        report: report.clone(),
+        ..Default::default()
+    };
+    let lines = write_timeline(options, &CsvOutput::new(Tz::UTC, Tz::UTC));
+    assert_eq!(lines.len(), 5);
+    assert_eq!(report.statistics().parsed_lines, 4);
+    assert_eq!(report.statistics().lines_without_timestamps, 1);
+}
@@ -0,0 +1,99 @@
+use libmactime2::*;
+use std::io::{self, Write};
+use std::sync::mpsc::{self, Receiver, Sender};
+
+fn sample_lines() -> Vec<String> {
+    vec![
+        ::bodyfile::Bodyfile3Line::new()
+            .with_name("/etc/passwd")
+            .with_mtime(1661772116)
+            .with_atime(1661774614)
+            .to_string(),
+        ::bodyfile::Bodyfile3Line::new()
+            .with_name("/etc/shadow")
+            .with_ctime(1661772000)
+            .to_string(),
+    ]
+}
+
+#[cfg(feature = "elastic")]
+#[test]
+fn test_json_into_memory() {
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let options = RunOptions::default();
+
+    let buffer = SharedBuffer::new();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let mut sorter = JsonSorter::with_receiver(decoder.get_receiver(), options)
+        .with_output_file(OutputFile::from_writer(buffer.clone()));
+    sorter.run();
+
+    for line in sample_lines() {
+        tx.send(line).unwrap();
+    }
+    drop(tx);
+    decoder.join().unwrap().unwrap();
+    sorter.join().unwrap().unwrap();
+
+    let output = String::from_utf8(buffer.contents()).unwrap();
+    let documents: Vec<serde_json::Value> = output
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect();
+    assert_eq!(documents.len(), 3);
+    assert_eq!(documents[0]["file"]["path"], "/etc/shadow");
+    assert_eq!(documents[1]["file"]["path"], "/etc/passwd");
+    assert_eq!(documents[2]["file"]["path"], "/etc/passwd");
+}
+
+/// a writer which behaves like a pipe whose reader has exited
+struct ClosedPipe;
+
+impl Write for ClosedPipe {
+    fn write(&mut self, _buf: &[u8]) -> io::Result<usize> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Err(io::ErrorKind::BrokenPipe.into())
+    }
+}
+
+#[test]
+fn test_broken_pipe() {
+    for convert_only in [false, true].iter().copied() {
+        let options = RunOptions {
+            sort_options: SortOptions {
+                convert_only,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+
+        let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+        let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+        let mut sorter = BodyfileSorter::default()
+            .with_receiver(decoder.get_receiver(), options.clone())
+            .with_output(Box::new(TxtOutput::new(chrono_tz::Tz::UTC, chrono_tz::Tz::UTC)))
+            .with_output_file(OutputFile::from_writer(ClosedPipe));
+        sorter.run();
+        for line in sample_lines() {
+            tx.send(line).unwrap();
+        }
+        drop(tx);
+        decoder.join().unwrap().unwrap();
+        assert!(matches!(sorter.join().unwrap(), Err(MactimeError::BrokenPipe)));
+
+        let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+        let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+        let mut sorter = JsonSorter::with_receiver(decoder.get_receiver(), options)
+            .with_output_file(OutputFile::from_writer(ClosedPipe));
+        sorter.run();
+        for line in sample_lines() {
+            tx.send(line).unwrap();
+        }
+        drop(tx);
+        decoder.join().unwrap().unwrap();
+        assert!(matches!(sorter.join().unwrap(), Err(MactimeError::BrokenPipe)));
+    }
+}
@@ -0,0 +1,61 @@
+use libmactime2::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+#[test]
+fn test_parallel_decoder() {
+    let lines: Vec<String> = (0..10000)
+        .map(|i| match i % 100 {
+            0 => "# comment".to_owned(),
+            1 => "invalid line".to_owned(),
+            _ => ::bodyfile::Bodyfile3Line::new()
+                .with_name(&format!("/tmp/sample_{}", i))
+                .with_mtime(i)
+                .to_string(),
+        })
+        .collect();
+
+    let sequential = decode(&lines, 1);
+    let parallel = decode(&lines, 4);
+
+    assert_eq!(sequential.len(), 9800);
+    assert_eq!(sequential, parallel);
+}
+
+fn decode(lines: &[String], threads: usize) -> Vec<String> {
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let options = RunOptions {
+        threads,
+        ..Default::default()
+    };
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options);
+    let receiver = decoder.get_receiver();
+
+    for line in lines {
+        tx.send(line.clone()).unwrap();
+    }
+    drop(tx);
+
+    let decoded = receiver.iter().map(|l| l.to_string()).collect();
+    decoder.join().unwrap().unwrap();
+    decoded
+}
+
+#[test]
+fn test_bounded_output() {
+    // a decoder whose output is not read stops as soon as its channel is full
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    for i in 0..3 * CHANNEL_CAPACITY {
+        tx.send(::bodyfile::Bodyfile3Line::new().with_name(&format!("/tmp/{}", i)).with_mtime(1).to_string())
+            .unwrap();
+    }
+    drop(tx);
+
+    let options = RunOptions::default();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let receiver = decoder.get_receiver();
+    std::thread::sleep(std::time::Duration::from_millis(200));
+    assert!(options.report.statistics().total_lines <= CHANNEL_CAPACITY + 1);
+
+    assert_eq!(receiver.iter().count(), 3 * CHANNEL_CAPACITY);
+    decoder.join().unwrap().unwrap();
+}
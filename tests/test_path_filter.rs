@@ -0,0 +1,30 @@
+use libmactime2::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+#[test]
+fn test_path_filter() {
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+
+    let options = RunOptions {
+        path_filter: PathFilterRules::default()
+            .with_exclude("/proc".parse().unwrap())
+            .with_exclude("re:/Cache/".parse().unwrap()),
+        ..Default::default()
+    };
+
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let mut filter = PathFilter::with_receiver(decoder.get_receiver(), options);
+    let names = filter.get_receiver();
+
+    for name in ["/proc/1/maps", "/etc/passwd", "/home/user/Cache/f_000001", "/procfs"] {
+        let bf = ::bodyfile::Bodyfile3Line::new().with_name(name).with_mtime(1661772116);
+        tx.send(bf.to_string()).unwrap();
+    }
+    drop(tx);
+
+    let names: Vec<String> = names.iter().map(|l| l.get_name().to_owned()).collect();
+    decoder.join().unwrap().unwrap();
+    filter.join().unwrap().unwrap();
+
+    assert_eq!(names, vec!["/etc/passwd", "/procfs"]);
+}
@@ -0,0 +1,92 @@
+mod common;
+
+use libmactime2::*;
+use chrono_tz::Tz;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+fn sample_bodyfile() -> String {
+    common::bodyfile(&[
+        "0|/tmp/c|1|r/rrw-r--r--|0|0|10|-1|11|-1|-1",
+        "0|/tmp/b|2|r/rrw-r--r--|0|0|10|-1|10.7|-1|-1",
+        "0|/tmp/a|3|r/rrw-r--r--|0|0|10|-1|10.5|-1|-1",
+        "0|/tmp/d|4|r/rrw-r--r--|0|0|10|10.123456789|10.123456789|-1|-1",
+    ])
+}
+
+fn write_timeline(output: &dyn Mactime2Writer) -> Vec<String> {
+    common::write_timeline(Timeline::from_reader(std::io::Cursor::new(sample_bodyfile())), output)
+}
+
+#[test]
+fn test_sort_fractions() {
+    let entries: Vec<(String, String)> = Timeline::from_reader(std::io::Cursor::new(sample_bodyfile()))
+        .sorted()
+        .unwrap()
+        .map(Result::unwrap)
+        .map(|(ts, entry)| (ts.unwrap().to_string(), entry.line.get_name().to_owned()))
+        .collect();
+    assert_eq!(
+        entries,
+        vec![
+            ("10.123456789".to_owned(), "/tmp/d".to_owned()),
+            ("10.5".to_owned(), "/tmp/a".to_owned()),
+            ("10.7".to_owned(), "/tmp/b".to_owned()),
+            ("11".to_owned(), "/tmp/c".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn test_txt_precision() {
+    // entries within the same second share a single timestamp
+    let lines = write_timeline(&TxtOutput::new(Tz::UTC, Tz::UTC));
+    assert!(lines[0].starts_with("1970-01-01T00:00:10+00:00 "));
+    assert!(lines[1].starts_with("                          "));
+    assert!(lines[3].starts_with("1970-01-01T00:00:11+00:00 "));
+
+    // with a higher precision, the blank timestamp has the same width
+    let lines = write_timeline(&TxtOutput::new(Tz::UTC, Tz::UTC).with_precision(TimestampPrecision::Millis));
+    assert!(lines[0].starts_with("1970-01-01T00:00:10.123+00:00 "));
+    assert!(lines[1].starts_with("1970-01-01T00:00:10.500+00:00 "));
+    assert!(lines[2].starts_with("1970-01-01T00:00:10.700+00:00 "));
+    assert_eq!(lines[1].find(" 10 "), lines[0].find(" 10 "));
+}
+
+#[cfg(feature = "elastic")]
+#[test]
+fn test_json_roundtrip() {
+    let options = RunOptions {
+        precision: TimestampPrecision::Nanos,
+        ..Default::default()
+    };
+
+    let buffer = SharedBuffer::new();
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
+    let mut sorter = JsonSorter::with_receiver(decoder.get_receiver(), options.clone())
+        .with_output_file(OutputFile::from_writer(buffer.clone()));
+    sorter.run();
+    for line in sample_bodyfile().lines() {
+        tx.send(line.to_owned()).unwrap();
+    }
+    drop(tx);
+    decoder.join().unwrap().unwrap();
+    sorter.join().unwrap().unwrap();
+
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let mut decoder = JsonDecoder::with_receiver(rx, options);
+    let receiver = decoder.get_receiver();
+    for line in String::from_utf8(buffer.contents()).unwrap().lines() {
+        tx.send(line.to_owned()).unwrap();
+    }
+    drop(tx);
+    let mut lines: Vec<String> = receiver.iter().map(|l| l.to_string()).collect();
+    decoder.join().unwrap().unwrap();
+
+    lines.sort();
+    lines.dedup();
+    let names: Vec<&str> = lines.iter().map(|l| l.split('|').nth(1).unwrap()).collect();
+    assert_eq!(names, vec!["/tmp/a", "/tmp/b", "/tmp/c", "/tmp/d"]);
+    let timestamps: Vec<&str> = lines.iter().map(|l| l.split('|').nth(8).unwrap()).collect();
+    assert_eq!(timestamps, vec!["10.5", "10.7", "11", "10.123456789"]);
+}
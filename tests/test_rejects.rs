@@ -0,0 +1,66 @@
+use libmactime2::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+fn sample_lines() -> Vec<String> {
+    (1..=3000)
+        .map(|i| match i % 1000 {
+            0 => format!("invalid line {}", i),
+            1 => "# comment".to_owned(),
+            2 => ::bodyfile::Bodyfile3Line::new().with_name("/tmp/no_timestamps").to_string(),
+            _ => ::bodyfile::Bodyfile3Line::new()
+                .with_name(&format!("/tmp/sample_{}", i))
+                .with_mtime(i)
+                .to_string(),
+        })
+        .collect()
+}
+
+#[test]
+fn test_rejects() {
+    for threads in [1, 4].iter().copied() {
+        let buffer = SharedBuffer::new();
+        let report = ParseReport::with_rejects(OutputFile::from_writer(buffer.clone())).unwrap();
+        let options = RunOptions {
+            threads,
+            report: report.for_input("sample.bodyfile"),
+            ..Default::default()
+        };
+
+        let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+        let mut decoder = BodyfileDecoder::with_receiver(rx, options);
+        let receiver = decoder.get_receiver();
+        for line in sample_lines() {
+            tx.send(line).unwrap();
+        }
+        drop(tx);
+        assert_eq!(receiver.iter().count(), 2994);
+        decoder.join().unwrap().unwrap();
+        report.finish().unwrap();
+
+        assert_eq!(
+            report.statistics(),
+            ParseStatistics {
+                total_lines: 3000,
+                parsed_lines: 2994,
+                rejected_lines: 3,
+                lines_without_timestamps: 3,
+                ambiguous_timestamps: 0,
+                nonexistent_timestamps: 0,
+            }
+        );
+
+        let rejects = String::from_utf8(buffer.contents()).unwrap();
+        let mut rejects: Vec<Vec<&str>> = rejects.lines().map(|l| l.split('\t').collect()).collect();
+
+        // worker threads write their rejected lines in no particular order
+        rejects[1..].sort_by_key(|reject| reject[1].parse::<usize>().unwrap());
+        assert_eq!(rejects.len(), 4);
+        assert_eq!(rejects[0], vec!["input", "line", "error", "content"]);
+        for (idx, reject) in rejects[1..].iter().enumerate() {
+            let line_number = (idx + 1) * 1000;
+            assert_eq!(reject[0], "sample.bodyfile");
+            assert_eq!(reject[1], line_number.to_string());
+            assert_eq!(reject[3], format!("invalid line {}", line_number));
+        }
+    }
+}
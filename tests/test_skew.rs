@@ -0,0 +1,108 @@
+mod common;
+
+use chrono_tz::Tz;
+use libmactime2::*;
+use std::sync::mpsc::{self, Receiver, Sender};
+
+const HOST1: &str = "0|/tmp/a|1|r/rrw-r--r--|0|0|10|-1|1662035696|-1|-1\n";
+const HOST2: &str = "0|/tmp/b|2|r/rrw-r--r--|0|0|10|-1|1662035700|-1|-1\n";
+
+fn skew(skew: &str) -> ClockSkew {
+    skew.parse().unwrap()
+}
+
+fn write_timeline(timeline: Timeline, output: CsvOutput) -> Vec<String> {
+    common::write_timeline(timeline, &output.with_skew_column())
+}
+
+/// parses the lines of a bodyfile, like every input is parsed by mactime2
+fn decode(bodyfile: &str, skew: Option<ClockSkew>) -> Vec<BodyfileLine> {
+    let options = RunOptions {
+        skew,
+        ..Default::default()
+    };
+    let (tx, rx): (Sender<String>, Receiver<String>) = mpsc::channel();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options);
+    let receiver = decoder.get_receiver();
+    for line in bodyfile.lines() {
+        tx.send(line.to_owned()).unwrap();
+    }
+    drop(tx);
+    let lines = receiver.iter().collect();
+    decoder.join().unwrap().unwrap();
+    lines
+}
+
+#[test]
+fn test_skew_per_input() {
+    for memory_limit in [None, Some(1)].iter().copied() {
+        let options = RunOptions {
+            sort_options: SortOptions {
+                memory_limit,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let timeline = Timeline::new(options)
+            .with_lines(decode(HOST1, None), Some("host1"))
+            .with_lines(decode(HOST2, Some(skew("-00:00:10"))), Some("host2"));
+        assert_eq!(
+            write_timeline(timeline, CsvOutput::new(Tz::UTC, Tz::UTC).with_source_column()),
+            vec![
+                "2022-09-01T12:34:50+00:00,10,m...,r/rrw-r--r--,0,0,2,/tmp/b,host2,-00:00:10",
+                "2022-09-01T12:34:56+00:00,10,m...,r/rrw-r--r--,0,0,1,/tmp/a,host1,+00:00:00",
+            ]
+        );
+    }
+}
+
+#[test]
+fn test_skew_before_timezone() {
+    // 02:30 did not exist in Berlin, but the clock was one hour ahead
+    let options = RunOptions {
+        src_zone: Tz::Europe__Berlin,
+        skew: Some(skew("-01:00:00")),
+        ..Default::default()
+    };
+    let bodyfile = "0|/tmp/gap|1|r/rrw-r--r--|0|0|10|-1|1648348200|-1|-1\n";
+    let lines = write_timeline(
+        Timeline::new(options).with_reader(bodyfile.as_bytes(), None),
+        CsvOutput::new(Tz::Europe__Berlin, Tz::UTC),
+    );
+    assert_eq!(lines, vec!["2022-03-27T00:30:00+00:00,10,m...,r/rrw-r--r--,0,0,1,/tmp/gap,-01:00:00"]);
+}
+
+#[test]
+fn test_duplicate_skew() {
+    let spec = |s: &str| s.parse::<SkewSpec>().unwrap();
+    let result = Mactime2Application::builder()
+        .skew(vec![spec("host1=+00:01:00"), spec("+00:02:00"), spec("host1=+00:03:00")])
+        .build();
+    assert!(matches!(result, Err(ConfigError::DuplicateSkew(label)) if label == "host1"));
+
+    let result = Mactime2Application::builder()
+        .skew(vec![spec("host1=+00:01:00"), spec("+00:02:00")])
+        .build();
+    assert!(result.is_ok());
+}
+
+#[cfg(feature = "elastic")]
+#[test]
+fn test_json_label() {
+    let buffer = SharedBuffer::new();
+    let (tx, rx): (Sender<BodyfileLine>, Receiver<BodyfileLine>) = mpsc::channel();
+    let mut sorter = JsonSorter::new(RunOptions::default())
+        .with_input(rx, Some("host2"))
+        .with_output_file(OutputFile::from_writer(buffer.clone()));
+    sorter.run();
+    for line in decode(HOST2, Some(skew("+00:00:04"))) {
+        tx.send(line).unwrap();
+    }
+    drop(tx);
+    sorter.join().unwrap().unwrap();
+
+    let document: serde_json::Value = serde_json::from_slice(&buffer.contents()).unwrap();
+    assert_eq!(document["@timestamp"], 1662035704000i64);
+    assert_eq!(document["labels"]["source"], "host2");
+    assert_eq!(document["labels"]["skew"], "+00:00:04");
+}
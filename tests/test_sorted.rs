@@ -1,6 +1,5 @@
 use libmactime2::*;
 use std::sync::mpsc::{self, Sender, Receiver};
-use std::cell::RefCell;
 
 #[macro_use]
 extern crate more_asserts;
@@ -11,13 +10,16 @@ fn test_sorted() {
 
     let options = RunOptions {
         strict_mode: false,
-        src_zone: chrono_tz::Tz::UTC
+        src_zone: chrono_tz::Tz::UTC,
+        ..Default::default()
     };
 
-    let mut decoder = BodyfileDecoder::with_receiver(rx, options);
+    let buffer = SharedBuffer::new();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, options.clone());
     let mut sorter = BodyfileSorter::default()
         .with_receiver(decoder.get_receiver(), options)
-        .with_output(Box::new(EventCatcher::new()));
+        .with_output(Box::new(CsvOutput::new(chrono_tz::Tz::UTC, chrono_tz::Tz::UTC)))
+        .with_output_file(OutputFile::from_writer(buffer.clone()));
 
     sorter.run();
     for day in 0..364 {
@@ -117,31 +119,18 @@ fn test_sorted() {
     }
     drop(tx);
 
-    decoder.join().unwrap();
-    let _ = sorter.join().unwrap();
+    decoder.join().unwrap().unwrap();
+    sorter.join().unwrap().unwrap();
+
+    // all timestamps are in UTC and after 1970, so they can be compared as strings
+    let output = String::from_utf8(buffer.contents()).unwrap();
+    let timestamps: Vec<&str> = output.lines().map(|l| l.split(',').next().unwrap()).collect();
+    assert_eq!(timestamps.len(), 364 * 23 * 32);
+    for pair in timestamps.windows(2) {
+        assert_le!(pair[0], pair[1]);
+    }
 }
 
 fn random_ts() -> i64 {
     rand::random::<u32>() as i64
 }
-
-struct EventCatcher {
-    last_timestamp: RefCell<i64>,
-}
-
-impl EventCatcher {
-    pub fn new () -> Self {
-        Self {
-            last_timestamp: RefCell::new(-1)
-        }
-    }
-}
-
-impl Mactime2Writer for EventCatcher {
-    fn fmt(&self, timestamp: &i64, _entry: &ListEntry) -> String {
-        assert_le!(*self.last_timestamp.borrow(), *timestamp);
-
-        *self.last_timestamp.borrow_mut() = *timestamp;
-        "".to_owned()
-    }
-}
\ No newline at end of file
@@ -0,0 +1,176 @@
+#![cfg(feature = "sqlite")]
+
+mod common;
+
+use chrono_tz::Tz;
+use libmactime2::*;
+use rusqlite::Connection;
+use std::io::Write;
+
+const BODYFILE: &str = "\
+d41d8cd98f00b204e9800998ecf8427e|/tmp/a|1|r/rrw-r--r--|0|0|10|1662035697|1662035696|-1|1662035696
+0|/tmp/b c|2|r/rrwxr-xr-x|1000|100|20|-1|-1|-1|-1
+";
+
+/// timestamp, date, MACB flags as text and as booleans, and the metadata of the file
+type Row = (Option<i64>, String, String, [bool; 4], (i64, String, i64, i64, String, String, String));
+
+fn rows(connection: &Connection) -> Vec<Row> {
+    let mut select = connection
+        .prepare(
+            "SELECT timestamp, date, macb, modified, accessed, changed, born, size, mode, uid, gid, inode, name, md5 \
+             FROM timeline ORDER BY id",
+        )
+        .unwrap();
+    select
+        .query_map([], |row| {
+            Ok((
+                row.get(0)?,
+                row.get(1)?,
+                row.get(2)?,
+                [row.get(3)?, row.get(4)?, row.get(5)?, row.get(6)?],
+                (row.get(7)?, row.get(8)?, row.get(9)?, row.get(10)?, row.get(11)?, row.get(12)?, row.get(13)?),
+            ))
+        })
+        .unwrap()
+        .map(Result::unwrap)
+        .collect()
+}
+
+fn metadata(connection: &Connection, key: &str) -> Vec<String> {
+    let mut select = connection.prepare("SELECT value FROM metadata WHERE key = ?1").unwrap();
+    select.query_map([key], |row| row.get(0)).unwrap().map(Result::unwrap).collect()
+}
+
+#[test]
+fn test_rows() {
+    let mut database = SqliteOutput::from_connection(Connection::open_in_memory().unwrap(), Tz::UTC, Tz::UTC).unwrap();
+    let entries = Timeline::new(RunOptions::default())
+        .with_reader(BODYFILE.as_bytes(), None)
+        .ordered()
+        .unwrap();
+    database.write(entries).unwrap();
+
+    let file_a = (
+        10,
+        "r/rrw-r--r--".to_owned(),
+        0,
+        0,
+        "1".to_owned(),
+        "/tmp/a".to_owned(),
+        "d41d8cd98f00b204e9800998ecf8427e".to_owned(),
+    );
+    let file_b = (
+        20,
+        "r/rrwxr-xr-x".to_owned(),
+        1000,
+        100,
+        "2".to_owned(),
+        "/tmp/b c".to_owned(),
+        "0".to_owned(),
+    );
+    // entries without timestamps come first, like in all timelines
+    assert_eq!(
+        rows(database.connection()),
+        vec![
+            (
+                None,
+                "0000-00-00T00:00:00+00:00".to_owned(),
+                "....".to_owned(),
+                [false; 4],
+                file_b
+            ),
+            (
+                Some(1662035696),
+                "2022-09-01T12:34:56+00:00".to_owned(),
+                "m..b".to_owned(),
+                [true, false, false, true],
+                file_a.clone()
+            ),
+            (
+                Some(1662035697),
+                "2022-09-01T12:34:57+00:00".to_owned(),
+                ".a..".to_owned(),
+                [false, true, false, false],
+                file_a
+            ),
+        ]
+    );
+}
+
+#[test]
+fn test_local_time() {
+    // the epoch refers to the real point in time, the date is displayed in
+    // the destination timezone
+    let mut database =
+        SqliteOutput::from_connection(Connection::open_in_memory().unwrap(), Tz::Europe__Berlin, Tz::America__New_York)
+            .unwrap();
+    let entries = Timeline::new(RunOptions {
+        src_zone: Tz::Europe__Berlin,
+        ..Default::default()
+    })
+    .with_reader("0|/tmp/a|1|r/rrw-r--r--|0|0|10|-1|1662035696|-1|-1\n".as_bytes(), None)
+    .ordered()
+    .unwrap();
+    database.write(entries).unwrap();
+
+    let rows = rows(database.connection());
+    assert_eq!(rows[0].0, Some(1662028496));
+    assert_eq!(rows[0].1, "2022-09-01T06:34:56-04:00");
+    assert_eq!(metadata(database.connection(), "src_zone"), vec!["Europe/Berlin"]);
+    assert_eq!(metadata(database.connection(), "dst_zone"), vec!["America/New_York"]);
+}
+
+#[test]
+fn test_run() {
+    let dir = tempfile::tempdir().unwrap();
+    let bodyfile = dir.path().join("host1.bodyfile");
+    std::fs::File::create(&bodyfile).unwrap().write_all(BODYFILE.as_bytes()).unwrap();
+    let output = dir.path().join("timeline.db");
+
+    let app = Mactime2Application::builder()
+        .bodyfile(format!("host1={}", bodyfile.display()))
+        .format(OutputFormat::SQLITE)
+        .output(output.clone())
+        .build()
+        .unwrap();
+    app.run().unwrap();
+
+    let connection = Connection::open(&output).unwrap();
+    assert_eq!(rows(&connection).len(), 3);
+    let sources: Vec<String> = connection
+        .prepare("SELECT DISTINCT source FROM timeline")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(sources, vec!["host1"]);
+
+    let indexes: Vec<String> = connection
+        .prepare("SELECT name FROM sqlite_master WHERE type = 'index' AND tbl_name = 'timeline' ORDER BY name")
+        .unwrap()
+        .query_map([], |row| row.get(0))
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+    assert_eq!(indexes, vec!["timeline_name", "timeline_timestamp"]);
+
+    assert_eq!(metadata(&connection, "input"), vec![bodyfile.display().to_string()]);
+    assert_eq!(metadata(&connection, "label"), vec!["host1"]);
+    assert_eq!(metadata(&connection, "version"), vec![env!("CARGO_PKG_VERSION")]);
+
+    // no temporary files are left behind
+    assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 2);
+}
+
+#[test]
+fn test_options() {
+    assert_eq!(
+        Mactime2Application::builder().format(OutputFormat::SQLITE).build().err(),
+        Some(ConfigError::MissingOption("sqlite", "output"))
+    );
+
+    assert!(common::application(&["-F", "sqlite"]).is_err());
+    assert!(common::application(&["-F", "sqlite", "-o", "timeline.db"]).is_ok());
+}
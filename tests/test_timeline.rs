@@ -0,0 +1,127 @@
+use libmactime2::*;
+use std::sync::mpsc;
+
+fn bodyfile() -> String {
+    [
+        ::bodyfile::Bodyfile3Line::new()
+            .with_name("/etc/passwd")
+            .with_mtime(300)
+            .with_atime(100)
+            .with_ctime(300),
+        ::bodyfile::Bodyfile3Line::new()
+            .with_name("/etc/shadow")
+            .with_crtime(200),
+        ::bodyfile::Bodyfile3Line::new().with_name("/etc/group"),
+    ]
+    .iter()
+    .map(|l| format!("{}\n", l))
+    .collect::<Vec<_>>()
+    .join("# a comment\n")
+}
+
+fn collect<I>(entries: I) -> Vec<(Option<i64>, String, String)>
+where
+    I: Iterator<Item = Result<(Option<Timestamp>, ListEntry), MactimeError>>,
+{
+    entries
+        .map(Result::unwrap)
+        .map(|(ts, entry)| (ts.map(|ts| ts.secs()), entry.flags.to_string(), entry.line.get_name().to_owned()))
+        .collect()
+}
+
+#[test]
+fn test_sorted() {
+    let entries = collect(Timeline::from_reader(std::io::Cursor::new(bodyfile())).sorted().unwrap());
+    assert_eq!(
+        entries,
+        vec![
+            (None, "....".to_owned(), "/etc/group".to_owned()),
+            (Some(100), ".a..".to_owned(), "/etc/passwd".to_owned()),
+            (Some(200), "...b".to_owned(), "/etc/shadow".to_owned()),
+            (Some(300), "m.c.".to_owned(), "/etc/passwd".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn test_entries() {
+    let entries = collect(Timeline::from_reader(std::io::Cursor::new(bodyfile())).entries());
+    assert_eq!(
+        entries,
+        vec![
+            (Some(300), "m.c.".to_owned(), "/etc/passwd".to_owned()),
+            (Some(100), ".a..".to_owned(), "/etc/passwd".to_owned()),
+            (Some(200), "...b".to_owned(), "/etc/shadow".to_owned()),
+            (None, "....".to_owned(), "/etc/group".to_owned()),
+        ]
+    );
+}
+
+#[test]
+fn test_filters_and_labels() {
+    let options = RunOptions {
+        path_filter: PathFilterRules::default().with_exclude("/etc/shadow".parse().unwrap()),
+        macb_filter: MacbFilter::new(vec!["m".parse().unwrap()]),
+        ..Default::default()
+    };
+
+    let (tx, rx) = mpsc::channel();
+    tx.send(
+        ::bodyfile::Bodyfile3Line::new()
+            .with_name("/tmp/x")
+            .with_mtime(50),
+    )
+    .unwrap();
+    drop(tx);
+
+    let entries: Vec<(Option<Timestamp>, ListEntry)> = Timeline::new(options)
+        .with_reader(std::io::Cursor::new(bodyfile()), Some("host1"))
+        .with_lines(rx, Some("host2"))
+        .sorted()
+        .unwrap()
+        .map(Result::unwrap)
+        .collect();
+
+    let entries: Vec<(Option<i64>, &str, &str)> = entries
+        .iter()
+        .map(|(ts, e)| (ts.map(|ts| ts.secs()), e.line.get_name(), e.source.as_deref().unwrap()))
+        .collect();
+    assert_eq!(entries, vec![(Some(50), "/tmp/x", "host2"), (Some(300), "/etc/passwd", "host1")]);
+}
+
+#[test]
+fn test_same_as_sorter() {
+    let lines: Vec<String> = (0..2000)
+        .map(|i| {
+            ::bodyfile::Bodyfile3Line::new()
+                .with_name(&format!("/tmp/sample_{}", i))
+                .with_mtime((i * 7919) % 1000)
+                .with_atime((i * 104729) % 1000)
+                .to_string()
+        })
+        .collect();
+
+    let buffer = SharedBuffer::new();
+    let (tx, rx) = mpsc::channel();
+    let mut decoder = BodyfileDecoder::with_receiver(rx, RunOptions::default());
+    let mut sorter = BodyfileSorter::default()
+        .with_receiver(decoder.get_receiver(), RunOptions::default())
+        .with_output(Box::new(CsvOutput::new(chrono_tz::Tz::UTC, chrono_tz::Tz::UTC)))
+        .with_output_file(OutputFile::from_writer(buffer.clone()));
+    sorter.run();
+    for line in lines.iter() {
+        tx.send(line.clone()).unwrap();
+    }
+    drop(tx);
+    decoder.join().unwrap().unwrap();
+    sorter.join().unwrap().unwrap();
+
+    let output = CsvOutput::new(chrono_tz::Tz::UTC, chrono_tz::Tz::UTC);
+    let mut expected = String::new();
+    for entry in Timeline::from_reader(std::io::Cursor::new(lines.join("\n"))).sorted().unwrap() {
+        let (ts, entry) = entry.unwrap();
+        expected.push_str(&output.fmt(ts.as_ref(), &entry));
+        expected.push('\n');
+    }
+    assert_eq!(String::from_utf8(buffer.contents()).unwrap(), expected);
+}